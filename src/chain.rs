@@ -0,0 +1,230 @@
+//! Chains quantum tunnel relays between. Headers are read from a `SourceChain` and
+//! submitted to light clients hosted on `DestinationChain`s; `relay` connects them with
+//! channels, so the relayer does not depend on which kinds of chains, live or simulated,
+//! it relays between.
+
+use crate::client_state::ClientState;
+use crate::error::RelayError;
+use crate::monitoring::MonitoringEvent;
+use crate::report::HeaderRecord;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use futures::channel::oneshot;
+use futures::future::LocalBoxFuture;
+use std::time::Duration;
+
+/// Future of a handler, which runs until its chain fails or runs out of headers.
+pub type HandlerFuture = LocalBoxFuture<'static, Result<(), RelayError>>;
+
+/// Chain headers are read from.
+pub trait SourceChain {
+    /// Header, along with anything else light client needs to verify it.
+    type Header: Clone + Send + 'static;
+
+    /// Reads headers of the chain into `outchan`. Light client outcome of each of them
+    /// arrives on `monitoring_inchan`, so simulated chains can tell when they are done.
+    fn read_headers(
+        self: Box<Self>,
+        outchan: Sender<Self::Header>,
+        monitoring_inchan: Receiver<MonitoringEvent>,
+    ) -> HandlerFuture;
+
+    /// Whether the chain runs out of headers, as simulated chains do. Relaying is then
+    /// over once every such chain has read all its headers, rather than once any
+    /// handler stops.
+    fn is_finite(&self) -> bool {
+        false
+    }
+}
+
+/// Chain hosting light client of another chain.
+pub trait DestinationChain {
+    /// Header the light client ingests.
+    type Header: Send + 'static;
+
+    /// Updates light client `client_id`, or a new one if not given, with headers from
+    /// `inchan`, reporting outcome of each of them to `monitoring_outchan` and `report_outchan`.
+    fn update_client(
+        self: Box<Self>,
+        client_id: Option<String>,
+        inchan: Receiver<Self::Header>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> HandlerFuture;
+
+    /// Whether the chain is live, so its handler may have a submission in flight when
+    /// relaying stops, which it is given time to finish.
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+/// Live chain whose light clients can be inspected.
+pub trait ClientStateQuerier {
+    /// Trusting period of light clients hosted on the chain.
+    fn trusting_period(&self) -> Result<Duration, RelayError>;
+
+    /// State of light client `client_id`.
+    fn client_state(
+        &self,
+        client_id: String,
+    ) -> LocalBoxFuture<'static, Result<ClientState, RelayError>>;
+
+    /// Identifiers of all light clients hosted on the chain.
+    fn client_ids(&self) -> LocalBoxFuture<'static, Result<Vec<String>, RelayError>>;
+}
+
+/// Handlers relaying headers of one source chain.
+pub struct Relay {
+    /// Handler reading headers of the source chain.
+    pub source: HandlerFuture,
+    /// Whether the source chain runs out of headers, see `SourceChain::is_finite`.
+    pub finite: bool,
+    /// Handlers updating light clients, in order of destinations.
+    pub destinations: Vec<HandlerFuture>,
+    /// Whether each destination is a live chain, in order of destinations.
+    pub live: Vec<bool>,
+    /// Number of headers waiting for each destination, in order of destinations.
+    pub queues: Vec<Box<dyn Fn() -> usize + Send + Sync>>,
+}
+
+/// Connects `source` with light clients on `destinations`, each given with id of the
+/// client to update, or `None` to create one. Every header is passed to all destinations.
+pub fn relay<H: Clone + Send + 'static>(
+    source: Box<dyn SourceChain<Header = H>>,
+    destinations: Vec<(Box<dyn DestinationChain<Header = H>>, Option<String>)>,
+    report_outchan: Sender<HeaderRecord>,
+) -> Relay {
+    let (chan_tx, chan_rx) = unbounded();
+    let (monitoring_tx, monitoring_rx) = unbounded();
+    let inchans = if destinations.len() == 1 {
+        vec![chan_rx]
+    } else {
+        let (outchans, inchans): (Vec<_>, Vec<_>) =
+            destinations.iter().map(|_| unbounded()).unzip();
+        let _ = fan_out(chan_rx, outchans);
+        inchans
+    };
+
+    let mut relay = Relay {
+        finite: source.is_finite(),
+        source: source.read_headers(chan_tx, monitoring_rx),
+        destinations: vec![],
+        live: vec![],
+        queues: vec![],
+    };
+    for ((destination, client_id), inchan) in destinations.into_iter().zip(inchans) {
+        let queue = inchan.clone();
+        relay.queues.push(Box::new(move || queue.len()));
+        relay.live.push(destination.is_live());
+        relay.destinations.push(destination.update_client(
+            client_id,
+            inchan,
+            monitoring_tx.clone(),
+            report_outchan.clone(),
+        ));
+    }
+    relay
+}
+
+/// Passes every message received on `inchan` to all `outchans`, until `inchan` disconnects.
+/// Returned receiver resolves once every message has been passed on, so async callers
+/// can wait for it without blocking their thread.
+pub fn fan_out<T: Clone + Send + 'static>(
+    inchan: Receiver<T>,
+    outchans: Vec<Sender<T>>,
+) -> oneshot::Receiver<()> {
+    let (done_tx, done_rx) = oneshot::channel();
+    std::thread::spawn(move || {
+        for msg in inchan.iter() {
+            for outchan in &outchans {
+                // Destination whose handler is gone stops the whole relayer anyway.
+                let _ = outchan.send(msg.clone());
+            }
+        }
+        let _ = done_tx.send(());
+    });
+    done_rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{relay, DestinationChain, HandlerFuture, SourceChain};
+    use crate::error::RelayError;
+    use crate::monitoring::MonitoringEvent;
+    use crate::report::HeaderRecord;
+    use crossbeam_channel::{unbounded, Receiver, Sender};
+    use futures::future::FutureExt;
+
+    struct MockSource(Vec<u64>);
+
+    impl SourceChain for MockSource {
+        type Header = u64;
+
+        fn read_headers(
+            self: Box<Self>,
+            outchan: Sender<u64>,
+            _: Receiver<MonitoringEvent>,
+        ) -> HandlerFuture {
+            async move {
+                for header in self.0 {
+                    outchan.send(header).map_err(RelayError::disconnected)?;
+                }
+                Ok(())
+            }
+            .boxed_local()
+        }
+    }
+
+    struct MockDestination(Sender<(Option<String>, u64)>);
+
+    impl DestinationChain for MockDestination {
+        type Header = u64;
+
+        fn update_client(
+            self: Box<Self>,
+            client_id: Option<String>,
+            inchan: Receiver<u64>,
+            _: Sender<MonitoringEvent>,
+            _: Sender<HeaderRecord>,
+        ) -> HandlerFuture {
+            async move {
+                for header in inchan.iter() {
+                    self.0
+                        .send((client_id.clone(), header))
+                        .map_err(RelayError::disconnected)?;
+                }
+                Ok(())
+            }
+            .boxed_local()
+        }
+    }
+
+    #[test]
+    fn test_every_destination_receives_every_header() {
+        let (ingested_tx, ingested_rx) = unbounded();
+        let (report_tx, _report_rx) = unbounded();
+        let destination =
+            |tx| -> Box<dyn DestinationChain<Header = u64>> { Box::new(MockDestination(tx)) };
+        let relay = relay(
+            Box::new(MockSource(vec![1, 2, 3])),
+            vec![
+                (destination(ingested_tx.clone()), Some("a".to_owned())),
+                (destination(ingested_tx), None),
+            ],
+            report_tx,
+        );
+        futures::executor::block_on(async move {
+            relay.source.await.unwrap();
+            for destination in relay.destinations {
+                destination.await.unwrap();
+            }
+        });
+
+        let ingested: Vec<(Option<String>, u64)> = ingested_rx.try_iter().collect();
+        for header in 1..=3 {
+            assert!(ingested.contains(&(Some("a".to_owned()), header)));
+            assert!(ingested.contains(&(None, header)));
+        }
+        assert_eq!(ingested.len(), 6);
+    }
+}
@@ -0,0 +1,112 @@
+//! Choosing identifiers of new light clients, and keeping record of the
+//! clients created, so that they are not orphaned once logs are gone.
+
+use crate::config::ClientIdScheme;
+use crate::utils::{generate_client_id, to_string};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Every created client is appended to this file, one json object per line, unless
+/// `clients_file` of the chain hosting it says otherwise.
+pub const DEFAULT_CLIENTS_FILE: &str = "quantum_tunnel_clients.jsonl";
+
+/// Chooses id of a new client. `existing` are ids of clients already stored on
+/// the target chain.
+pub fn choose(
+    requested: Option<String>,
+    scheme: &ClientIdScheme,
+    counterparty_chain_id: &str,
+    existing: &[String],
+) -> Result<String, String> {
+    let taken = |id: &str| existing.iter().any(|e| e == id);
+    if let Some(id) = requested {
+        if taken(&id) {
+            return Err(format!("client id {} is already taken", id));
+        }
+        return Ok(id);
+    }
+    match scheme {
+        ClientIdScheme::Random => loop {
+            let id = generate_client_id();
+            if !taken(&id) {
+                return Ok(id);
+            }
+        },
+        ClientIdScheme::Counterparty => {
+            if counterparty_chain_id.is_empty() {
+                return Err("counterparty chain id is required to derive client id".to_string());
+            }
+            Ok((0..)
+                .map(|n| format!("{}-{}", counterparty_chain_id, n))
+                .find(|id| !taken(id))
+                .unwrap())
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ClientRecord<'a> {
+    chain: &'a str,
+    client_id: &'a str,
+    counterparty_chain_id: &'a str,
+    tx_hash: &'a str,
+    created_at: String,
+}
+
+/// Appends created client to `clients_file`.
+pub fn record(
+    clients_file: &str,
+    chain: &str,
+    client_id: &str,
+    counterparty_chain_id: &str,
+    tx_hash: &str,
+) -> Result<(), String> {
+    let record = ClientRecord {
+        chain,
+        client_id,
+        counterparty_chain_id,
+        tx_hash,
+        created_at: tendermint::Time::now().to_rfc3339(),
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(clients_file)
+        .map_err(to_string)?;
+    writeln!(
+        file,
+        "{}",
+        serde_json::to_string(&record).map_err(to_string)?
+    )
+    .map_err(to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::choose;
+    use crate::config::ClientIdScheme;
+
+    #[test]
+    fn test_choose_client_id() {
+        let existing = vec!["testing-0".to_owned(), "testing-1".to_owned()];
+        assert_eq!(
+            choose(None, &ClientIdScheme::Counterparty, "testing", &existing),
+            Ok("testing-2".to_owned())
+        );
+        assert!(choose(
+            Some("testing-1".to_owned()),
+            &ClientIdScheme::Random,
+            "testing",
+            &existing
+        )
+        .is_err());
+        assert!(choose(None, &ClientIdScheme::Counterparty, "", &existing).is_err());
+        assert_eq!(
+            choose(None, &ClientIdScheme::Random, "testing", &existing)
+                .unwrap()
+                .len(),
+            10
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! State of a light client as stored on the chain hosting it, in a form common
+//! to the wasm client on cosmos chain and the tendermint client on substrate chain.
+
+use crate::cosmos::types::{TMClientInfo, WasmClientState, WasmConsensusState};
+use crate::error::RelayError;
+use log::*;
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+/// How often send handlers refresh on-chain state of the client they update.
+pub const CLIENT_STATE_QUERY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Recorded in place of the hash of a transaction or extrinsic which is known to have
+/// been applied only from state of the client.
+pub const UNCONFIRMED_HASH: &str = "unconfirmed";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ClientState {
+    /// Identifier of the client.
+    pub client_id: String,
+    /// Height of the latest header ingested by the client.
+    pub latest_height: u64,
+    /// Time of the latest header ingested by the client, if client keeps track of it.
+    pub latest_timestamp: Option<tendermint::Time>,
+    /// Height at which misbehaviour froze the client, if any.
+    pub frozen_height: Option<u64>,
+    /// State as stored by the module hosting the client, for inspection.
+    pub stored: Option<StoredState>,
+}
+
+/// Client state, along with consensus state where it is kept apart, as decoded from
+/// storage of the module hosting the client.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoredState {
+    /// Substrate light client in wormhole module of cosmos chain, with its consensus
+    /// state at the latest height, if that could be queried.
+    Wasm {
+        client_state: WasmClientState,
+        consensus_state: Option<WasmConsensusState>,
+    },
+    /// Cosmos light client in tendermint light client module of substrate chain.
+    Tendermint(TMClientInfo),
+}
+
+impl ClientState {
+    /// Whether client has ingested header at `height` or a later one already.
+    pub fn knows(&self, height: u64) -> bool {
+        height <= self.latest_height
+    }
+
+    /// Whether trusting period has passed since the latest header ingested by client.
+    /// Clients which do not keep track of header time are never considered expired.
+    pub fn is_expired(&self, trusting_period: Duration, now: SystemTime) -> bool {
+        match self.latest_timestamp {
+            Some(timestamp) => {
+                let timestamp: SystemTime = timestamp.into();
+                timestamp + trusting_period < now
+            }
+            None => false,
+        }
+    }
+
+    /// Short description of whether client can still be updated.
+    pub fn status(&self, trusting_period: Duration, now: SystemTime) -> String {
+        match self.frozen_height {
+            Some(height) => format!("frozen at {}", height),
+            None if self.is_expired(trusting_period, now) => "expired".to_owned(),
+            None => "active".to_owned(),
+        }
+    }
+
+    /// Fails if client can no longer be updated and has to be recreated.
+    pub fn ensure_active(&self, trusting_period: Duration, now: SystemTime) -> Result<(), String> {
+        if let Some(height) = self.frozen_height {
+            return Err(format!(
+                "client {} was frozen at height: {}, it has to be recreated",
+                self.client_id, height
+            ));
+        }
+        if self.is_expired(trusting_period, now) {
+            return Err(format!(
+                "client {} expired, its latest header at height: {} is older than trusting period of {:?}, it has to be recreated",
+                self.client_id, self.latest_height, trusting_period
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Tells what became of header at `height` whose submission to client `client_id` was
+/// taken by the chain with result unknown, as `err` tells. Client has the header if it
+/// `ingested` it, otherwise the chain refused it.
+pub fn confirm(
+    client_id: &str,
+    height: u64,
+    ingested: bool,
+    err: &str,
+) -> Result<String, RelayError> {
+    if ingested {
+        warn!(
+            "{}, client {} has ingested header at height: {} nevertheless",
+            err, client_id, height
+        );
+        Ok(UNCONFIRMED_HASH.to_owned())
+    } else {
+        Err(RelayError::TxRejected {
+            codespace: None,
+            code: None,
+            log: format!(
+                "{}, client {} has not ingested header at height: {}",
+                err, client_id, height
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{confirm, ClientState, UNCONFIRMED_HASH};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_client_state_detects_expiry_and_freeze() {
+        let timestamp = tendermint::Time::parse_from_rfc3339("2020-10-01T00:00:00Z").unwrap();
+        let mut state = ClientState {
+            client_id: "abcdefghij".to_owned(),
+            latest_height: 10,
+            latest_timestamp: Some(timestamp),
+            frozen_height: None,
+            stored: None,
+        };
+        let trusting_period = Duration::from_secs(3600);
+        let now: SystemTime = timestamp.into();
+        assert!(state.knows(10));
+        assert!(!state.knows(11));
+        assert!(state.ensure_active(trusting_period, now).is_ok());
+        assert!(state
+            .ensure_active(trusting_period, now + Duration::from_secs(7200))
+            .is_err());
+        state.frozen_height = Some(9);
+        assert!(state.ensure_active(trusting_period, now).is_err());
+    }
+
+    #[test]
+    fn test_unconfirmed_submission_is_told_from_client() {
+        assert_eq!(
+            confirm("abcdefghij", 11, true, "connection closed").unwrap(),
+            UNCONFIRMED_HASH
+        );
+        assert!(confirm("abcdefghij", 11, false, "connection closed")
+            .unwrap_err()
+            .is_rejection());
+    }
+}
@@ -5,15 +5,23 @@
 //! The default application comes with two subcommands:
 //!
 //! - `start`: launches the application
+//! - `generate`: write simulation data without a live chain
+//! - `query`: inspect light clients stored on either chain
+//! - `tx`: create or update light clients outside of the relay loop
 //! - `version`: print application version
 //!
 //! See the `impl Configurable` below for how to specify the path to the
 //! application's configuration file.
 
+mod generate;
+mod query;
 mod start;
+mod tx;
 mod version;
 
-use self::{start::StartCmd, version::VersionCmd};
+use self::{
+    generate::GenerateCmd, query::QueryCmd, start::StartCmd, tx::TxCmd, version::VersionCmd,
+};
 use crate::config::QuantumTunnelConfig;
 use abscissa_core::{config::Override, Command, Configurable, FrameworkError, Options, Runnable};
 use std::fs::File;
@@ -33,6 +41,18 @@ pub enum QuantumTunnelCmd {
     #[options(help = "start the application")]
     Start(StartCmd),
 
+    /// The `generate` subcommand
+    #[options(help = "generate simulation data")]
+    Generate(GenerateCmd),
+
+    /// The `query` subcommand
+    #[options(help = "inspect light clients")]
+    Query(QueryCmd),
+
+    /// The `tx` subcommand
+    #[options(help = "submit light client transactions")]
+    Tx(TxCmd),
+
     /// The `version` subcommand
     #[options(help = "display version information")]
     Version(VersionCmd),
@@ -67,6 +87,7 @@ impl Configurable<QuantumTunnelConfig> for QuantumTunnelCmd {
     ) -> Result<QuantumTunnelConfig, FrameworkError> {
         match self {
             QuantumTunnelCmd::Start(cmd) => cmd.override_config(config),
+            QuantumTunnelCmd::Tx(cmd) => cmd.override_config(config),
             _ => Ok(config),
         }
     }
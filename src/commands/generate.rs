@@ -0,0 +1,93 @@
+//! `generate` subcommand - produces simulation files without a live chain
+
+use crate::prelude::*;
+
+use crate::substrate::generator::{AuthorityChange, ChainGenerator, ChangeKind};
+use abscissa_core::{Command, Options, Runnable};
+use std::fs::File;
+use std::io::Write;
+
+/// `generate` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum GenerateCmd {
+    /// The `substrate` subcommand
+    #[options(help = "generate substrate headers finalized by synthetic GRANDPA authorities")]
+    Substrate(GenerateSubstrateCmd),
+}
+
+/// `generate substrate` subcommand
+#[derive(Command, Debug, Options)]
+pub struct GenerateSubstrateCmd {
+    #[options(help = "path of the simulation file to write")]
+    output: String,
+
+    #[options(help = "number of blocks to generate", default = "10")]
+    blocks: u32,
+
+    #[options(help = "number of the first generated block", default = "1")]
+    start_height: u32,
+
+    #[options(help = "size of the initial authority set", default = "4")]
+    authorities: usize,
+
+    #[options(help = "id of the initial authority set", default = "0")]
+    set_id: u64,
+
+    #[options(help = "attach justification to every n-th block", default = "1")]
+    justification_period: u32,
+
+    #[options(help = "schedule authority set change as <height>:<delay>:<authorities>")]
+    scheduled_change: Vec<String>,
+
+    #[options(help = "force authority set change as <height>:<delay>:<authorities>")]
+    forced_change: Vec<String>,
+}
+
+impl GenerateSubstrateCmd {
+    fn generate(&self) -> Result<(), String> {
+        if self.output.is_empty() {
+            return Err("output path is required".to_string());
+        }
+        if self.authorities == 0 {
+            return Err("authority set cannot be empty".to_string());
+        }
+
+        let mut generator = ChainGenerator::new(
+            self.start_height,
+            self.authorities,
+            self.set_id,
+            self.justification_period,
+        );
+        for change in &self.scheduled_change {
+            generator.schedule_change(AuthorityChange::parse(ChangeKind::Scheduled, change)?)?;
+        }
+        for change in &self.forced_change {
+            generator.schedule_change(AuthorityChange::parse(ChangeKind::Forced, change)?)?;
+        }
+
+        let mut payloads = Vec::with_capacity(self.blocks as usize);
+        for _ in 0..self.blocks {
+            payloads.push(generator.next_block()?.to_simulation_json()?);
+        }
+
+        let mut file = File::create(&self.output).map_err(|e| e.to_string())?;
+        file.write_all(payloads.join("\n\n").as_bytes())
+            .map_err(|e| e.to_string())?;
+        info!(
+            "Wrote {} substrate headers to {}",
+            payloads.len(),
+            self.output
+        );
+        Ok(())
+    }
+}
+
+impl Runnable for GenerateSubstrateCmd {
+    /// Generate substrate simulation file.
+    fn run(&self) {
+        if let Err(e) = self.generate() {
+            status_err!("unable to generate substrate simulation data: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! `query` subcommand - inspects light clients stored on either chain
+
+use crate::prelude::*;
+
+use crate::chain::ClientStateQuerier;
+use crate::client_state::ClientState;
+use crate::config::{ChainConfig, CosmosChainConfig, SubstrateChainConfig};
+use abscissa_core::{Command, Options, Runnable};
+use serde_json::Value;
+use std::time::{Duration, SystemTime};
+
+/// `query` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum QueryCmd {
+    /// The `client` subcommand
+    #[options(help = "show state of a light client: query client <chain> <client_id>")]
+    Client(QueryClientCmd),
+
+    /// The `clients` subcommand
+    #[options(help = "list light clients stored on a chain: query clients <chain>")]
+    Clients(QueryClientsCmd),
+}
+
+/// `query client` subcommand
+#[derive(Command, Debug, Options)]
+pub struct QueryClientCmd {
+    /// Chain hosting the client, `cosmos`, `substrate` or name of a chain of `chains`
+    #[options(free)]
+    chain: String,
+
+    /// Identifier of the client
+    #[options(free)]
+    client_id: String,
+
+    #[options(help = "output format, table or json", default = "table")]
+    output: String,
+}
+
+/// `query clients` subcommand
+#[derive(Command, Debug, Options)]
+pub struct QueryClientsCmd {
+    /// Chain hosting the clients, `cosmos`, `substrate` or name of a chain of `chains`
+    #[options(free)]
+    chain: String,
+
+    #[options(help = "output format, table or json", default = "table")]
+    output: String,
+}
+
+/// Live chain hosting the clients, given by its name in `chains`, or as `cosmos`/`substrate`.
+fn querier(chain: &str) -> Result<Box<dyn ClientStateQuerier>, String> {
+    let config = app_config();
+    // Chains of paths are looked up by name.
+    if let Some(named) = config.chains.get(chain) {
+        return Ok(match named {
+            ChainConfig::Cosmos(cfg) => Box::new(cfg.clone()),
+            ChainConfig::Substrate(cfg) => Box::new(cfg.clone()),
+        });
+    }
+    match chain {
+        "cosmos" => match &config.cosmos {
+            Some(CosmosChainConfig::Real(cfg)) => Ok(Box::new(cfg.clone())),
+            Some(CosmosChainConfig::Simulation(_)) => {
+                Err("cosmos chain is configured as simulation".to_string())
+            }
+            None => Err("cosmos chain is not configured".to_string()),
+        },
+        "substrate" => match &config.substrate {
+            Some(SubstrateChainConfig::Real(cfg)) => Ok(Box::new(cfg.clone())),
+            Some(SubstrateChainConfig::Simulation(_)) => {
+                Err("substrate chain is configured as simulation".to_string())
+            }
+            None => Err("substrate chain is not configured".to_string()),
+        },
+        _ => Err(format!(
+            "unknown chain: {}, expected cosmos, substrate or name of a configured chain",
+            chain
+        )),
+    }
+}
+
+/// Prints states of clients; `details` adds their stored client and consensus state
+/// to the table, which always carries them in JSON.
+fn print_states(
+    states: &[ClientState],
+    trusting_period: Duration,
+    output: &str,
+    details: bool,
+) -> Result<(), String> {
+    match output {
+        "json" => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(states).map_err(|e| e.to_string())?
+            );
+        }
+        "table" => {
+            let now = SystemTime::now();
+            println!(
+                "{:<24} {:>14} {:<32} {}",
+                "CLIENT ID", "LATEST HEIGHT", "LATEST TIMESTAMP", "STATUS"
+            );
+            for state in states {
+                let timestamp = state
+                    .latest_timestamp
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_else(|| "-".to_owned());
+                println!(
+                    "{:<24} {:>14} {:<32} {}",
+                    state.client_id,
+                    state.latest_height,
+                    timestamp,
+                    state.status(trusting_period, now)
+                );
+            }
+            if details {
+                for state in states.iter().filter(|state| state.stored.is_some()) {
+                    println!();
+                    println!("{}:", state.client_id);
+                    let stored = serde_json::to_value(&state.stored).map_err(|e| e.to_string())?;
+                    print_fields("  ", &stored);
+                }
+            }
+        }
+        _ => return Err(format!("unknown output format: {}", output)),
+    }
+    Ok(())
+}
+
+/// Prints every leaf of `value` as `field: value`, nested fields indented below their parent.
+fn print_fields(indent: &str, value: &Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                match field {
+                    Value::Object(_) | Value::Array(_) => {
+                        println!("{}{}:", indent, name);
+                        print_fields(&format!("{}  ", indent), field);
+                    }
+                    _ => println!("{}{}: {}", indent, name, field),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                println!("{}[{}]:", indent, index);
+                print_fields(&format!("{}  ", indent), item);
+            }
+        }
+        _ => println!("{}{}", indent, value),
+    }
+}
+
+impl QueryClientCmd {
+    async fn query(&self) -> Result<(), String> {
+        let querier = querier(&self.chain)?;
+        let state = querier
+            .client_state(self.client_id.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        let trusting_period = querier.trusting_period().map_err(|e| e.to_string())?;
+        print_states(&[state], trusting_period, &self.output, true)
+    }
+}
+
+impl Runnable for QueryClientCmd {
+    /// Print state of a light client.
+    #[tokio::main]
+    async fn run(&self) {
+        if let Err(e) = self.query().await {
+            status_err!("unable to query client {}: {}", self.client_id, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+impl QueryClientsCmd {
+    async fn query(&self) -> Result<(), String> {
+        let querier = querier(&self.chain)?;
+        let mut states = vec![];
+        for client_id in querier.client_ids().await.map_err(|e| e.to_string())? {
+            states.push(
+                querier
+                    .client_state(client_id)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            );
+        }
+        let trusting_period = querier.trusting_period().map_err(|e| e.to_string())?;
+        print_states(&states, trusting_period, &self.output, false)
+    }
+}
+
+impl Runnable for QueryClientsCmd {
+    /// Print state of every light client stored on a chain.
+    #[tokio::main]
+    async fn run(&self) {
+        if let Err(e) = self.query().await {
+            status_err!("unable to query clients on {} chain: {}", self.chain, e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -4,13 +4,25 @@
 /// accessors along with logging macros. Customize as you see fit.
 use crate::prelude::*;
 
-use crate::config::{CosmosChainConfig, QuantumTunnelConfig, SubstrateChainConfig};
-use crate::cosmos::Handler as CosmosHandler;
-use crate::substrate::Handler as SubstrateHandler;
+use crate::config::{ChainConfig, CosmosChainConfig, QuantumTunnelConfig, SubstrateChainConfig};
+use crate::error::RelayError;
+use crate::relayer::Relayer;
+use crate::report::SimulationReport;
+use crate::utils::to_string;
 use abscissa_core::error::Context;
 use abscissa_core::{config, Command, FrameworkError, FrameworkErrorKind, Options, Runnable};
-use crossbeam_channel::unbounded;
 use std::env;
+use std::fmt;
+use std::io;
+use std::time::Instant;
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Exit status when relaying stopped with an error, or was interrupted while waiting
+/// for transactions in flight.
+const EXIT_RELAY_FAILED: i32 = 1;
+
+/// Exit status when configuration is rejected before relaying starts.
+const EXIT_INVALID_CONFIG: i32 = 2;
 
 /// `start` subcommand
 ///
@@ -26,73 +38,129 @@ pub struct StartCmd {
     cosmos_chain_id: String,
     cosmos_client: String,
     substrate_client: String,
+
+    #[options(help = "write JSON report of the simulation run to given path")]
+    report: String,
+
+    #[options(help = "write JUnit XML report of the simulation run to given path")]
+    junit_report: String,
 }
 
 impl Runnable for StartCmd {
     /// Start the application.
     #[tokio::main]
     async fn run(&self) {
-        let config = app_config();
-        let (cosmos_chan_tx, cosmos_chan_rx) = unbounded();
-        let (substrate_chan_tx, substrate_chan_rx) = unbounded();
-
-        // Simulation monitoring channels are used to send information about consumed blocks to
-        // simulation handler which enables simulation handler to detect successful or failure
-        // simulation.
-        // Sender goes to recv_handler and Receiver goes to send_handler
-        // This channels will be ignored if it is live chain
-        let (simulation_monitoring_tx, simulation_monitoring_rx) = unbounded();
-
-        let mut cosmos_client_id = None;
-        if !self.cosmos_client.is_empty() {
-            cosmos_client_id = Some(self.cosmos_client.clone());
+        if let Err(failure) = self.relay(app_config().clone()).await {
+            status_err!("{}", failure);
+            std::process::exit(failure.exit_status());
+        }
+    }
+}
+
+/// Reason `start` exits with a failure status.
+#[derive(Debug)]
+enum Failure {
+    /// Configuration was rejected before relaying started.
+    InvalidConfig(RelayError),
+    /// Relaying stopped with an error.
+    RelayFailed(RelayError),
+}
+
+impl Failure {
+    fn exit_status(&self) -> i32 {
+        match self {
+            Failure::InvalidConfig(_) => EXIT_INVALID_CONFIG,
+            Failure::RelayFailed(_) => EXIT_RELAY_FAILED,
         }
+    }
+}
 
-        let mut substrate_client_id = None;
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::InvalidConfig(e) => write!(f, "invalid configuration: {}", e),
+            Failure::RelayFailed(e) => write!(f, "relaying stopped: {}", e),
+        }
+    }
+}
+
+impl StartCmd {
+    /// Builds relayer of chains configured in `config`, resuming clients given on
+    /// command line.
+    fn build(&self, config: QuantumTunnelConfig) -> Result<Relayer, Failure> {
+        let mut builder = Relayer::builder(config);
+        if !self.cosmos_client.is_empty() {
+            builder = builder.cosmos_client_id(self.cosmos_client.clone());
+        }
         if !self.substrate_client.is_empty() {
-            substrate_client_id = Some(self.substrate_client.clone());
+            builder = builder.substrate_client_id(self.substrate_client.clone());
         }
+        builder.build().map_err(Failure::InvalidConfig)
+    }
 
-        tokio::select! {
-            res = CosmosHandler::recv_handler(
-                config.cosmos.clone(),
-                cosmos_chan_tx,
-                simulation_monitoring_rx.clone()
-            ) => {
-                if res.is_err() {
-                    panic!(format!("Error occurred while receiving data from Cosmos chain: {}", res.err().unwrap()));
-                }
-            },
-            res = SubstrateHandler::recv_handler(
-                config.substrate.clone(),
-                substrate_chan_tx,
-                simulation_monitoring_rx.clone()
-            ) => {
-                if res.is_err() {
-                    panic!(format!("Error occurred while receiving data from Substrate chain: {}", res.err().unwrap()));
-                }
-            },
-            res = SubstrateHandler::send_handler(
-                config.substrate.clone(),
-                substrate_client_id,
-                cosmos_chan_rx,
-                simulation_monitoring_tx.clone()
-            ) => {
-                if res.is_err() {
-                    panic!(format!("Error occurred while sending data to Substrate chain: {}", res.err().unwrap()));
+    /// Relays until relaying stops or the process is asked to shut down.
+    async fn relay(&self, config: QuantumTunnelConfig) -> Result<(), Failure> {
+        let mut relayer = self.build(config.clone())?;
+
+        // First signal stops relaying gracefully, second one exits without waiting for
+        // transactions in flight.
+        let handle = relayer.handle();
+        tokio::spawn(async move {
+            if let Err(e) = shutdown_signal().await {
+                error!("Unable to listen for shutdown signals: {}", e);
+                return;
+            }
+            info!("Shutting down, waiting for transactions in flight to be confirmed");
+            handle.stop();
+            if shutdown_signal().await.is_ok() {
+                warn!("Exiting without waiting for transactions in flight");
+                std::process::exit(EXIT_RELAY_FAILED);
+            }
+        });
+
+        // Outcome of every header sent to a light client is only collected when it is
+        // going to be written out once the run is over.
+        let events = if self.report.is_empty() && self.junit_report.is_empty() {
+            None
+        } else {
+            Some(relayer.subscribe())
+        };
+
+        let started = Instant::now();
+        let result = relayer.run().await;
+
+        if let Some(events) = events {
+            let report = SimulationReport::new(
+                &config,
+                &result.clone().map_err(to_string),
+                started.elapsed(),
+                events.try_iter().collect(),
+            );
+            if !self.report.is_empty() {
+                if let Err(e) = report.write_json(&self.report) {
+                    error!("Unable to write report to {}: {}", self.report, e);
                 }
-            },
-            res = CosmosHandler::send_handler(
-                config.cosmos.clone(),
-                cosmos_client_id,
-                substrate_chan_rx,
-                simulation_monitoring_tx.clone()
-            ) => {
-                if res.is_err() {
-                    panic!(format!("Error occurred while sending data to cosmos chain: {}", res.err().unwrap()));
+            }
+            if !self.junit_report.is_empty() {
+                if let Err(e) = report.write_junit(&self.junit_report) {
+                    error!(
+                        "Unable to write JUnit report to {}: {}",
+                        self.junit_report, e
+                    );
                 }
             }
         }
+
+        result.map_err(Failure::RelayFailed)
+    }
+}
+
+/// Resolves once the process receives SIGINT or SIGTERM.
+async fn shutdown_signal() -> Result<(), String> {
+    let mut terminate = signal(SignalKind::terminate()).map_err(to_string)?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res.map_err(to_string),
+        _ = terminate.recv() => Ok(()),
     }
 }
 
@@ -104,49 +172,100 @@ impl config::Override<QuantumTunnelConfig> for StartCmd {
         &self,
         mut config: QuantumTunnelConfig,
     ) -> Result<QuantumTunnelConfig, FrameworkError> {
-        let mut is_live = false;
-        match (&mut config.cosmos, &mut config.substrate) {
-            // Both configuration cannot be simulation at the same time
-            (CosmosChainConfig::Simulation(_), SubstrateChainConfig::Simulation(_)) => {
-                return Err(FrameworkError::from(Context::new(
+        let read_seed = |name: &str| {
+            env::var(name).map_err(|e| {
+                FrameworkError::from(Context::new(
                     FrameworkErrorKind::ConfigError,
-                    None,
-                )))
-            }
-            (CosmosChainConfig::Real(_), SubstrateChainConfig::Real(_)) => {
-                is_live = true;
+                    Some(Box::new(e)),
+                ))
+            })
+        };
+
+        // Chains of paths are always live, each one signs with seed from its own variable.
+        if !config.paths.is_empty() {
+            let names: Vec<String> = config
+                .paths
+                .values()
+                .flat_map(|path| vec![path.cosmos.clone(), path.substrate.clone()])
+                .collect();
+            for name in names {
+                match config.chains.get_mut(&name) {
+                    Some(ChainConfig::Cosmos(cfg)) => {
+                        cfg.signer_seed = read_seed(&ChainConfig::signer_seed_var(&name))?;
+                    }
+                    Some(ChainConfig::Substrate(cfg)) => {
+                        cfg.signer_seed = read_seed(&ChainConfig::signer_seed_var(&name))?;
+                    }
+                    None => {}
+                }
             }
-            _ => {}
+            return Ok(config);
         }
 
-        if let CosmosChainConfig::Real(ref mut cfg) = config.cosmos {
+        // Chain simulated on its own signs nothing and has no other chain to link to.
+        if config.same_kind_simulation() {
+            return Ok(config);
+        }
+
+        let (cosmos, substrate) = config.pair_mut().map_err(|e| {
+            FrameworkError::from(Context::new(
+                FrameworkErrorKind::ConfigError,
+                Some(Box::new(io::Error::new(io::ErrorKind::InvalidInput, e))),
+            ))
+        })?;
+        if let CosmosChainConfig::Real(cfg) = cosmos {
             if !self.cosmos_chain_id.is_empty() {
                 cfg.chain_id = self.cosmos_chain_id.clone();
             }
 
             // Let's read environment variables to get seed data.
-            cfg.signer_seed = env::var("COSMOS_SIGNER_SEED").map_err(|e| {
-                FrameworkError::from(Context::new(
-                    FrameworkErrorKind::ConfigError,
-                    Some(Box::new(e)),
-                ))
-            })?;
-
-            cfg.is_other_side_simulation = !is_live;
+            cfg.signer_seed = read_seed("COSMOS_SIGNER_SEED")?;
         }
 
-        if let SubstrateChainConfig::Real(ref mut cfg) = config.substrate {
+        if let SubstrateChainConfig::Real(cfg) = substrate {
             // Let's read environment variables to get seed data.
-            cfg.signer_seed = env::var("SUBSTRATE_SIGNER_SEED").map_err(|e| {
-                FrameworkError::from(Context::new(
-                    FrameworkErrorKind::ConfigError,
-                    Some(Box::new(e)),
-                ))
-            })?;
-
-            cfg.is_other_side_simulation = !is_live;
+            cfg.signer_seed = read_seed("SUBSTRATE_SIGNER_SEED")?;
         }
 
+        // Chain ids are known only now that cosmos one may have been overridden.
+        config.link_chains();
+
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Failure, StartCmd, EXIT_INVALID_CONFIG, EXIT_RELAY_FAILED};
+    use crate::config::QuantumTunnelConfig;
+    use crate::error::RelayError;
+
+    fn start_cmd() -> StartCmd {
+        StartCmd {
+            cosmos_chain_id: String::new(),
+            cosmos_client: String::new(),
+            substrate_client: String::new(),
+            report: String::new(),
+            junit_report: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_rejected_config_and_failed_relaying_exit_with_own_status() {
+        let data = std::fs::read_to_string("test_data/paths_config.json").unwrap();
+        let mut config: QuantumTunnelConfig = serde_json::from_str(&data).unwrap();
+        assert!(start_cmd().build(config.clone()).is_ok());
+
+        config.shutdown.drain_timeout = "soon".to_owned();
+        let failure = start_cmd().build(config).err().unwrap();
+        assert!(matches!(failure, Failure::InvalidConfig(_)));
+        assert_eq!(failure.exit_status(), EXIT_INVALID_CONFIG);
+
+        let failure = Failure::RelayFailed(RelayError::Unconfirmed(
+            "transactions in flight were not confirmed".to_owned(),
+        ));
+        assert_eq!(failure.exit_status(), EXIT_RELAY_FAILED);
+        assert_ne!(EXIT_INVALID_CONFIG, EXIT_RELAY_FAILED);
+        assert_ne!(EXIT_RELAY_FAILED, 0);
+    }
+}
@@ -0,0 +1,291 @@
+//! `tx` subcommand - creates or updates light clients outside of the relay loop
+
+use crate::prelude::*;
+
+use crate::config::{
+    ChainConfig, CosmosChainConfig, CosmosConfig, QuantumTunnelConfig, SubstrateChainConfig,
+    SubstrateConfig,
+};
+use crate::cosmos::Handler as CosmosHandler;
+use crate::error::RelayError;
+use crate::substrate::Handler as SubstrateHandler;
+use abscissa_core::error::Context;
+use abscissa_core::{config, Command, FrameworkError, FrameworkErrorKind, Options, Runnable};
+use std::env;
+
+/// `tx` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum TxCmd {
+    /// The `create-client` subcommand
+    #[options(help = "create a light client: tx create-client <chain>")]
+    CreateClient(TxCreateClientCmd),
+
+    /// The `update-client` subcommand
+    #[options(help = "update a light client: tx update-client <chain> <client_id>")]
+    UpdateClient(TxUpdateClientCmd),
+}
+
+/// `tx create-client` subcommand
+#[derive(Command, Debug, Options)]
+pub struct TxCreateClientCmd {
+    /// Chain hosting the client, `cosmos` or `substrate`
+    #[options(free)]
+    chain: String,
+
+    #[options(
+        no_short,
+        help = "height of the header on the opposite chain to trust, or latest",
+        default = "latest"
+    )]
+    height: String,
+
+    #[options(
+        no_short,
+        help = "identifier of the new client, overrides client_id from config"
+    )]
+    client_id: String,
+
+    #[options(
+        no_short,
+        help = "name of the path whose chains to use, if paths are configured"
+    )]
+    path: String,
+}
+
+/// `tx update-client` subcommand
+#[derive(Command, Debug, Options)]
+pub struct TxUpdateClientCmd {
+    /// Chain hosting the client, `cosmos` or `substrate`
+    #[options(free)]
+    chain: String,
+
+    /// Identifier of the client
+    #[options(free)]
+    client_id: String,
+
+    #[options(
+        no_short,
+        help = "height of the header on the opposite chain to submit, or latest",
+        default = "latest"
+    )]
+    height: String,
+
+    #[options(
+        no_short,
+        help = "name of the path whose chains to use, if paths are configured"
+    )]
+    path: String,
+}
+
+/// Both chains have to be live, as header is read from one and submitted to the other.
+/// They are the chains of `path` if it is given.
+fn live_configs(
+    config: &QuantumTunnelConfig,
+    path: &str,
+) -> Result<(CosmosConfig, SubstrateConfig), RelayError> {
+    if !path.is_empty() {
+        return config
+            .relay_paths()
+            .map_err(RelayError::Config)?
+            .into_iter()
+            .find(|relay_path| relay_path.name == path)
+            .map(|relay_path| (relay_path.cosmos, relay_path.substrate))
+            .ok_or_else(|| RelayError::Config(format!("unknown path: {}", path)));
+    }
+    match config.pair().map_err(RelayError::Config)? {
+        (CosmosChainConfig::Real(cosmos), SubstrateChainConfig::Real(substrate)) => {
+            Ok((cosmos.clone(), substrate.clone()))
+        }
+        _ => Err(RelayError::config(
+            "both chains have to be configured as real",
+        )),
+    }
+}
+
+fn parse_height(height: &str) -> Result<Option<u64>, RelayError> {
+    match height {
+        "latest" => Ok(None),
+        height => height
+            .parse()
+            .map(Some)
+            .map_err(|e| RelayError::Config(format!("invalid height: {}, {}", height, e))),
+    }
+}
+
+impl TxCreateClientCmd {
+    /// Returns identifier of the created client, and the file it is recorded in along
+    /// with hash of the transaction.
+    async fn create(&self) -> Result<(String, String), RelayError> {
+        let (mut cosmos_cfg, mut substrate_cfg) = live_configs(&app_config(), &self.path)?;
+        let height = parse_height(&self.height)?;
+        let requested = if self.client_id.is_empty() {
+            None
+        } else {
+            Some(self.client_id.clone())
+        };
+        match self.chain.as_str() {
+            "cosmos" => {
+                cosmos_cfg.client_id = requested.or(cosmos_cfg.client_id);
+                let client_id = CosmosHandler::new_client_id(&cosmos_cfg).await?;
+                let header = SubstrateHandler::fetch_header(&substrate_cfg, height).await?;
+                info!(
+                    "Creating substrate light client {} trusting header at height: {}",
+                    client_id, header.block.block.header.number
+                );
+                let client_id =
+                    CosmosHandler::create_client(cosmos_cfg.clone(), client_id, header).await?;
+                Ok((client_id, cosmos_cfg.clients_file))
+            }
+            "substrate" => {
+                substrate_cfg.client_id = requested.or(substrate_cfg.client_id);
+                let client_id = SubstrateHandler::new_client_id(&substrate_cfg).await?;
+                let (header, _) = CosmosHandler::fetch_header(&cosmos_cfg, height).await?;
+                info!(
+                    "Creating cosmos light client {} trusting header at height: {}",
+                    client_id, header.signed_header.header.height
+                );
+                let hash =
+                    SubstrateHandler::create_client(&substrate_cfg, &client_id, header).await?;
+                SubstrateHandler::record_client(&substrate_cfg, &client_id, &hash);
+                Ok((client_id, substrate_cfg.clients_file))
+            }
+            chain => Err(RelayError::Config(format!(
+                "unknown chain: {}, expected cosmos or substrate",
+                chain
+            ))),
+        }
+    }
+}
+
+impl Runnable for TxCreateClientCmd {
+    /// Create a light client.
+    #[tokio::main]
+    async fn run(&self) {
+        match self.create().await {
+            Ok((client_id, clients_file)) => status_ok!(
+                "Created",
+                "client {} on {} chain, tx is recorded in {}",
+                client_id,
+                self.chain,
+                clients_file
+            ),
+            Err(e) => {
+                status_err!("unable to create client on {} chain: {}", self.chain, e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+impl TxUpdateClientCmd {
+    async fn update(&self) -> Result<String, RelayError> {
+        let (cosmos_cfg, substrate_cfg) = live_configs(&app_config(), &self.path)?;
+        let height = parse_height(&self.height)?;
+        match self.chain.as_str() {
+            "cosmos" => {
+                let header = SubstrateHandler::fetch_header(&substrate_cfg, height).await?;
+                info!(
+                    "Updating substrate light client {} with header at height: {}",
+                    self.client_id, header.block.block.header.number
+                );
+                CosmosHandler::update_client(cosmos_cfg, header, self.client_id.clone()).await
+            }
+            "substrate" => {
+                let (header, next_validators) =
+                    CosmosHandler::fetch_header(&cosmos_cfg, height).await?;
+                info!(
+                    "Updating cosmos light client {} with header at height: {}",
+                    self.client_id, header.signed_header.header.height
+                );
+                SubstrateHandler::update_client(
+                    &substrate_cfg,
+                    &self.client_id,
+                    header,
+                    next_validators,
+                )
+                .await
+            }
+            chain => Err(RelayError::Config(format!(
+                "unknown chain: {}, expected cosmos or substrate",
+                chain
+            ))),
+        }
+    }
+}
+
+impl Runnable for TxUpdateClientCmd {
+    /// Update a light client.
+    #[tokio::main]
+    async fn run(&self) {
+        match self.update().await {
+            Ok(hash) => status_ok!(
+                "Updated",
+                "client {} on {} chain, tx: {}",
+                self.client_id,
+                self.chain,
+                hash
+            ),
+            Err(e) => {
+                status_err!(
+                    "unable to update client {} on {} chain: {}",
+                    self.client_id,
+                    self.chain,
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+impl config::Override<QuantumTunnelConfig> for TxCmd {
+    // Signer seed is read from environment variable, the same way `start` does it,
+    // for the chain hosting the client.
+    fn override_config(
+        &self,
+        mut config: QuantumTunnelConfig,
+    ) -> Result<QuantumTunnelConfig, FrameworkError> {
+        let (chain, path) = match self {
+            TxCmd::CreateClient(cmd) => (&cmd.chain, &cmd.path),
+            TxCmd::UpdateClient(cmd) => (&cmd.chain, &cmd.path),
+        };
+        let read_seed = |name: &str| {
+            env::var(name).map_err(|e| {
+                FrameworkError::from(Context::new(
+                    FrameworkErrorKind::ConfigError,
+                    Some(Box::new(e)),
+                ))
+            })
+        };
+        // Chains of a path take the seed from variable named after the chain.
+        if let Some(path_cfg) = config.paths.get(path) {
+            let name = match chain.as_str() {
+                "cosmos" => path_cfg.cosmos.clone(),
+                _ => path_cfg.substrate.clone(),
+            };
+            match config.chains.get_mut(&name) {
+                Some(ChainConfig::Cosmos(cfg)) => {
+                    cfg.signer_seed = read_seed(&ChainConfig::signer_seed_var(&name))?;
+                }
+                Some(ChainConfig::Substrate(cfg)) => {
+                    cfg.signer_seed = read_seed(&ChainConfig::signer_seed_var(&name))?;
+                }
+                None => {}
+            }
+            return Ok(config);
+        }
+        if let Ok((cosmos, substrate)) = config.pair_mut() {
+            match (chain.as_str(), cosmos, substrate) {
+                ("cosmos", CosmosChainConfig::Real(cfg), _) => {
+                    cfg.signer_seed = read_seed("COSMOS_SIGNER_SEED")?;
+                }
+                ("substrate", _, SubstrateChainConfig::Real(cfg)) => {
+                    cfg.signer_seed = read_seed("SUBSTRATE_SIGNER_SEED")?;
+                }
+                _ => {}
+            }
+        }
+        config.link_chains();
+        Ok(config)
+    }
+}
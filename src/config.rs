@@ -1,15 +1,302 @@
 //! QuantumTunnel Config
 
+use crate::client_id;
+use crate::health::Health;
+use crate::metrics::Metrics;
+use crate::shutdown::Shutdown;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// QuantumTunnel Configuration
-#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct QuantumTunnelConfig {
-    /// Configuration pertaining to the cosmos chain.
-    pub cosmos: CosmosChainConfig,
-    /// Configuration pertaining to the substrate chain.
-    pub substrate: SubstrateChainConfig,
+    /// Configuration pertaining to the cosmos chain, unless `paths` are configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosmos: Option<CosmosChainConfig>,
+    /// Configuration pertaining to the substrate chain, unless `paths` are configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub substrate: Option<SubstrateChainConfig>,
+    /// Live chains by name, relayed between by `paths`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chains: BTreeMap<String, ChainConfig>,
+    /// Relay paths by name, each between a cosmos and a substrate chain of `chains`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub paths: BTreeMap<String, PathConfig>,
+    /// Configuration of prometheus metrics endpoint; metrics are not served if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsConfig>,
+    /// Configuration of health check endpoint, served apart from metrics; health checks
+    /// are served along with metrics only if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthConfig>,
+    /// How relayer stops once it is asked to.
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+}
+
+// Default configuration relays between a single pair of live chains.
+impl Default for QuantumTunnelConfig {
+    fn default() -> Self {
+        Self {
+            cosmos: Some(CosmosChainConfig::default()),
+            substrate: Some(SubstrateChainConfig::default()),
+            chains: BTreeMap::new(),
+            paths: BTreeMap::new(),
+            metrics: None,
+            health: None,
+            shutdown: ShutdownConfig::default(),
+        }
+    }
+}
+
+impl QuantumTunnelConfig {
+    /// The cosmos and substrate chain configured without `paths`.
+    pub fn pair(&self) -> Result<(&CosmosChainConfig, &SubstrateChainConfig), String> {
+        match (&self.cosmos, &self.substrate) {
+            (Some(cosmos), Some(substrate)) => Ok((cosmos, substrate)),
+            _ => Err("both cosmos and substrate chains have to be configured".to_string()),
+        }
+    }
+
+    /// Mutable access to the cosmos and substrate chain configured without `paths`.
+    pub fn pair_mut(
+        &mut self,
+    ) -> Result<(&mut CosmosChainConfig, &mut SubstrateChainConfig), String> {
+        match (&mut self.cosmos, &mut self.substrate) {
+            (Some(cosmos), Some(substrate)) => Ok((cosmos, substrate)),
+            _ => Err("both cosmos and substrate chains have to be configured".to_string()),
+        }
+    }
+
+    /// Whether a single chain is simulated on its own. Its headers are then relayed to
+    /// an in-process light client of its own kind, as if to another chain of that kind.
+    pub fn same_kind_simulation(&self) -> bool {
+        matches!(
+            (&self.cosmos, &self.substrate),
+            (Some(CosmosChainConfig::Simulation(_)), None)
+                | (None, Some(SubstrateChainConfig::Simulation(_)))
+        )
+    }
+
+    /// Tells each chain of the single pair what it needs to know about the other one:
+    /// whether it is simulated, and its chain id if both are live.
+    pub fn link_chains(&mut self) {
+        match (&mut self.cosmos, &mut self.substrate) {
+            // Both configuration are simulation, light clients are run in-process.
+            (
+                Some(CosmosChainConfig::Simulation(cosmos_cfg)),
+                Some(SubstrateChainConfig::Simulation(substrate_cfg)),
+            ) => {
+                cosmos_cfg.is_other_side_simulation = true;
+                substrate_cfg.is_other_side_simulation = true;
+            }
+            (
+                Some(CosmosChainConfig::Real(cosmos_cfg)),
+                Some(SubstrateChainConfig::Real(substrate_cfg)),
+            ) => {
+                cosmos_cfg.is_other_side_simulation = false;
+                substrate_cfg.is_other_side_simulation = false;
+                cosmos_cfg.counterparty_chain_id = substrate_cfg.chain_id.clone();
+                substrate_cfg.counterparty_chain_id = cosmos_cfg.chain_id.clone();
+                cosmos_cfg.counterparty = Some(substrate_cfg.clone());
+            }
+            (Some(CosmosChainConfig::Real(cfg)), Some(SubstrateChainConfig::Simulation(_))) => {
+                cfg.is_other_side_simulation = true;
+            }
+            (Some(CosmosChainConfig::Simulation(_)), Some(SubstrateChainConfig::Real(cfg))) => {
+                cfg.is_other_side_simulation = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Resolves chains of every path. Configuration of both chains is copied into the path,
+    /// with client ids and counterparty chain ids of the path filled in.
+    pub fn relay_paths(&self) -> Result<Vec<RelayPath>, String> {
+        if self.paths.is_empty() {
+            return Err("no paths are configured".to_string());
+        }
+        if self.cosmos.is_some() || self.substrate.is_some() {
+            return Err(
+                "either cosmos and substrate chains, or chains and paths can be configured"
+                    .to_string(),
+            );
+        }
+        self.paths
+            .iter()
+            .map(|(name, path)| {
+                let (mut cosmos, mut substrate) =
+                    match (self.chains.get(&path.cosmos), self.chains.get(&path.substrate)) {
+                        (Some(ChainConfig::Cosmos(cosmos)), Some(ChainConfig::Substrate(substrate))) => {
+                            (cosmos.clone(), substrate.clone())
+                        }
+                        (None, _) => {
+                            return Err(format!("unknown chain {} in path {}", path.cosmos, name))
+                        }
+                        (_, None) => {
+                            return Err(format!("unknown chain {} in path {}", path.substrate, name))
+                        }
+                        (Some(ChainConfig::Substrate(_)), Some(ChainConfig::Cosmos(_))) => {
+                            return Err(format!(
+                                "chains of path {} are swapped: {} is a substrate chain and {} is a cosmos chain",
+                                name, path.cosmos, path.substrate
+                            ))
+                        }
+                        (Some(ChainConfig::Cosmos(_)), Some(ChainConfig::Cosmos(_))) => {
+                            return Err(format!(
+                                "path {} is between two cosmos chains, {} and {}; {}",
+                                name, path.cosmos, path.substrate, SAME_KIND_UNSUPPORTED
+                            ))
+                        }
+                        (Some(ChainConfig::Substrate(_)), Some(ChainConfig::Substrate(_))) => {
+                            return Err(format!(
+                                "path {} is between two substrate chains, {} and {}; {}",
+                                name, path.cosmos, path.substrate, SAME_KIND_UNSUPPORTED
+                            ))
+                        }
+                    };
+                cosmos.name = path.cosmos.clone();
+                cosmos.path = Some(name.clone());
+                cosmos.counterparty_name = path.substrate.clone();
+                substrate.name = path.substrate.clone();
+                substrate.path = Some(name.clone());
+                substrate.counterparty_name = path.cosmos.clone();
+                cosmos.counterparty_chain_id = substrate.chain_id.clone();
+                substrate.counterparty_chain_id = cosmos.chain_id.clone();
+                cosmos.counterparty = Some(substrate.clone());
+                Ok(RelayPath {
+                    name: name.clone(),
+                    cosmos_chain: path.cosmos.clone(),
+                    substrate_chain: path.substrate.clone(),
+                    cosmos,
+                    substrate,
+                    cosmos_client_id: path.cosmos_client_id.clone(),
+                    substrate_client_id: path.substrate_client_id.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Relaying between live chains of the same kind is not implemented. Cosmos SDK chains
+/// host 07-tendermint clients natively, but quantum tunnel only submits wasm client
+/// messages of the wormhole module, and no substrate runtime it relays to hosts a GRANDPA
+/// light client.
+const SAME_KIND_UNSUPPORTED: &str =
+    "relaying between live chains of the same kind is not implemented, only a single simulated chain can be relayed to an in-process light client of its own kind";
+
+/// Named Live Chain Configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ChainConfig {
+    /// Live cosmos chain
+    #[serde(rename = "cosmos")]
+    Cosmos(CosmosConfig),
+
+    /// Live substrate chain
+    #[serde(rename = "substrate")]
+    Substrate(SubstrateConfig),
+}
+
+impl ChainConfig {
+    /// Name of environment variable holding signer seed of chain `name`,
+    /// e.g. `GAIA_TESTNET_SIGNER_SEED` for `gaia-testnet`.
+    pub fn signer_seed_var(name: &str) -> String {
+        let name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}_SIGNER_SEED", name)
+    }
+}
+
+/// Relay Path Configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathConfig {
+    /// name of the cosmos chain
+    pub cosmos: String,
+    /// name of the substrate chain
+    pub substrate: String,
+    /// substrate light client on cosmos chain to resume; a new one is created if not set.
+    #[serde(default)]
+    pub cosmos_client_id: Option<String>,
+    /// cosmos light client on substrate chain to resume; a new one is created if not set.
+    #[serde(default)]
+    pub substrate_client_id: Option<String>,
+}
+
+/// Path with configuration of both of its chains resolved.
+#[derive(Clone, Debug)]
+pub struct RelayPath {
+    /// name of the path
+    pub name: String,
+    /// name of the cosmos chain
+    pub cosmos_chain: String,
+    /// name of the substrate chain
+    pub substrate_chain: String,
+    /// configuration of the cosmos chain
+    pub cosmos: CosmosConfig,
+    /// configuration of the substrate chain
+    pub substrate: SubstrateConfig,
+    /// substrate light client on cosmos chain to resume
+    pub cosmos_client_id: Option<String>,
+    /// cosmos light client on substrate chain to resume
+    pub substrate_client_id: Option<String>,
+}
+
+/// Metrics Endpoint Configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct MetricsConfig {
+    /// address metrics http server listens on, e.g. 127.0.0.1:9100
+    pub listen_addr: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: "127.0.0.1:9100".to_owned(),
+        }
+    }
+}
+
+/// Health Check Endpoint Configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct HealthConfig {
+    /// address health check http server listens on, e.g. 127.0.0.1:9101
+    pub listen_addr: String,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: "127.0.0.1:9101".to_owned(),
+        }
+    }
+}
+
+/// Shutdown Configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ShutdownConfig {
+    /// how long to wait for transactions and extrinsics in flight to be confirmed, e.g. 60s
+    pub drain_timeout: String,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            drain_timeout: "60s".to_owned(),
+        }
+    }
 }
 
 /// Cosmos chain specific configuration enum
@@ -60,6 +347,15 @@ pub struct CosmosConfig {
     pub rpc_addr: String,
     /// address of cosmos rest service
     pub lcd_addr: String,
+    /// addresses of websockets of other nodes of the chain, used when `rpc_addr` fails.
+    #[serde(default)]
+    pub fallback_rpc_addrs: Vec<String>,
+    /// addresses of rest services of other nodes of the chain, used when `lcd_addr` fails.
+    #[serde(default)]
+    pub fallback_lcd_addrs: Vec<String>,
+    /// address of websocket of a second cosmos node, headers are cross-checked against it if set.
+    #[serde(default)]
+    pub witness_rpc_addr: Option<String>,
     /// Bip39 seed of relayer account on cosmos chain. Does not serialize/deserialize.
     #[serde(skip)]
     pub signer_seed: String,
@@ -77,9 +373,52 @@ pub struct CosmosConfig {
     pub max_clock_drift: String,
     /// identifier of the wasm blob uploaded into the wormhole module on cosmos chain.
     pub wasm_id: u32,
+    /// identifier of substrate light client created on cosmos chain; chosen by `client_id_scheme` if not set.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// scheme of identifiers of created substrate light clients.
+    #[serde(default)]
+    pub client_id_scheme: ClientIdScheme,
+    /// file every created client is appended to.
+    #[serde(default = "default_clients_file")]
+    pub clients_file: String,
+    /// Chain id of substrate chain, set if it is live. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub counterparty_chain_id: String,
+    /// Substrate chain, set if it is live, headers missing in substrate light client are
+    /// fetched from. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub counterparty: Option<SubstrateConfig>,
+    /// Expiry watchdog of substrate light client hosted on cosmos chain.
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    /// Retries of failed submissions to substrate light client.
+    #[serde(default)]
+    pub retry: RetryConfig,
     /// Flag indicating whether opposite side is simulation. Does not serialize/deserialize.
     #[serde(skip)]
     pub is_other_side_simulation: bool,
+    /// Name of the chain in metrics, health checks and logs: its entry in `chains`, or
+    /// `cosmos`. Does not serialize/deserialize.
+    #[serde(skip, default = "default_cosmos_name")]
+    pub name: String,
+    /// Path light client hosted on the chain belongs to, when relaying paths. Does not
+    /// serialize/deserialize.
+    #[serde(skip)]
+    pub path: Option<String>,
+    /// Name of the chain whose headers are relayed to light client hosted on the chain.
+    /// Does not serialize/deserialize.
+    #[serde(skip, default = "default_substrate_name")]
+    pub counterparty_name: String,
+    /// Status of handlers of the relayer relaying the chain. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub(crate) health: Health,
+    /// Shutdown of the relayer relaying the chain. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub(crate) shutdown: Shutdown,
+    /// Metrics of the relayer relaying the chain. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub(crate) metrics: Metrics,
 }
 
 // Default values for Cosmos Chain Configuration
@@ -89,6 +428,9 @@ impl Default for CosmosConfig {
             chain_id: "<chain_id>".to_owned(),
             rpc_addr: "http://localhost:26657/".to_owned(),
             lcd_addr: "http://localhost:1317/".to_owned(),
+            fallback_rpc_addrs: vec![],
+            fallback_lcd_addrs: vec![],
+            witness_rpc_addr: None,
             signer_seed: "".to_owned(),
             gas: 500000,
             gas_price: "0.00025stake".to_owned(),
@@ -97,7 +439,73 @@ impl Default for CosmosConfig {
             unbonding_period: "504h".to_owned(),
             max_clock_drift: "30s".to_owned(),
             wasm_id: 1,
+            client_id: None,
+            client_id_scheme: ClientIdScheme::default(),
+            clients_file: default_clients_file(),
+            counterparty_chain_id: "".to_owned(),
+            counterparty: None,
+            watchdog: WatchdogConfig::default(),
+            retry: RetryConfig::default(),
             is_other_side_simulation: false,
+            name: default_cosmos_name(),
+            path: None,
+            counterparty_name: default_substrate_name(),
+            health: Health::default(),
+            shutdown: Shutdown::default(),
+            metrics: Metrics::default(),
+        }
+    }
+}
+
+impl CosmosConfig {
+    /// Websocket addresses, primary one first.
+    pub fn rpc_addrs(&self) -> Vec<String> {
+        with_fallbacks(&self.rpc_addr, &self.fallback_rpc_addrs)
+    }
+
+    /// Rest service addresses, primary one first.
+    pub fn lcd_addrs(&self) -> Vec<String> {
+        with_fallbacks(&self.lcd_addr, &self.fallback_lcd_addrs)
+    }
+}
+
+/// Light Client Expiry Watchdog Configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct WatchdogConfig {
+    /// fraction of trusting period since last update after which latest header is force-relayed.
+    pub refresh_fraction: f64,
+    /// fraction of trusting period since last update after which expiry is reported as imminent.
+    pub alert_fraction: f64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            refresh_fraction: 0.5,
+            alert_fraction: 0.9,
+        }
+    }
+}
+
+/// Retry Policy Configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RetryConfig {
+    /// attempts of a single submission before an error which may go away is given up on.
+    pub max_attempts: u32,
+    /// delay before the first retry, doubled with every following one, e.g. 1s
+    pub initial_backoff: String,
+    /// longest delay between retries, e.g. 30s
+    pub max_backoff: String,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: "1s".to_owned(),
+            max_backoff: "30s".to_owned(),
         }
     }
 }
@@ -110,16 +518,47 @@ pub struct CosmosSimulationConfig {
     pub simulation_file_path: String,
     /// Simulation run till this specific height
     pub should_run_till_height: u64,
+    /// If set, simulation is considered successful only if light client rejects
+    /// a header with reason containing this text.
+    #[serde(default)]
+    pub expected_rejection: Option<String>,
+    /// Timing of the simulation run.
+    #[serde(default)]
+    pub timing: SimulationTiming,
+    /// trusting period of in-process tendermint light client, used when no substrate
+    /// chain is configured and headers are relayed to a simulated cosmos chain.
+    #[serde(default = "default_trusting_period")]
+    pub trusting_period: String,
+    /// clock drift tolerance of in-process tendermint light client, used when no
+    /// substrate chain is configured.
+    #[serde(default = "default_max_clock_drift")]
+    pub max_clock_drift: String,
+    /// Flag indicating whether opposite side is simulation. In that case headers of opposite
+    /// side are verified by in-process substrate light client. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub is_other_side_simulation: bool,
 }
 
 /// Substrate Chain Configuration
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct SubstrateConfig {
+    /// substrate chain id, only used in identifiers of light clients tracking it
+    #[serde(default = "default_substrate_chain_id")]
+    pub chain_id: String,
     /// address of websocket on substrate chain
     pub ws_addr: String,
     /// address of rpc socket on substrate chain
     pub rpc_addr: String,
+    /// addresses of websockets of other nodes of the chain, used when `ws_addr` fails.
+    #[serde(default)]
+    pub fallback_ws_addrs: Vec<String>,
+    /// addresses of rpc sockets of other nodes of the chain, used when `rpc_addr` fails.
+    #[serde(default)]
+    pub fallback_rpc_addrs: Vec<String>,
+    /// addresses of rpc sockets of other substrate nodes, finalized blocks are cross-checked against them.
+    #[serde(default)]
+    pub witness_rpc_addrs: Vec<String>,
     /// Bip39 seed of relayer account on substrate chain. Does not serialize/deserialize.
     #[serde(skip)]
     pub signer_seed: String,
@@ -129,25 +568,132 @@ pub struct SubstrateConfig {
     pub unbonding_period: String,
     /// clock drift tolerance.
     pub max_clock_drift: String,
+    /// identifier of cosmos light client created on substrate chain; chosen by `client_id_scheme` if not set.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// scheme of identifiers of created cosmos light clients.
+    #[serde(default)]
+    pub client_id_scheme: ClientIdScheme,
+    /// file every created client is appended to.
+    #[serde(default = "default_clients_file")]
+    pub clients_file: String,
+    /// Chain id of cosmos chain, set if it is live. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub counterparty_chain_id: String,
+    /// Expiry watchdog of tendermint light client hosted on substrate chain.
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    /// Retries of failed submissions to tendermint light client.
+    #[serde(default)]
+    pub retry: RetryConfig,
     /// Flag indicating whether opposite side is simulation or not. Does not serialize/deserialize.
     #[serde(skip)]
     pub is_other_side_simulation: bool,
+    /// Name of the chain in metrics, health checks and logs: its entry in `chains`, or
+    /// `substrate`. Does not serialize/deserialize.
+    #[serde(skip, default = "default_substrate_name")]
+    pub name: String,
+    /// Path light client hosted on the chain belongs to, when relaying paths. Does not
+    /// serialize/deserialize.
+    #[serde(skip)]
+    pub path: Option<String>,
+    /// Name of the chain whose headers are relayed to light client hosted on the chain.
+    /// Does not serialize/deserialize.
+    #[serde(skip, default = "default_cosmos_name")]
+    pub counterparty_name: String,
+    /// Status of handlers of the relayer relaying the chain. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub(crate) health: Health,
+    /// Shutdown of the relayer relaying the chain. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub(crate) shutdown: Shutdown,
+    /// Metrics of the relayer relaying the chain. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub(crate) metrics: Metrics,
 }
 
 impl Default for SubstrateConfig {
     fn default() -> Self {
         Self {
+            chain_id: default_substrate_chain_id(),
             ws_addr: "ws://localhost:9944/".to_owned(),
             rpc_addr: "http://localhost:9933/".to_owned(),
+            fallback_ws_addrs: vec![],
+            fallback_rpc_addrs: vec![],
+            witness_rpc_addrs: vec![],
             signer_seed: "".to_owned(),
             trusting_period: "72h".into(),
             unbonding_period: "504h".into(),
             max_clock_drift: "30s".into(),
+            client_id: None,
+            client_id_scheme: ClientIdScheme::default(),
+            clients_file: default_clients_file(),
+            counterparty_chain_id: "".to_owned(),
+            watchdog: WatchdogConfig::default(),
+            retry: RetryConfig::default(),
             is_other_side_simulation: false,
+            name: default_substrate_name(),
+            path: None,
+            counterparty_name: default_cosmos_name(),
+            health: Health::default(),
+            shutdown: Shutdown::default(),
+            metrics: Metrics::default(),
         }
     }
 }
 
+impl SubstrateConfig {
+    /// Websocket addresses, primary one first.
+    pub fn ws_addrs(&self) -> Vec<String> {
+        with_fallbacks(&self.ws_addr, &self.fallback_ws_addrs)
+    }
+
+    /// Rpc socket addresses, primary one first.
+    pub fn rpc_addrs(&self) -> Vec<String> {
+        with_fallbacks(&self.rpc_addr, &self.fallback_rpc_addrs)
+    }
+}
+
+fn with_fallbacks(primary: &str, fallbacks: &[String]) -> Vec<String> {
+    let mut addrs = vec![primary.to_owned()];
+    addrs.extend(fallbacks.iter().cloned());
+    addrs
+}
+
+/// Define the default file created clients are recorded in when it is not set in config.
+fn default_clients_file() -> String {
+    client_id::DEFAULT_CLIENTS_FILE.to_owned()
+}
+
+fn default_cosmos_name() -> String {
+    "cosmos".to_owned()
+}
+
+fn default_substrate_name() -> String {
+    "substrate".to_owned()
+}
+
+/// Define the default substrate chain id when it is not set in config.
+fn default_substrate_chain_id() -> String {
+    "substrate".to_owned()
+}
+
+/// Scheme of identifiers of created light clients
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientIdScheme {
+    /// Random lowercase letters.
+    Random,
+    /// `<counterparty-chain-id>-<n>` with the lowest `n` not taken yet.
+    Counterparty,
+}
+
+impl Default for ClientIdScheme {
+    fn default() -> Self {
+        ClientIdScheme::Random
+    }
+}
+
 /// Substrate Chain Simulation Configuration
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -157,4 +703,102 @@ pub struct SubstrateSimulationConfig {
     /// Simulation should run till this specific height
     /// to be considered successful.
     pub should_run_till_height: u64,
+    /// If set, simulation is considered successful only if light client rejects
+    /// a header with reason containing this text.
+    #[serde(default)]
+    pub expected_rejection: Option<String>,
+    /// Timing of the simulation run.
+    #[serde(default)]
+    pub timing: SimulationTiming,
+    /// trusting period of in-process tendermint light client, e.g. 72h
+    #[serde(default = "default_trusting_period")]
+    pub trusting_period: String,
+    /// clock drift tolerance of in-process tendermint light client.
+    #[serde(default = "default_max_clock_drift")]
+    pub max_clock_drift: String,
+    /// Flag indicating whether opposite side is simulation. In that case headers of opposite
+    /// side are verified by in-process tendermint light client. Does not serialize/deserialize.
+    #[serde(skip)]
+    pub is_other_side_simulation: bool,
+}
+
+/// Define the default trusting period when it is not set in simulation config.
+fn default_trusting_period() -> String {
+    SubstrateConfig::default().trusting_period
+}
+
+/// Define the default clock drift when it is not set in simulation config.
+fn default_max_clock_drift() -> String {
+    SubstrateConfig::default().max_clock_drift
+}
+
+/// Simulation Timing Configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct SimulationTiming {
+    /// interval at which simulation handlers poll their channels, e.g. 1s
+    pub poll_interval: String,
+    /// RFC3339 instant at which virtual clock starts; wall clock time if not set.
+    pub virtual_now: Option<String>,
+    /// speed of virtual clock relative to wall clock; 0 freezes it at `virtual_now`.
+    pub replay_speed: f64,
+    /// Use timestamp of the header being verified as current time, so fixtures never expire.
+    pub header_time_as_now: bool,
+}
+
+impl Default for SimulationTiming {
+    fn default() -> Self {
+        Self {
+            poll_interval: "1s".to_owned(),
+            virtual_now: None,
+            replay_speed: 1.0,
+            header_time_as_now: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChainConfig, QuantumTunnelConfig};
+
+    #[test]
+    fn test_paths_resolve_chains() {
+        let data = std::fs::read_to_string("test_data/paths_config.json").unwrap();
+        let mut config: QuantumTunnelConfig = serde_json::from_str(&data).unwrap();
+        let paths = config.relay_paths().unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].name, "testing-node-a");
+        assert_eq!(paths[0].cosmos.counterparty_chain_id, "node-a");
+        assert_eq!(paths[0].substrate.counterparty_chain_id, "testing");
+        assert_eq!(paths[0].cosmos_client_id.as_deref(), Some("nodeaclient"));
+        assert_eq!(paths[1].substrate.rpc_addr, "http://localhost:9934/");
+        assert_eq!(paths[1].substrate_client_id, None);
+        assert_eq!(ChainConfig::signer_seed_var("node-a"), "NODE_A_SIGNER_SEED");
+
+        config.paths.get_mut("testing-node-b").unwrap().substrate = "testing".to_owned();
+        let err = config.relay_paths().unwrap_err();
+        assert!(err.contains("between two cosmos chains"), "{}", err);
+        assert!(err.contains("not implemented"), "{}", err);
+
+        let path = config.paths.get_mut("testing-node-b").unwrap();
+        path.cosmos = "node-b".to_owned();
+        let err = config.relay_paths().unwrap_err();
+        assert!(err.contains("are swapped"), "{}", err);
+    }
+
+    #[test]
+    fn test_single_simulated_chain_is_same_kind_simulation() {
+        for file in &[
+            "test_data/simulated_cosmos_to_cosmos_config.json",
+            "test_data/simulated_substrate_to_substrate_config.json",
+        ] {
+            let data = std::fs::read_to_string(file).unwrap();
+            let config: QuantumTunnelConfig = serde_json::from_str(&data).unwrap();
+            assert!(config.same_kind_simulation(), "{}", file);
+            assert!(config.pair().is_err());
+        }
+        let data = std::fs::read_to_string("test_data/simulated_both_chains_config.json").unwrap();
+        let config: QuantumTunnelConfig = serde_json::from_str(&data).unwrap();
+        assert!(!config.same_kind_simulation());
+    }
 }
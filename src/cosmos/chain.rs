@@ -0,0 +1,201 @@
+//! Cosmos chain as a source of tendermint headers and as a host of substrate light clients.
+
+use crate::chain::{ClientStateQuerier, DestinationChain, HandlerFuture, SourceChain};
+use crate::client_state::ClientState;
+use crate::config::{CosmosChainConfig, CosmosConfig, CosmosSimulationConfig, SimulationTiming};
+use crate::cosmos::connection::CosmosConnection;
+use crate::cosmos::types::TMHeader;
+use crate::cosmos::Handler as CosmosHandler;
+use crate::error::RelayError;
+use crate::failover;
+use crate::monitoring::MonitoringEvent;
+use crate::report::HeaderRecord;
+use crate::substrate::chain::InProcessTendermintClient;
+use crate::substrate::types::SignedBlockWithAuthoritySet;
+use crossbeam_channel::{Receiver, Sender};
+use futures::future::{FutureExt, LocalBoxFuture};
+use parse_duration::parse;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Cosmos chain configured by `cfg`, live or simulated, as a source of headers.
+pub fn source(
+    cfg: CosmosChainConfig,
+) -> Box<dyn SourceChain<Header = (TMHeader, Vec<tendermint::validator::Info>)>> {
+    match cfg {
+        CosmosChainConfig::Real(cfg) => Box::new(cfg),
+        CosmosChainConfig::Simulation(cfg) => Box::new(cfg),
+    }
+}
+
+/// Cosmos chain configured by `cfg`, live or simulated, as a host of substrate light
+/// client. Live chain is submitted to through `connection` if given, which may be shared
+/// with other paths. If both sides are simulation, headers are verified by in-process
+/// light client.
+pub fn destination(
+    cfg: CosmosChainConfig,
+    connection: Option<Rc<CosmosConnection>>,
+) -> Box<dyn DestinationChain<Header = SignedBlockWithAuthoritySet>> {
+    match cfg {
+        CosmosChainConfig::Real(cfg) => Box::new(CosmosDestination { cfg, connection }),
+        CosmosChainConfig::Simulation(cfg) if cfg.is_other_side_simulation => {
+            Box::new(InProcessGrandpaClient {
+                chain: "cosmos".to_string(),
+                timing: cfg.timing,
+            })
+        }
+        CosmosChainConfig::Simulation(cfg) => Box::new(cfg),
+    }
+}
+
+/// Cosmos light client verifying headers of simulated cosmos chain configured by `cfg`
+/// in-process, in place of another cosmos chain hosting it.
+pub fn same_kind_client(
+    cfg: &CosmosSimulationConfig,
+) -> Box<dyn DestinationChain<Header = (TMHeader, Vec<tendermint::validator::Info>)>> {
+    Box::new(InProcessTendermintClient {
+        chain: "cosmos".to_string(),
+        trusting_period: cfg.trusting_period.clone(),
+        max_clock_drift: cfg.max_clock_drift.clone(),
+        timing: cfg.timing.clone(),
+    })
+}
+
+/// Live cosmos chain read by the relayer.
+impl SourceChain for CosmosConfig {
+    type Header = (TMHeader, Vec<tendermint::validator::Info>);
+
+    fn read_headers(
+        self: Box<Self>,
+        outchan: Sender<Self::Header>,
+        _monitoring_inchan: Receiver<MonitoringEvent>,
+    ) -> HandlerFuture {
+        CosmosHandler::recv_handler(*self, outchan).boxed_local()
+    }
+}
+
+/// Simulated cosmos chain, whose headers are read from the simulation file. It finishes
+/// once light client of opposite chain is done with them.
+impl SourceChain for CosmosSimulationConfig {
+    type Header = (TMHeader, Vec<tendermint::validator::Info>);
+
+    fn read_headers(
+        self: Box<Self>,
+        outchan: Sender<Self::Header>,
+        monitoring_inchan: Receiver<MonitoringEvent>,
+    ) -> HandlerFuture {
+        CosmosHandler::simulate_recv_handler(
+            self.simulation_file_path,
+            self.should_run_till_height,
+            self.expected_rejection,
+            self.timing,
+            outchan,
+            monitoring_inchan,
+        )
+        .boxed_local()
+    }
+
+    fn is_finite(&self) -> bool {
+        true
+    }
+}
+
+/// Live cosmos chain hosting substrate light client. It is submitted to through
+/// `connection` if given, which may be shared with other paths.
+pub struct CosmosDestination {
+    pub cfg: CosmosConfig,
+    pub connection: Option<Rc<CosmosConnection>>,
+}
+
+impl DestinationChain for CosmosDestination {
+    type Header = SignedBlockWithAuthoritySet;
+
+    fn update_client(
+        self: Box<Self>,
+        client_id: Option<String>,
+        inchan: Receiver<Self::Header>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> HandlerFuture {
+        CosmosHandler::send_handler(
+            self.cfg,
+            self.connection,
+            client_id,
+            inchan,
+            monitoring_outchan,
+            report_outchan,
+        )
+        .boxed_local()
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+}
+
+/// Simulated cosmos chain opposite a live substrate chain, which cannot ingest its
+/// headers, so they are drained.
+impl DestinationChain for CosmosSimulationConfig {
+    type Header = SignedBlockWithAuthoritySet;
+
+    fn update_client(
+        self: Box<Self>,
+        _client_id: Option<String>,
+        inchan: Receiver<Self::Header>,
+        _monitoring_outchan: Sender<MonitoringEvent>,
+        _report_outchan: Sender<HeaderRecord>,
+    ) -> HandlerFuture {
+        CosmosHandler::drain_handler(*self, inchan).boxed_local()
+    }
+}
+
+/// Substrate light client verifying GRANDPA justifications in-process, in place of the
+/// chain hosting it, which is simulated as well, or not configured at all when a simulated
+/// substrate chain is relayed to one of its own kind.
+pub struct InProcessGrandpaClient {
+    /// Chain the client stands in for, as named in relay events.
+    pub chain: String,
+    /// Timing of the simulation run.
+    pub timing: SimulationTiming,
+}
+
+impl DestinationChain for InProcessGrandpaClient {
+    type Header = SignedBlockWithAuthoritySet;
+
+    fn update_client(
+        self: Box<Self>,
+        _client_id: Option<String>,
+        inchan: Receiver<Self::Header>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> HandlerFuture {
+        CosmosHandler::in_process_send_handler(*self, inchan, monitoring_outchan, report_outchan)
+            .boxed_local()
+    }
+}
+
+impl ClientStateQuerier for CosmosConfig {
+    fn trusting_period(&self) -> Result<Duration, RelayError> {
+        parse(&self.trusting_period).map_err(RelayError::config)
+    }
+
+    fn client_state(
+        &self,
+        client_id: String,
+    ) -> LocalBoxFuture<'static, Result<ClientState, RelayError>> {
+        let (metrics, name, addrs) = (self.metrics.clone(), self.name.clone(), self.lcd_addrs());
+        async move {
+            failover::call(&metrics, &name, &addrs, |lcd_addr| {
+                CosmosHandler::query_client_state(lcd_addr, client_id.clone())
+            })
+            .await
+        }
+        .boxed_local()
+    }
+
+    fn client_ids(&self) -> LocalBoxFuture<'static, Result<Vec<String>, RelayError>> {
+        let (metrics, name, addrs) = (self.metrics.clone(), self.name.clone(), self.lcd_addrs());
+        async move { failover::call(&metrics, &name, &addrs, CosmosHandler::query_client_ids).await }
+            .boxed_local()
+    }
+}
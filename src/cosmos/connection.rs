@@ -0,0 +1,220 @@
+//! Relayer account on a cosmos chain. Send handlers of every path hosting light clients
+//! on the chain share one connection, so they sign with the same key, reuse connections
+//! to LCD nodes and take turns submitting transactions.
+
+use crate::config::CosmosConfig;
+use crate::cosmos::types::{
+    AccountQueryResponse, BalanceQueryResponse, Coins, StdFee, StdSignature, StdTx, TxRpcResponse,
+};
+use crate::cosmos::Handler as CosmosHandler;
+use crate::error::RelayError;
+use crate::failover;
+use crate::metrics::Metrics;
+use bytes::buf::Buf;
+use futures::lock::Mutex;
+use hyper::client::HttpConnector;
+use hyper::{body::aggregate, Body, Client, Method, Request};
+use k256::ecdsa::SigningKey;
+use log::*;
+use serde::de::DeserializeOwned;
+
+/// Relayer account on a cosmos chain, along with LCD nodes it submits txs through.
+pub struct CosmosConnection {
+    /// Name of the chain in metrics and logs.
+    chain: String,
+    /// Metrics of the relayer relaying the chain.
+    metrics: Metrics,
+    chain_id: String,
+    lcd_addrs: Vec<String>,
+    signer: SigningKey,
+    /// Bech32 address of relayer account.
+    pub address: String,
+    http: Client<HttpConnector>,
+    /// Held from reading account sequence until tx using it is broadcast, so concurrent
+    /// txs do not reuse it.
+    submission: Mutex<()>,
+}
+
+impl CosmosConnection {
+    /// Derives relayer account of the chain configured by `cfg` from its signer seed.
+    pub fn new(cfg: &CosmosConfig) -> Result<Self, RelayError> {
+        let (signer, _, address) =
+            CosmosHandler::signer_from_seed(cfg.signer_seed.clone()).map_err(RelayError::Signer)?;
+        Ok(CosmosConnection {
+            chain: cfg.name.clone(),
+            metrics: cfg.metrics.clone(),
+            chain_id: cfg.chain_id.clone(),
+            lcd_addrs: cfg.lcd_addrs(),
+            signer,
+            address,
+            http: Client::new(),
+            submission: Mutex::new(()),
+        })
+    }
+
+    /// Signs tx carrying `msgs` with relayer account and broadcasts it, returns hash of the tx.
+    pub async fn submit_tx(
+        &self,
+        msgs: Vec<serde_json::Value>,
+        fee: StdFee,
+        memo: String,
+    ) -> Result<String, RelayError> {
+        let mut tx = StdTx {
+            msg: msgs,
+            fee,
+            signatures: vec![],
+            memo,
+        };
+
+        // Sequence is read and used under the lock, so concurrent txs do not reuse it.
+        let _submission = self.submission.lock().await;
+        let (account_number, sequence) =
+            failover::call(&self.metrics, &self.chain, &self.lcd_addrs, |lcd_addr| {
+                self.get_account(lcd_addr)
+            })
+            .await?;
+        let bytes_to_sign = tx.get_sign_bytes(self.chain_id.clone(), account_number, sequence);
+        let signature_block = StdSignature::sign(&self.signer, bytes_to_sign);
+        tx.signatures.push(signature_block);
+        let wrapped_tx = serde_json::json!({"tx": &tx, "mode":"block", "account_number": &account_number.to_string(), "sequence": &sequence.to_string()});
+
+        let json_bytes = serde_json::to_vec(&wrapped_tx).map_err(RelayError::encoding)?;
+
+        let tx_response: TxRpcResponse =
+            match failover::call(&self.metrics, &self.chain, &self.lcd_addrs, |lcd_addr| {
+                self.broadcast_tx(json_bytes.clone(), lcd_addr)
+            })
+            .await
+            {
+                Ok(tx_response) => tx_response,
+                // Tx may have reached the node, so rather than being sent again, account
+                // sequence tells whether the chain took it.
+                Err(RelayError::Rpc(e)) => return Err(self.unconfirmed(sequence, e).await),
+                Err(e) => return Err(e),
+            };
+        // Fee is charged once tx makes it into a block, regardless of its result,
+        // while tx rejected by check is never included.
+        if tx_response.height > 0 {
+            for coin in &tx.fee.amount {
+                self.metrics
+                    .observe_fee(&self.chain, coin.denom(), coin.amount() as f64);
+            }
+        }
+        match failover::call(&self.metrics, &self.chain, &self.lcd_addrs, |lcd_addr| {
+            self.get_balances(lcd_addr)
+        })
+        .await
+        {
+            Ok(balances) => {
+                for coin in &balances {
+                    self.metrics
+                        .observe_balance(&self.chain, coin.denom(), coin.amount() as f64);
+                }
+            }
+            Err(e) => warn!("Unable to query balance of relayer account: {}", e),
+        }
+        if tx_response.code != 0 {
+            error!(
+                "Tx failed log: {:?} at height: {:?}",
+                tx_response.raw_log, tx_response.height
+            );
+            return Err(RelayError::TxRejected {
+                codespace: Some(tx_response.codespace),
+                code: Some(tx_response.code),
+                log: tx_response.raw_log,
+            });
+        };
+        Ok(tx_response.txhash)
+    }
+
+    /// Broadcasts signed tx and waits until it is included in a block. Only failing to
+    /// connect to the node is a transport error, any later failure may come after the
+    /// node received the tx.
+    async fn broadcast_tx(
+        &self,
+        json_bytes: Vec<u8>,
+        lcd_addr: String,
+    ) -> Result<TxRpcResponse, RelayError> {
+        let tx_req = Request::builder()
+            .method(Method::POST)
+            .uri(lcd_addr + "txs")
+            .header("content-type", "application/json")
+            .body(Body::from(json_bytes))
+            .map_err(RelayError::config)?;
+
+        // Await the response...
+        let tx_resp = self.http.request(tx_req).await.map_err(|e| {
+            if e.is_connect() {
+                RelayError::transport(e)
+            } else {
+                RelayError::rpc(e)
+            }
+        })?;
+        let tx_body = aggregate(tx_resp).await.map_err(RelayError::rpc)?;
+        let tx_rstr = String::from_utf8(tx_body.bytes().to_vec()).map_err(RelayError::rpc)?;
+        let tx_response: TxRpcResponse = serde_json::from_str(&tx_rstr).map_err(RelayError::rpc)?;
+        Ok(tx_response)
+    }
+
+    /// Tells what became of tx signed with account `sequence`, whose broadcast failed with
+    /// `err` after it may have reached the node. Once the chain takes the tx, sequence of
+    /// the account moves past it, and the tx must not be sent again.
+    async fn unconfirmed(&self, sequence: u64, err: String) -> RelayError {
+        match failover::call(&self.metrics, &self.chain, &self.lcd_addrs, |lcd_addr| {
+            self.get_account(lcd_addr)
+        })
+        .await
+        {
+            Ok((_, current)) if current > sequence => RelayError::Unconfirmed(format!(
+                "{}, tx with account sequence {} was taken by the chain nevertheless",
+                err, sequence
+            )),
+            Ok(_) => RelayError::Rpc(format!(
+                "{}, tx with account sequence {} was not taken by the chain",
+                err, sequence
+            )),
+            Err(e) => RelayError::Unconfirmed(format!(
+                "{}, unable to tell whether tx with account sequence {} was taken by the chain: {}",
+                err, sequence, e
+            )),
+        }
+    }
+
+    async fn get_account(&self, lcd_addr: String) -> Result<(u64, u64), RelayError> {
+        let response: AccountQueryResponse = self
+            .query(lcd_addr + "auth/accounts/" + &self.address)
+            .await?;
+        Ok((
+            response.result.value.account_number,
+            response.result.value.sequence,
+        ))
+    }
+
+    async fn get_balances(&self, lcd_addr: String) -> Result<Coins, RelayError> {
+        let response: BalanceQueryResponse = self
+            .query(lcd_addr + "bank/balances/" + &self.address)
+            .await?;
+        Ok(response.result)
+    }
+
+    /// Queries LCD node at `uri`. Queries change nothing, so any failure to get a response
+    /// is a transport error, and the query is sent to another node.
+    async fn query<T: DeserializeOwned>(&self, uri: String) -> Result<T, RelayError> {
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(""))
+            .map_err(RelayError::config)?;
+
+        // Await the response...
+        let resp = self
+            .http
+            .request(req)
+            .await
+            .map_err(RelayError::transport)?;
+        let body = aggregate(resp).await.map_err(RelayError::transport)?;
+        let rstr = String::from_utf8(body.bytes().to_vec()).map_err(RelayError::rpc)?;
+        serde_json::from_str(&rstr).map_err(RelayError::rpc)
+    }
+}
@@ -1,33 +1,67 @@
-use crate::config::{CosmosChainConfig, CosmosConfig};
+use crate::client_id;
+use crate::client_state::{self, ClientState, StoredState, CLIENT_STATE_QUERY_INTERVAL};
+use crate::config::{CosmosConfig, CosmosSimulationConfig, SimulationTiming, SubstrateConfig};
+use crate::cosmos::chain::InProcessGrandpaClient;
+use crate::cosmos::connection::CosmosConnection;
 use crate::cosmos::crypto::{privkey_from_seed, seed_from_mnemonic};
+use crate::cosmos::misbehaviour::MisbehaviourMonitor;
 use crate::cosmos::types::simulation::Message;
 use crate::cosmos::types::{
-    AccountQueryResponse, DecCoin, MsgCreateWasmClient, MsgUpdateWasmClient, StdFee, StdMsg,
-    StdSignature, StdTx, TMHeader, TxRpcResponse,
+    ClientStateQueryResponse, ClientStatesQueryResponse, ConsensusStateQueryResponse, DecCoin,
+    MsgCreateWasmClient, MsgUpdateWasmClient, StdFee, StdMsg, TMHeader,
 };
-use crate::error::ErrorKind;
+use crate::error::{ErrorKind, RelayError};
+use crate::failover;
+use crate::report::HeaderRecord;
 use crate::error::ErrorKind::{MalformedResponse, UnexpectedPayload};
+use crate::monitoring::{MonitoringEvent, SimulationMonitor};
+use crate::progress;
+use crate::retry::{Decision, RetryPolicy};
 use crate::substrate::types::{CreateSignedBlockWithAuthoritySet, SignedBlockWithAuthoritySet};
-use crate::utils::{generate_client_id, to_string};
+use crate::substrate::verifier::GrandpaVerifier;
+use crate::substrate::Handler as SubstrateHandler;
+use crate::utils::clock::VirtualClock;
+use crate::utils::to_string;
+use crate::watchdog::ExpiryWatchdog;
 use bytes::buf::Buf;
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use futures::try_join;
 use hyper::{body::aggregate, Body, Client as HClient, Method, Request};
 use log::*;
 use parse_duration::parse;
+use serde::de::DeserializeOwned;
 use k256::{elliptic_curve::SecretKey, ecdsa::SigningKey};
 use k256::EncodedPoint as Secp256k1;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::path::Path;
+use std::rc::Rc;
 use std::string::ToString;
+use std::time::{Duration, Instant, SystemTime};
 use subtle_encoding::bech32;
+use tendermint::block::Height;
 use tendermint::net::Address;
 use tendermint_light_client::{AccountId, PublicKey};
-use tendermint_rpc::{WebSocketClient, SubscriptionClient, Client};
+use tendermint_rpc::{WebSocketClient, Subscription, SubscriptionClient, Client};
+use tokio::task::JoinHandle;
 use tendermint_rpc::query::EventType;
 use futures::StreamExt;
 use url::Url;
 
+/// Number of headers substrate light client keeps in its storage.
+const MAX_HEADERS_ALLOWED_TO_STORE: u64 = 256;
+/// Number of headers substrate light client accepts without a justification.
+const MAX_HEADERS_ALLOWED_BETWEEN_JUSTIFICATIONS: u64 = 512;
+
+/// Address of the node, client connected to it, handle of its driver and subscription to new blocks.
+type NewBlockSubscription = (
+    String,
+    WebSocketClient,
+    JoinHandle<Result<(), tendermint_rpc::Error>>,
+    Subscription,
+);
+
 pub struct CosmosHandler {}
 impl CosmosHandler {
     fn parse_tm_addr(url: Url) -> Result<Address, String> {
@@ -44,26 +78,16 @@ impl CosmosHandler {
         })
     }
 
-    /// Receive handler entrypoint
-    /// Branches to different internal methods depending upon whether
-    /// configuration is `Real` or `Simulation`
+    /// Receive handler of live cosmos chain.
     pub async fn recv_handler(
-        cfg: CosmosChainConfig,
+        cfg: CosmosConfig,
         outchan: Sender<(TMHeader, Vec<tendermint::validator::Info>)>,
-        monitoring_inchan: Receiver<(bool, u64)>,
-    ) -> Result<(), String> {
-        match cfg {
-            CosmosChainConfig::Real(cfg) => Self::chain_recv_handler(cfg, outchan).await,
-            CosmosChainConfig::Simulation(cfg) => {
-                Self::simulate_recv_handler(
-                    cfg.simulation_file_path,
-                    cfg.should_run_till_height,
-                    outchan,
-                    monitoring_inchan,
-                )
-                .await
-            }
-        }
+    ) -> Result<(), RelayError> {
+        let (chain, health) = (cfg.name.clone(), cfg.health.clone());
+        health.starting(&chain, None, "recv");
+        let result = Self::chain_recv_handler(cfg, outchan).await;
+        health.stopped(&chain, None, "recv", &result);
+        result
     }
 
     /// Simulation receive handler, which as the name suggests
@@ -73,112 +97,195 @@ impl CosmosHandler {
     pub async fn simulate_recv_handler(
         test_file: String,
         should_run_till_height: u64,
+        expected_rejection: Option<String>,
+        timing: SimulationTiming,
         outchan: Sender<(TMHeader, Vec<tendermint::validator::Info>)>,
-        monitoring_inchan: Receiver<(bool, u64)>,
-    ) -> Result<(), String> {
+        monitoring_inchan: Receiver<MonitoringEvent>,
+    ) -> Result<(), RelayError> {
+        let clock = VirtualClock::from_config(&timing).map_err(RelayError::Config)?;
         let simulation_data =
-            std::fs::read_to_string(Path::new(test_file.as_str())).map_err(to_string)?;
+            std::fs::read_to_string(Path::new(test_file.as_str())).map_err(RelayError::config)?;
         let stringified_headers: Vec<&str> = simulation_data.split("\n\n").collect();
         let number_of_simulated_headers = stringified_headers.len();
         for str in stringified_headers {
-            let payload: Message = serde_json::from_str(str).map_err(to_string)?;
+            let payload: Message = serde_json::from_str(str).map_err(RelayError::config)?;
             outchan
                 .try_send((payload.header, payload.next_validators))
-                .map_err(to_string)?;
+                .map_err(RelayError::disconnected)?;
         }
 
-        let mut number_of_headers_ingested_till = 0;
-        let mut successfully_ingested_till = 0;
+        let mut monitor = SimulationMonitor::new(
+            "cosmos",
+            number_of_simulated_headers,
+            should_run_till_height,
+            expected_rejection,
+        );
         // Let's wait for the receive handler on other side to catch up
         loop {
-            let result = monitoring_inchan.try_recv();
-            if result.is_err() {
-                match result.err().unwrap() {
-                    TryRecvError::Empty => {
-                        // Let's wait for data to appear
-                        tokio::time::delay_for(core::time::Duration::new(1, 0)).await;
-                    }
-                    TryRecvError::Disconnected => {
-                        return Err(
-                            "monitoring channel of substrate send handler is disconnected"
-                                .to_string(),
-                        );
-                    }
+            let event = match monitoring_inchan.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Empty) => {
+                    // Let's wait for data to appear
+                    clock.tick().await;
+                    continue;
                 }
-                continue;
-            }
-
-            let (terminated, reported_height) = result.unwrap();
-            if !terminated {
-                successfully_ingested_till = reported_height;
-                number_of_headers_ingested_till += 1;
-            }
-
-            if terminated || (number_of_headers_ingested_till == number_of_simulated_headers) {
-                if successfully_ingested_till != should_run_till_height {
-                    return Err(format!("Ingesting simulation data failed on cosmos chain. Expected to ingest headers till height: {}, ingested till: {}", should_run_till_height, successfully_ingested_till));
-                } else {
-                    info!(
-                        "Cosmos headers simulated successfully. Ingested headers till height: {}",
-                        successfully_ingested_till
-                    );
+                Err(TryRecvError::Disconnected) => {
+                    return Err(RelayError::disconnected(
+                        "monitoring channel of substrate send handler is disconnected",
+                    ));
                 }
-                break;
-            } else {
+            };
+
+            if let Some(outcome) = monitor.observe(event) {
+                let successfully_ingested_till = outcome.map_err(RelayError::Verification)?;
                 info!(
-                    "cosmos light client has successfully ingested header at: {}",
+                    "Cosmos headers simulated successfully. Ingested headers till height: {}",
                     successfully_ingested_till
                 );
+                return Ok(());
             }
         }
-        Ok(())
     }
 
     /// Subscribes to new blocks from Websocket, and pushes TMHeader objects into the Channel.
+    /// If witness is configured, every header is cross-checked against it. On conflict,
+    /// an alert is raised and relaying halts, as no substrate runtime is known to accept
+    /// evidence of misbehaviour. When subscription ends, it is resumed on the next
+    /// configured node.
     pub async fn chain_recv_handler(
         cfg: CosmosConfig,
         outchan: Sender<(TMHeader, Vec<tendermint::validator::Info>)>,
-    ) -> Result<(), String> {
-        let rpc_url = Url::parse(&cfg.rpc_addr).map_err(to_string)?;
-        let tm_addr = CosmosHandler::parse_tm_addr(rpc_url)?;
+    ) -> Result<(), RelayError> {
+        let mut monitor = match &cfg.witness_rpc_addr {
+            Some(witness_rpc_addr) => {
+                let witness_url = Url::parse(witness_rpc_addr).map_err(RelayError::config)?;
+                let witness_addr =
+                    CosmosHandler::parse_tm_addr(witness_url).map_err(RelayError::Config)?;
+                let trusting_period = parse(&cfg.trusting_period).map_err(RelayError::config)?;
+                let max_clock_drift = parse(&cfg.max_clock_drift).map_err(RelayError::config)?;
+                Some(
+                    MisbehaviourMonitor::connect(witness_addr, trusting_period, max_clock_drift)
+                        .await?,
+                )
+            }
+            None => None,
+        };
+        cfg.health.running(&cfg.name, None, "recv");
+        let mut previous_block: Option<TMHeader> = None;
+        let mut backoff = failover::Backoff::new();
+
+        loop {
+            let (rpc_addr, mut client, driver_handle, mut subs) =
+                match failover::call_until_success(
+                    &cfg.metrics,
+                    &cfg.name,
+                    &cfg.rpc_addrs(),
+                    Self::subscribe_new_blocks,
+                )
+                .await
+                {
+                    Ok(subscription) => subscription,
+                    Err(e) => {
+                        if let Some(monitor) = monitor.take() {
+                            if let Err(e) = monitor.close().await {
+                                warn!("Unable to close connection to witness: {}", e);
+                            }
+                        }
+                        return Err(e);
+                    }
+                };
+
+            let ended = loop {
+                let response = match tokio::time::timeout(failover::STALL_TIMEOUT, subs.next())
+                    .await
+                {
+                    Ok(Some(response)) => response,
+                    Ok(None) => break "subscription to new blocks ended".to_owned(),
+                    Err(_) => {
+                        break format!("no new block received for {:?}", failover::STALL_TIMEOUT)
+                    }
+                };
+                let response = Self::recv_data(response, &mut client).await;
+                if response.is_err() {
+                    error!(
+                        "Error: {} while processing tendermint node response",
+                        response.err().unwrap()
+                    );
+                    continue;
+                }
+                let header = response.unwrap();
+                backoff.reset();
+                cfg.metrics
+                    .observe_header(&cfg.name, header.signed_header.header.height.value());
+                cfg.health.activity(&cfg.name, None, "recv");
+                if let Some(monitor) = monitor.as_mut() {
+                    match monitor.check(&header, &client).await {
+                        Ok(None) => {}
+                        // Tendermint light client has to be frozen manually, relaying stops
+                        // before a header of a possible fork reaches it.
+                        Ok(Some(evidence)) => {
+                            let e = RelayError::Misbehaviour(format!(
+                                "primary and witness nodes committed different blocks at height: {}, primary: {}, witness: {}",
+                                header.signed_header.header.height,
+                                evidence.header_1.signed_header.commit.block_id.hash,
+                                evidence.header_2.signed_header.commit.block_id.hash
+                            ));
+                            error!("ALERT: {}, relaying halted", e);
+                            return Err(e);
+                        }
+                        // Witness is advisory, an unreachable or lagging one does not stop relaying.
+                        Err(e) => warn!("{}, relaying header unchecked", e),
+                    }
+                }
+                if previous_block.is_none() {
+                    previous_block = Some(header);
+                    continue;
+                }
+                outchan
+                    .try_send((previous_block.unwrap(), header.validator_set.clone()))
+                    .map_err(RelayError::disconnected)?;
+                previous_block = Some(header);
+            };
+
+            // Signal to the driver to terminate.
+            let _ = client.close().map_err(to_string);
+
+            // Await the driver's termination to ensure proper connection closure.
+            let result = driver_handle
+                .await
+                .map_err(to_string)
+                .and_then(|result| result.map_err(to_string));
+            failover::failed(
+                &cfg.metrics,
+                &cfg.name,
+                &rpc_addr,
+                &format!("{}: {:?}", ended, result),
+            );
+            // Blocks may be missed until subscription resumes, so validator set of the block
+            // following the last one received is not known. Light client verifies the first
+            // header received afterwards by skipping, as it does for any non-adjacent one.
+            previous_block = None;
+            // Node which drops every subscription right away is not hammered with new ones.
+            tokio::time::delay_for(backoff.delay()).await;
+        }
+    }
+
+    /// Subscribes to new blocks produced on node at `rpc_addr`.
+    async fn subscribe_new_blocks(rpc_addr: String) -> Result<NewBlockSubscription, RelayError> {
+        let rpc_url = Url::parse(&rpc_addr).map_err(RelayError::config)?;
+        let tm_addr = CosmosHandler::parse_tm_addr(rpc_url).map_err(RelayError::Config)?;
         info!("opening websocket to to {:?}", tm_addr.clone());
         let (mut client, driver) = WebSocketClient::new(tm_addr.clone())
             .await
-            .map_err(to_string)?;
+            .map_err(RelayError::transport)?;
         let driver_handle = tokio::spawn(async move { driver.run().await });
 
         info!("connected websocket to {:?}", tm_addr.clone());
-        let mut subs = client
+        let subs = client
             .subscribe(EventType::NewBlock.into())
             .await
-            .map_err(to_string)?;
-        let mut previous_block: Option<TMHeader> = None;
-
-        while let Some(response) = subs.next().await {
-            let response = Self::recv_data(response, &mut client).await;
-            if response.is_err() {
-                error!(
-                    "Error: {} while processing tendermint node response",
-                    response.err().unwrap()
-                );
-                continue;
-            }
-            let header = response.unwrap();
-            if previous_block.is_none() {
-                previous_block = Some(header);
-                continue;
-            }
-            outchan
-                .try_send((previous_block.unwrap(), header.validator_set.clone()))
-                .map_err(to_string)?;
-            previous_block = Some(header);
-        }
-
-        // Signal to the driver to terminate.
-        let _ = client.close().map_err(to_string);
-
-        // Await the driver's termination to ensure proper connection closure.
-        driver_handle.await.unwrap().map_err(to_string)
+            .map_err(RelayError::transport)?;
+        Ok((rpc_addr, client, driver_handle, subs))
     }
 
     async fn recv_data(
@@ -218,7 +325,73 @@ impl CosmosHandler {
         }
     }
 
-    fn signer_from_seed(
+    /// Fetches header at `height` along with validator set of the following block,
+    /// which light client needs to verify the next header. Without `height`, the
+    /// header preceding the latest block is fetched, as it is the newest one whose
+    /// next validator set is known.
+    pub async fn fetch_header(
+        cfg: &CosmosConfig,
+        height: Option<u64>,
+    ) -> Result<(TMHeader, Vec<tendermint::validator::Info>), RelayError> {
+        failover::call(&cfg.metrics, &cfg.name, &cfg.rpc_addrs(), |rpc_addr| {
+            Self::fetch_header_from(rpc_addr, height)
+        })
+        .await
+    }
+
+    async fn fetch_header_from(
+        rpc_addr: String,
+        height: Option<u64>,
+    ) -> Result<(TMHeader, Vec<tendermint::validator::Info>), RelayError> {
+        let rpc_url = Url::parse(&rpc_addr).map_err(RelayError::config)?;
+        let tm_addr = CosmosHandler::parse_tm_addr(rpc_url).map_err(RelayError::Config)?;
+        let (mut client, driver) = WebSocketClient::new(tm_addr)
+            .await
+            .map_err(RelayError::transport)?;
+        let driver_handle = tokio::spawn(async move { driver.run().await });
+
+        let result = async {
+            let height = match height {
+                Some(height) => height,
+                None => {
+                    client
+                        .status()
+                        .await
+                        .map_err(RelayError::transport)?
+                        .sync_info
+                        .latest_block_height
+                        .value()
+                        - 1
+                }
+            };
+            let height = Height::try_from(height).map_err(RelayError::config)?;
+            let (signed_header_response, validator_set_response, next_validator_set_response) =
+                try_join!(
+                    client.commit(height),
+                    client.validators(height),
+                    client.validators(height.increment())
+                )
+                .map_err(RelayError::transport)?;
+            Ok((
+                TMHeader {
+                    signed_header: signed_header_response.signed_header,
+                    validator_set: validator_set_response.validators,
+                },
+                next_validator_set_response.validators,
+            ))
+        }
+        .await;
+
+        // Signal to the driver to terminate.
+        let _ = client.close();
+        driver_handle
+            .await
+            .map_err(RelayError::transport)?
+            .map_err(RelayError::transport)?;
+        result
+    }
+
+    pub(crate) fn signer_from_seed(
         seed: String,
     ) -> Result<(SigningKey, PublicKey, String), String> {
         let key = seed_from_mnemonic(seed).map_err(to_string)?;
@@ -229,59 +402,168 @@ impl CosmosHandler {
         Ok((signing_key, tmpubkey, address))
     }
 
-    /// Send handler entrypoint
-    /// Branches to different internal methods depending upon whether
-    /// configuration is `Real` or `Simulation`
+    /// Send handler of live cosmos chain.
     /// If other side is simulation, some additional bookkeeping is done to
     /// make sure `simulation_recv_handler` gets accurate data.
+    /// Live chain is submitted to through `connection` shared with other paths, or
+    /// through a connection of its own if none is given.
     pub async fn send_handler(
-        cfg: CosmosChainConfig,
+        cfg: CosmosConfig,
+        connection: Option<Rc<CosmosConnection>>,
         client_id: Option<String>,
         inchan: Receiver<SignedBlockWithAuthoritySet>,
-        monitoring_outchan: Sender<(bool, u64)>,
-    ) -> Result<(), String> {
-        match cfg {
-            CosmosChainConfig::Real(cfg) => {
-                if cfg.is_other_side_simulation {
-                    // Swallow up the error to prevent quantum tunnel to terminate. This will give simulation data reader the chance to print the result.
-                    let result = Self::chain_send_handler(
-                        cfg,
-                        client_id,
-                        inchan,
-                        monitoring_outchan.clone(),
-                    )
-                    .await;
-                    // Send signal to simulation_recv_handler that receive handler is terminated
-                    monitoring_outchan.try_send((true, 0)).map_err(to_string)?;
-                    if result.is_err() {
-                        error!("Error occurred while trying to send simulated cosmos data to cosmos chain: {}", result.err().unwrap());
-                    }
-                    // This gives simulation_recv_handler time to print result and then exit.
-                    futures::future::pending::<()>().await;
-                    Ok(())
-                } else {
-                    Self::chain_send_handler(cfg, client_id, inchan, monitoring_outchan).await
-                }
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> Result<(), RelayError> {
+        let (chain, path, health) = (cfg.name.clone(), cfg.path.clone(), cfg.health.clone());
+        health.starting(&chain, path.as_deref(), "send");
+        let is_other_side_simulation = cfg.is_other_side_simulation;
+        let result = match connection {
+            Some(connection) => Ok(connection),
+            None => CosmosConnection::new(&cfg).map(Rc::new),
+        };
+        let result = match result {
+            Ok(connection) => {
+                Self::chain_send_handler(
+                    cfg,
+                    connection,
+                    client_id,
+                    inchan,
+                    monitoring_outchan.clone(),
+                    report_outchan,
+                )
+                .await
             }
-            // If we are running simulation, we just drain incoming headers.
-            CosmosChainConfig::Simulation(_cfg) => {
-                loop {
-                    let result = inchan.try_recv();
-                    if result.is_err() {
-                        match result.err().unwrap() {
-                            TryRecvError::Disconnected => {
-                                return Err(
-                                    "Substrate chain-data channel's input end is disconnected."
-                                        .to_string(),
-                                );
-                            }
-                            _ => {}
-                        }
+            Err(e) => Err(e),
+        };
+        health.stopped(&chain, path.as_deref(), "send", &result);
+        if is_other_side_simulation {
+            // Swallow up the error to prevent quantum tunnel to terminate. This will give simulation data reader the chance to print the result.
+            // Send signal to simulation_recv_handler that receive handler is terminated
+            monitoring_outchan
+                .try_send(MonitoringEvent::Terminated)
+                .map_err(RelayError::disconnected)?;
+            if result.is_err() {
+                error!(
+                    "Error occurred while trying to send simulated substrate data to cosmos chain: {}",
+                    result.err().unwrap()
+                );
+            }
+            // This gives simulation_recv_handler time to print result and then exit.
+            futures::future::pending::<()>().await;
+            Ok(())
+        } else {
+            result
+        }
+    }
+
+    /// Send handler of simulated cosmos chain, which cannot ingest any headers of live
+    /// substrate chain, so it just drains them.
+    pub async fn drain_handler(
+        cfg: CosmosSimulationConfig,
+        inchan: Receiver<SignedBlockWithAuthoritySet>,
+    ) -> Result<(), RelayError> {
+        let clock = VirtualClock::from_config(&cfg.timing).map_err(RelayError::Config)?;
+        loop {
+            let result = inchan.try_recv();
+            if result.is_err() {
+                match result.err().unwrap() {
+                    TryRecvError::Disconnected => {
+                        return Err(RelayError::disconnected(
+                            "Substrate chain-data channel's input end is disconnected.",
+                        ));
                     }
-                    // Compulsory delay of 1 second to prevent busy loop.
-                    tokio::time::delay_for(core::time::Duration::new(1, 0)).await;
+                    _ => {}
                 }
             }
+            // Compulsory delay to prevent busy loop.
+            clock.tick().await;
+        }
+    }
+
+    /// Verifies headers of simulated substrate chain with in-process substrate light client
+    /// until it has no more headers to send, then signals `simulate_recv_handler` of the
+    /// substrate chain to decide the outcome. Never returns afterwards, so the outcome is
+    /// reported by `simulate_recv_handler` even if verification failed.
+    pub async fn in_process_send_handler(
+        client: InProcessGrandpaClient,
+        inchan: Receiver<SignedBlockWithAuthoritySet>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> Result<(), RelayError> {
+        let result =
+            Self::simulate_send_handler(client, inchan, monitoring_outchan.clone(), report_outchan)
+                .await;
+        // Send signal to simulation_recv_handler that receive handler is terminated
+        monitoring_outchan
+            .try_send(MonitoringEvent::Terminated)
+            .map_err(RelayError::disconnected)?;
+        if result.is_err() {
+            error!("Error occurred while trying to send simulated substrate data to in-process substrate light client: {}", result.err().unwrap());
+        }
+        // This gives simulation_recv_handler time to print result and then exit.
+        futures::future::pending::<()>().await;
+        Ok(())
+    }
+
+    /// Feeds header data received from opposite chain to substrate light client
+    /// verification logic running inside quantum tunnel, in place of `client.chain`.
+    /// Returns once opposite chain has no more headers to send.
+    pub async fn simulate_send_handler(
+        client: InProcessGrandpaClient,
+        inchan: Receiver<SignedBlockWithAuthoritySet>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> Result<(), RelayError> {
+        let clock = VirtualClock::from_config(&client.timing).map_err(RelayError::Config)?;
+        let mut verifier: Option<GrandpaVerifier> = None;
+        loop {
+            let msg = match inchan.try_recv() {
+                Ok(msg) => msg,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+                Err(TryRecvError::Empty) => {
+                    clock.tick().await;
+                    continue;
+                }
+            };
+
+            let current_height = msg.block.block.header.number;
+            let started = Instant::now();
+            let result = match verifier.as_mut() {
+                None => {
+                    verifier = Some(GrandpaVerifier::new(
+                        msg,
+                        MAX_HEADERS_ALLOWED_BETWEEN_JUSTIFICATIONS,
+                    ));
+                    info!(
+                        "Created in-process substrate light client at height: {}",
+                        current_height
+                    );
+                    Ok(None)
+                }
+                Some(verifier) => verifier.ingest(msg).map(|_| None).map_err(|e| {
+                    RelayError::Verification(format!(
+                        "in-process substrate light client failed to ingest header at height: {}, error: {}",
+                        current_height, e
+                    ))
+                }),
+            };
+            report_outchan
+                .try_send(HeaderRecord::new(
+                    &client.chain,
+                    current_height as u64,
+                    &result.clone().map_err(to_string),
+                    started.elapsed(),
+                ))
+                .map_err(RelayError::disconnected)?;
+            monitoring_outchan
+                .try_send(MonitoringEvent::from_result(current_height as u64, &result))
+                .map_err(RelayError::disconnected)?;
+            result?;
+            info!(
+                "In-process substrate light client verified header at height: {}",
+                current_height
+            );
         }
     }
 
@@ -289,83 +571,498 @@ impl CosmosHandler {
     /// light client payload and sends it to substrate light client running in
     /// cosmos chain.
     /// If client id is not passed, first payload sent would be for creating the client.
+    /// Failed submissions are retried as `cfg.retry` allows; substrate light client
+    /// accepts only contiguous headers, so once retries run out relaying halts.
     pub async fn chain_send_handler(
         cfg: CosmosConfig,
+        connection: Rc<CosmosConnection>,
         client_id: Option<String>,
         inchan: Receiver<SignedBlockWithAuthoritySet>,
-        monitoring_outchan: Sender<(bool, u64)>,
-    ) -> Result<(), String> {
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> Result<(), RelayError> {
+        let retry = RetryPolicy::new(&cfg.retry, false)?;
         let mut new_client = false;
         let id = if client_id.is_none() {
             new_client = true;
-            generate_client_id()
+            retry
+                .call("list substrate light clients", || Self::new_client_id(&cfg))
+                .await?
         } else {
             client_id.unwrap()
         };
+        // Substrate light client accepts only contiguous headers, so every header is
+        // relayed and watchdog can only alert about imminent expiry.
+        let trusting_period = parse(&cfg.trusting_period).map_err(RelayError::config)?;
+        let mut watchdog = ExpiryWatchdog::alert_only(
+            &cfg.health,
+            &cfg.metrics,
+            &cfg.name,
+            cfg.path.as_deref(),
+            trusting_period,
+            cfg.watchdog.clone(),
+        );
+        cfg.health.running(&cfg.name, cfg.path.as_deref(), "send");
+
+        // Headers already ingested by client, e.g. before relayer restarted, are skipped.
+        let mut client_state = None;
+        let mut client_state_queried_at = Instant::now();
+        if !new_client {
+            client_state = Self::refresh_client_state(&cfg, &id, trusting_period).await?;
+            if let Some(state) = &client_state {
+                watchdog.seed(state);
+                info!(
+                    "Substrate light client {} is at height: {}, relaying headers from height: {}",
+                    id,
+                    state.latest_height,
+                    state.latest_height + 1
+                );
+            }
+        }
+        // Client whose state cannot be queried is resumed after the header it was last
+        // sent, as recorded in progress file.
+        let recorded = match &client_state {
+            None if !new_client => progress::last_relayed(&cfg.name, &id),
+            _ => None,
+        };
+        if let Some(height) = recorded {
+            info!(
+                "Substrate light client {} was last sent header at height: {} according to {}, relaying headers from height: {}",
+                id,
+                height,
+                progress::PROGRESS_FILE,
+                height + 1
+            );
+        }
 
+        // Height of the latest header client is known to have ingested.
+        let mut ingested_till = client_state
+            .as_ref()
+            .map(|state| state.latest_height)
+            .or(recorded);
+        // Headers client is missing, fetched from substrate chain ahead of queued ones.
+        let mut backfill: VecDeque<SignedBlockWithAuthoritySet> = VecDeque::new();
+        // Header whose submission failed, along with the number of the next attempt.
+        let mut retrying: Option<(SignedBlockWithAuthoritySet, u32)> = None;
         loop {
-            let result = inchan.try_recv();
-            let msg = if result.is_err() {
-                match result.err().unwrap() {
-                    TryRecvError::Disconnected => {
-                        return Err(
-                            "Substrate chain-data channel's input end is disconnected.".to_string()
-                        );
+            watchdog.check();
+            // Submission in flight has been confirmed by now, queued headers are left.
+            if cfg.shutdown.requested() {
+                info!(
+                    "Shutting down, substrate light client {} is no longer updated",
+                    id
+                );
+                return Ok(());
+            }
+            let (msg, attempt) = match retrying
+                .take()
+                .or_else(|| backfill.pop_front().map(|msg| (msg, 1)))
+            {
+                Some(retried) => retried,
+                None => match inchan.try_recv() {
+                    Ok(msg) => (msg, 1),
+                    Err(TryRecvError::Disconnected) => {
+                        return Err(RelayError::disconnected(
+                            "Substrate chain-data channel's input end is disconnected.",
+                        ));
                     }
-                    _ => {
+                    Err(TryRecvError::Empty) => {
                         warn!("Did not receive any data from Substrate chain-data channel. Retrying in a second ...");
                         tokio::time::delay_for(core::time::Duration::new(1, 0)).await;
                         continue;
                     }
-                }
-            } else {
-                result.unwrap()
+                },
             };
 
             let current_height = msg.block.block.header.number;
+            // Failed attempt may have made it into a block nevertheless, so client state
+            // is queried again before the header is submitted once more.
+            if !new_client
+                && (attempt > 1 || client_state_queried_at.elapsed() >= CLIENT_STATE_QUERY_INTERVAL)
+            {
+                client_state = Self::refresh_client_state(&cfg, &id, trusting_period)
+                    .await?
+                    .or(client_state);
+                client_state_queried_at = Instant::now();
+                if let Some(state) = &client_state {
+                    ingested_till = ingested_till.max(Some(state.latest_height));
+                }
+            }
+            let ingested = match &client_state {
+                Some(state) => state.knows(current_height as u64),
+                None => recorded.map_or(false, |height| current_height as u64 <= height),
+            };
+            if ingested {
+                info!(
+                    "Skipping header at height: {}, substrate light client {} has ingested it already",
+                    current_height, id
+                );
+                continue;
+            }
+            // Substrate light client accepts only contiguous headers, so the ones it is
+            // missing, e.g. finalized while relayer was stopped, are fetched first.
+            if let (Some(till), Some(substrate_cfg)) = (ingested_till, &cfg.counterparty) {
+                if current_height as u64 > till + 1 {
+                    info!(
+                        "Substrate light client {} is at height: {}, fetching headers up to height: {} from substrate chain",
+                        id,
+                        till,
+                        current_height - 1
+                    );
+                    match Self::fetch_missing(substrate_cfg, till + 1, current_height as u64).await
+                    {
+                        Ok(missing) => {
+                            backfill.extend(missing);
+                            backfill.push_back(msg);
+                            continue;
+                        }
+                        Err(e) => match retry.decide(&e, attempt) {
+                            Decision::Retry(backoff) => {
+                                warn!(
+                                    "Attempt {} to fetch headers missing in substrate light client {} failed: {}, retrying in {:?}",
+                                    attempt, id, e, backoff
+                                );
+                                tokio::time::delay_for(backoff).await;
+                                retrying = Some((msg, attempt + 1));
+                                continue;
+                            }
+                            Decision::Skip | Decision::Halt => return Err(e),
+                        },
+                    }
+                }
+            }
 
-            if new_client {
-                new_client = false;
-                CosmosHandler::create_client(cfg.clone(), id.clone(), msg).await?;
+            let started = Instant::now();
+            let result = if new_client {
+                CosmosHandler::submit_create_client(&cfg, &connection, id.clone(), msg.clone())
+                    .await
             } else {
-                CosmosHandler::update_client(cfg.clone(), msg, id.clone()).await?;
+                CosmosHandler::submit_update_client(&cfg, &connection, msg.clone(), id.clone())
+                    .await
+            };
+            // Tx taken by the chain is not sent again, client tells whether it was applied.
+            let result = match result {
+                Err(RelayError::Unconfirmed(e)) => {
+                    Self::confirm_submission(
+                        &cfg,
+                        &retry,
+                        &id,
+                        new_client,
+                        current_height as u64,
+                        &e,
+                    )
+                    .await
+                }
+                result => result,
+            };
+            if new_client {
+                if let Ok(hash) = &result {
+                    new_client = false;
+                    Self::record_client(&cfg, &id, hash);
+                }
+            }
+            cfg.metrics.observe_submission(
+                &cfg.counterparty_name,
+                &cfg.name,
+                cfg.path.as_deref(),
+                current_height as u64,
+                result.is_ok(),
+            );
+            let decision = result.as_ref().err().map(|e| retry.decide(e, attempt));
+            if let Some(Decision::Retry(backoff)) = decision {
+                warn!(
+                    "Attempt {} to relay header at height: {} to substrate light client {} failed: {}, retrying in {:?}",
+                    attempt,
+                    current_height,
+                    id,
+                    result.unwrap_err(),
+                    backoff
+                );
+                tokio::time::delay_for(backoff).await;
+                retrying = Some((msg, attempt + 1));
+                continue;
+            }
+            if let Ok(hash) = &result {
+                ingested_till = Some(current_height as u64);
+                watchdog.updated();
+                cfg.health.activity(&cfg.name, cfg.path.as_deref(), "send");
+                if let Err(e) = progress::relayed(&cfg.name, &id, current_height as u64, hash) {
+                    warn!(
+                        "Unable to write relaying progress to {}: {}",
+                        progress::PROGRESS_FILE,
+                        e
+                    );
+                }
             }
 
+            let record = HeaderRecord::new(
+                &cfg.name,
+                current_height as u64,
+                &result.clone().map(Some).map_err(to_string),
+                started.elapsed(),
+            );
             if cfg.is_other_side_simulation {
+                let result = result.clone().map(Some);
+                report_outchan
+                    .try_send(record)
+                    .map_err(RelayError::disconnected)?;
                 monitoring_outchan
-                    .try_send((false, current_height as u64))
-                    .map_err(to_string)?;
+                    .try_send(MonitoringEvent::from_result(current_height as u64, &result))
+                    .map_err(RelayError::disconnected)?;
+            } else {
+                // Nobody may be listening to relay events of live chains.
+                let _ = report_outchan.try_send(record);
             }
+            if let Err(e) = result {
+                if decision != Some(Decision::Skip) {
+                    return Err(e);
+                }
+                warn!(
+                    "Skipping header at height: {}, substrate light client {} could not ingest it: {}",
+                    current_height, id, e
+                );
+            }
+        }
+    }
+
+    /// Chooses identifier of a new substrate light client, making sure it is not taken.
+    pub async fn new_client_id(cfg: &CosmosConfig) -> Result<String, RelayError> {
+        // Id which looks free only because clients could not be listed may be taken.
+        let existing = failover::call(
+            &cfg.metrics,
+            &cfg.name,
+            &cfg.lcd_addrs(),
+            Self::query_client_ids,
+        )
+        .await?;
+        client_id::choose(
+            cfg.client_id.clone(),
+            &cfg.client_id_scheme,
+            &cfg.counterparty_chain_id,
+            &existing,
+        )
+        .map_err(RelayError::Config)
+    }
+
+    /// Tells whether header at `height`, whose tx was taken by the chain with result unknown
+    /// as `err` tells, reached client `client_id`, which the tx was to create if `create` is
+    /// set. Result stays unknown if the client cannot be queried.
+    async fn confirm_submission(
+        cfg: &CosmosConfig,
+        retry: &RetryPolicy,
+        client_id: &str,
+        create: bool,
+        height: u64,
+        err: &str,
+    ) -> Result<String, RelayError> {
+        let addrs = cfg.lcd_addrs();
+        let ingested = if create {
+            retry
+                .call("list substrate light clients", || {
+                    failover::call(&cfg.metrics, &cfg.name, &addrs, Self::query_client_ids)
+                })
+                .await
+                .map(|ids| ids.iter().any(|id| id == client_id))
+        } else {
+            retry
+                .call("query state of substrate light client", || {
+                    failover::call(&cfg.metrics, &cfg.name, &addrs, |lcd_addr| {
+                        Self::query_client_state(lcd_addr, client_id.to_owned())
+                    })
+                })
+                .await
+                .map(|state| state.knows(height))
+        };
+        match ingested {
+            Ok(ingested) => client_state::confirm(client_id, height, ingested, err),
+            Err(e) => Err(RelayError::Unconfirmed(format!(
+                "{}, unable to query substrate light client {}: {}",
+                err, client_id, e
+            ))),
         }
     }
 
+    /// Fetches finalized substrate headers from height `from` up to, but not including, `to`.
+    async fn fetch_missing(
+        substrate_cfg: &SubstrateConfig,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<SignedBlockWithAuthoritySet>, RelayError> {
+        let mut headers = vec![];
+        for height in from..to {
+            headers.push(SubstrateHandler::fetch_header(substrate_cfg, Some(height)).await?);
+        }
+        Ok(headers)
+    }
+
+    /// Logs and persists identifier of created substrate light client, so it can be
+    /// passed to subsequent runs.
+    pub fn record_client(cfg: &CosmosConfig, client_id: &str, hash: &str) {
+        info!(
+            "Created substrate light client with id: {}, pass it with --cosmos-client to resume relaying",
+            client_id
+        );
+        if let Err(e) = client_id::record(
+            &cfg.clients_file,
+            "cosmos",
+            client_id,
+            &cfg.counterparty_chain_id,
+            hash,
+        ) {
+            warn!(
+                "Unable to record client {} in {}: {}",
+                client_id, cfg.clients_file, e
+            );
+        }
+    }
+
+    /// Queries state of the client and fails if it can no longer be updated.
+    /// Query errors are only logged, as client state is advisory.
+    async fn refresh_client_state(
+        cfg: &CosmosConfig,
+        client_id: &str,
+        trusting_period: Duration,
+    ) -> Result<Option<ClientState>, RelayError> {
+        match failover::call(&cfg.metrics, &cfg.name, &cfg.lcd_addrs(), |lcd_addr| {
+            Self::query_client_state(lcd_addr, client_id.to_owned())
+        })
+        .await
+        {
+            Ok(state) => {
+                state
+                    .ensure_active(trusting_period, SystemTime::now())
+                    .map_err(RelayError::Verification)?;
+                Ok(Some(state))
+            }
+            Err(e) => {
+                warn!(
+                    "Unable to query state of substrate light client {}: {}",
+                    client_id, e
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Queries state of substrate light client stored in wormhole module.
+    pub async fn query_client_state(
+        lcd_addr: String,
+        client_id: String,
+    ) -> Result<ClientState, RelayError> {
+        let response: ClientStateQueryResponse = CosmosHandler::query_lcd(format!(
+            "{}ibc/clients/{}/client-state",
+            lcd_addr, client_id
+        ))
+        .await?;
+        let state = response.result.client_state.value;
+        // Consensus state only tells the time of the latest header, client state is usable without it.
+        let consensus_state = match CosmosHandler::query_lcd::<ConsensusStateQueryResponse>(
+            format!(
+                "{}ibc/clients/{}/consensus-state/{}",
+                lcd_addr, client_id, state.latest_height
+            ),
+        )
+        .await
+        {
+            Ok(response) => Some(response.result.consensus_state.value),
+            Err(e) => {
+                warn!(
+                    "Unable to query consensus state of substrate light client {} at height: {}: {}",
+                    client_id, state.latest_height, e
+                );
+                None
+            }
+        };
+        Ok(ClientState {
+            client_id,
+            latest_height: state.latest_height,
+            latest_timestamp: consensus_state
+                .as_ref()
+                .and_then(|consensus_state| consensus_state.timestamp),
+            frozen_height: match state.frozen_height {
+                0 => None,
+                height => Some(height),
+            },
+            stored: Some(StoredState::Wasm {
+                client_state: state,
+                consensus_state,
+            }),
+        })
+    }
+
+    /// Lists identifiers of clients stored in wormhole module.
+    pub async fn query_client_ids(lcd_addr: String) -> Result<Vec<String>, RelayError> {
+        let response: ClientStatesQueryResponse =
+            CosmosHandler::query_lcd(format!("{}ibc/clients", lcd_addr)).await?;
+        Ok(response
+            .result
+            .into_iter()
+            .map(|client_state| client_state.value.id)
+            .collect())
+    }
+
+    async fn query_lcd<T: DeserializeOwned>(uri: String) -> Result<T, RelayError> {
+        let hclient = HClient::new();
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(""))
+            .map_err(RelayError::config)?;
+
+        let resp = hclient.request(req).await.map_err(RelayError::transport)?;
+        if !resp.status().is_success() {
+            return Err(RelayError::Rpc(format!(
+                "LCD responded with status: {}",
+                resp.status()
+            )));
+        }
+        let body = aggregate(resp).await.map_err(RelayError::transport)?;
+        let rstr = String::from_utf8(body.bytes().to_vec()).map_err(RelayError::rpc)?;
+        serde_json::from_str(&rstr).map_err(RelayError::rpc)
+    }
+
+    /// Creates substrate light client trusting `header` and records it in the clients
+    /// file, returns id of the client.
     pub async fn create_client(
         cfg: CosmosConfig,
         client_id: String,
         header: SignedBlockWithAuthoritySet,
-    ) -> Result<String, String> {
-        let (signer, _, address) =
-            CosmosHandler::signer_from_seed(cfg.signer_seed.clone()).map_err(to_string)?;
+    ) -> Result<String, RelayError> {
+        let connection = CosmosConnection::new(&cfg)?;
+        let hash = Self::submit_create_client(&cfg, &connection, client_id.clone(), header).await?;
+        Self::record_client(&cfg, &client_id, &hash);
+        Ok(client_id)
+    }
 
+    /// Creates substrate light client trusting `header` with tx signed by relayer
+    /// account of `connection`, returns hash of the tx.
+    pub async fn submit_create_client(
+        cfg: &CosmosConfig,
+        connection: &CosmosConnection,
+        client_id: String,
+        header: SignedBlockWithAuthoritySet,
+    ) -> Result<String, RelayError> {
         let msg = MsgCreateWasmClient {
             header: CreateSignedBlockWithAuthoritySet {
                 block: header.block,
                 authority_set: header.authority_set,
                 set_id: header.set_id,
-                max_headers_allowed_to_store: 256,
-                max_headers_allowed_between_justifications: 512,
+                max_headers_allowed_to_store: MAX_HEADERS_ALLOWED_TO_STORE,
+                max_headers_allowed_between_justifications:
+                    MAX_HEADERS_ALLOWED_BETWEEN_JUSTIFICATIONS,
             },
-            address: address.clone(),
+            address: connection.address.clone(),
             trusting_period: parse(&cfg.trusting_period)
-                .map_err(to_string)?
+                .map_err(RelayError::config)?
                 .as_nanos()
                 .to_string(),
             max_clock_drift: parse(&cfg.max_clock_drift)
-                .map_err(to_string)?
+                .map_err(RelayError::config)?
                 .as_nanos()
                 .to_string(),
             unbonding_period: parse(&cfg.unbonding_period)
-                .map_err(to_string)?
+                .map_err(RelayError::config)?
                 .as_nanos()
                 .to_string(),
             client_id: client_id.clone(),
@@ -375,35 +1072,39 @@ impl CosmosHandler {
         let m = vec![serde_json::json!({"type": MsgCreateWasmClient::get_type(), "value": &msg})];
         let f = StdFee {
             gas: cfg.gas,
-            amount: vec![DecCoin::from(cfg.gas_price).mul(cfg.gas as f64).to_coin()],
+            amount: vec![DecCoin::from(cfg.gas_price.clone())
+                .mul(cfg.gas as f64)
+                .to_coin()],
         };
 
-        let retval = CosmosHandler::submit_tx(
-            m,
-            f,
-            "".to_owned(),
-            signer,
-            address.clone(),
-            cfg.chain_id.clone(),
-            cfg.lcd_addr.clone(),
-        )
-        .await
-        .map_err(to_string)?;
-        info!("Substrate light client creation TxHash: {:?}", retval);
-        Ok(client_id.clone())
+        let retval = connection.submit_tx(m, f, "".to_owned()).await?;
+        info!(
+            "Substrate light client {} creation TxHash: {:?}",
+            client_id, retval
+        );
+        Ok(retval)
     }
 
     pub async fn update_client(
         cfg: CosmosConfig,
         header: SignedBlockWithAuthoritySet,
         client_id: String,
-    ) -> Result<String, String> {
-        let (signer, _, address) =
-            CosmosHandler::signer_from_seed(cfg.signer_seed.clone()).map_err(to_string)?;
+    ) -> Result<String, RelayError> {
+        let connection = CosmosConnection::new(&cfg)?;
+        Self::submit_update_client(&cfg, &connection, header, client_id).await
+    }
 
+    /// Updates substrate light client with `header` with tx signed by relayer account
+    /// of `connection`, returns hash of the tx.
+    pub async fn submit_update_client(
+        cfg: &CosmosConfig,
+        connection: &CosmosConnection,
+        header: SignedBlockWithAuthoritySet,
+        client_id: String,
+    ) -> Result<String, RelayError> {
         let msg = MsgUpdateWasmClient {
             header,
-            address: address.clone(),
+            address: connection.address.clone(),
             client_id: client_id.clone(),
         };
 
@@ -411,91 +1112,15 @@ impl CosmosHandler {
             vec![serde_json::json!({"type": MsgUpdateWasmClient::get_type(), "value": &msg})];
         let txfee = StdFee {
             gas: cfg.gas,
-            amount: vec![DecCoin::from(cfg.gas_price).mul(cfg.gas as f64).to_coin()],
+            amount: vec![DecCoin::from(cfg.gas_price.clone())
+                .mul(cfg.gas as f64)
+                .to_coin()],
         };
 
-        let retval = CosmosHandler::submit_tx(
-            msgs,
-            txfee,
-            "".to_owned(),
-            signer,
-            address.clone(),
-            cfg.chain_id.clone(),
-            cfg.lcd_addr.clone(),
-        )
-        .await
-        .map_err(to_string)?;
+        let retval = connection.submit_tx(msgs, txfee, "".to_owned()).await?;
         info!("Substrate light client updation TxHash: {:?}", retval);
         Ok(retval)
     }
-
-    async fn submit_tx(
-        msgs: Vec<serde_json::Value>,
-        fee: StdFee,
-        memo: String,
-        signer: SigningKey,
-        address: String,
-        chain_id: String,
-        lcd_addr: String,
-    ) -> Result<String, String> {
-        let mut tx = StdTx {
-            msg: msgs.to_vec(),
-            fee,
-            signatures: vec![],
-            memo,
-        };
-
-        let (account_number, sequence) =
-            CosmosHandler::get_account(address, lcd_addr.clone()).await?;
-        let bytes_to_sign = tx.get_sign_bytes(chain_id, account_number, sequence);
-        let signature_block = StdSignature::sign(signer, bytes_to_sign);
-        tx.signatures.push(signature_block.clone());
-        let wrapped_tx = serde_json::json!({"tx": &tx, "mode":"block", "account_number": &account_number.to_string(), "sequence": &sequence.to_string()});
-
-        let json_bytes = serde_json::to_vec(&wrapped_tx).map_err(to_string)?;
-
-        let hclient = HClient::new();
-        let tx_req = Request::builder()
-            .method(Method::POST)
-            .uri(lcd_addr.clone() + &"txs".to_owned())
-            .header("content-type", "application/json")
-            .body(Body::from(json_bytes))
-            .map_err(to_string)?;
-
-        // Await the response...
-        let tx_resp = hclient.request(tx_req).await.map_err(to_string)?;
-        let tx_body = aggregate(tx_resp).await.map_err(to_string)?;
-        let tx_rstr = String::from_utf8(tx_body.bytes().to_vec()).map_err(to_string)?;
-        let tx_response: TxRpcResponse = serde_json::from_str(&tx_rstr).map_err(to_string)?;
-        if tx_response.code != 0 {
-            error!(
-                "Tx failed log: {:?} at height: {:?}",
-                tx_response.raw_log, tx_response.height
-            );
-            return Err(format!("Tx failed, response from node: {:?}", tx_response));
-        };
-        Ok(tx_response.txhash)
-    }
-
-    async fn get_account(account: String, lcd_addr: String) -> Result<(u64, u64), String> {
-        let hclient = HClient::new();
-        let acc_req = Request::builder()
-            .method(Method::GET)
-            .uri(lcd_addr.clone() + &"auth/accounts/".to_owned() + &account)
-            .header("content-type", "application/json")
-            .body(Body::from(""))
-            .map_err(to_string)?;
-
-        // Await the response...
-        let acc_resp = hclient.request(acc_req).await.map_err(to_string)?;
-        let acc_body = aggregate(acc_resp).await.map_err(to_string)?;
-        let acc_rstr = String::from_utf8(acc_body.bytes().to_vec()).map_err(to_string)?;
-        let response: AccountQueryResponse = serde_json::from_str(&acc_rstr).map_err(to_string)?;
-        Ok((
-            response.result.value.account_number,
-            response.result.value.sequence,
-        ))
-    }
 }
 
 #[cfg(test)]
@@ -505,6 +1130,7 @@ mod tests {
     use k256::ecdsa::Signature;
     use k256::elliptic_curve::SecretKey;
     use k256::EncodedPoint as Secp256k1;
+    use signature::Signer;
 
     const EXAMPLE_SEED: &str = "sunny source soul allow brave luggage mandate metal worth state vapor couple butter retreat solid drift cargo alley degree junk bean price element easy";
 
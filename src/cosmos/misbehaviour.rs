@@ -0,0 +1,182 @@
+//! Detection of tendermint misbehaviour. Every header received from the primary
+//! node is compared with the header a witness node reports at the same height;
+//! two different blocks committed at one height mean validators equivocated, or
+//! one of the nodes is lying, and either way the light client must not trust them.
+//! Conflicting headers are evidence only if both verify against the latest header
+//! the nodes agreed on, so a lying node cannot get the light client frozen.
+
+use crate::cosmos::types::{TMHeader, TMMisbehaviour};
+use crate::cosmos::verifier::TendermintVerifier;
+use crate::error::RelayError;
+use futures::try_join;
+use log::*;
+use std::time::{Duration, SystemTime};
+use tendermint::net::Address;
+use tendermint_rpc::{Client, WebSocketClient};
+use tokio::task::JoinHandle;
+
+pub struct MisbehaviourMonitor {
+    witness_addr: Address,
+    client: WebSocketClient,
+    driver_handle: JoinHandle<Result<(), tendermint_rpc::Error>>,
+    trusting_period: Duration,
+    max_clock_drift: Duration,
+    /// Latest header primary and witness nodes agreed on.
+    trusted: Option<TMHeader>,
+}
+
+impl MisbehaviourMonitor {
+    /// Connects to the witness node. Conflicting headers are verified the way light
+    /// client would, with given trusting period and clock drift.
+    pub async fn connect(
+        witness_addr: Address,
+        trusting_period: Duration,
+        max_clock_drift: Duration,
+    ) -> Result<Self, RelayError> {
+        info!("opening websocket to witness {:?}", witness_addr);
+        let (client, driver) = WebSocketClient::new(witness_addr.clone())
+            .await
+            .map_err(RelayError::transport)?;
+        let driver_handle = tokio::spawn(async move { driver.run().await });
+        Ok(MisbehaviourMonitor {
+            witness_addr,
+            client,
+            driver_handle,
+            trusting_period,
+            max_clock_drift,
+            trusted: None,
+        })
+    }
+
+    /// Fetches header at the height of `header` from the witness and returns both
+    /// as evidence if they conflict. `primary` is the node `header` was received from.
+    pub async fn check(
+        &mut self,
+        header: &TMHeader,
+        primary: &WebSocketClient,
+    ) -> Result<Option<TMMisbehaviour>, RelayError> {
+        let height = header.signed_header.header.height;
+        let (signed_header_response, validator_set_response) =
+            try_join!(self.client.commit(height), self.client.validators(height)).map_err(|e| {
+                RelayError::Transport(format!(
+                    "unable to fetch header at height: {} from witness {:?}: {}",
+                    height, self.witness_addr, e
+                ))
+            })?;
+        let witness_header = TMHeader {
+            signed_header: signed_header_response.signed_header,
+            validator_set: validator_set_response.validators,
+        };
+        if !conflicts(header, &witness_header) {
+            self.trusted = Some(header.clone());
+            return Ok(None);
+        }
+        self.verify_conflicting(header, primary, &witness_header)
+            .await?;
+        Ok(Some(TMMisbehaviour {
+            header_1: header.clone(),
+            header_2: witness_header,
+        }))
+    }
+
+    /// Verifies both conflicting headers against the latest header nodes agreed on,
+    /// which needs more than 2/3 of each header's validators to have signed it, and
+    /// more than 1/3 of the trusted validators, as skipping verification does.
+    async fn verify_conflicting(
+        &self,
+        primary_header: &TMHeader,
+        primary: &WebSocketClient,
+        witness_header: &TMHeader,
+    ) -> Result<(), RelayError> {
+        let height = primary_header.signed_header.header.height;
+        let trusted = self.trusted.clone().ok_or_else(|| {
+            RelayError::Verification(format!(
+                "primary and witness nodes committed different blocks at height: {}, but never agreed on a header to verify them against",
+                height
+            ))
+        })?;
+        let trusted_height = trusted.signed_header.header.height;
+        let (trusted_next_validators, primary_next_validators, witness_next_validators) =
+            try_join!(
+                self.client.validators(trusted_height.increment()),
+                primary.validators(height.increment()),
+                self.client.validators(height.increment())
+            )
+            .map_err(|e| {
+                RelayError::Transport(format!(
+                    "unable to fetch validators to verify conflicting headers at height: {}: {}",
+                    height, e
+                ))
+            })?;
+        let verifier = TendermintVerifier::new(
+            trusted,
+            trusted_next_validators.validators,
+            self.trusting_period,
+            self.max_clock_drift,
+        )
+        .map_err(RelayError::Verification)?;
+        let now = SystemTime::now();
+        for (node, header, next_validators) in &[
+            (
+                "primary",
+                primary_header,
+                primary_next_validators.validators,
+            ),
+            (
+                "witness",
+                witness_header,
+                witness_next_validators.validators,
+            ),
+        ] {
+            verifier.verify(header, next_validators, now).map_err(|e| {
+                RelayError::Verification(format!(
+                    "{} header at height: {} does not verify against header at height: {} both nodes agreed on, it is not evidence of misbehaviour: {}",
+                    node, height, trusted_height, e
+                ))
+            })?;
+        }
+        Ok(())
+    }
+
+    pub async fn close(self) -> Result<(), RelayError> {
+        // Signal to the driver to terminate.
+        let _ = self.client.close();
+        self.driver_handle
+            .await
+            .map_err(RelayError::transport)?
+            .map_err(RelayError::transport)
+    }
+}
+
+/// Whether headers of the same chain at the same height commit to different blocks.
+/// Commits without signatures prove nothing, so they are never considered conflicting.
+fn conflicts(primary: &TMHeader, witness: &TMHeader) -> bool {
+    let (primary, witness) = (&primary.signed_header, &witness.signed_header);
+    primary.header.chain_id == witness.header.chain_id
+        && primary.header.height == witness.header.height
+        && !witness.commit.signatures.is_empty()
+        && primary.commit.block_id != witness.commit.block_id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::conflicts;
+    use crate::cosmos::types::simulation::Message;
+
+    #[test]
+    fn test_conflicting_block_ids_are_detected() {
+        let data = std::fs::read_to_string("test_data/cosmos_light_client_simulated.txt").unwrap();
+        let primary = serde_json::Deserializer::from_str(&data)
+            .into_iter::<Message>()
+            .next()
+            .unwrap()
+            .unwrap()
+            .header;
+        let mut witness = primary.clone();
+        assert!(!conflicts(&primary, &witness));
+        witness.signed_header.commit.block_id.hash = tendermint::Hash::None;
+        assert!(conflicts(&primary, &witness));
+        witness.signed_header.header.chain_id = "other-chain".parse().unwrap();
+        assert!(!conflicts(&primary, &witness));
+    }
+}
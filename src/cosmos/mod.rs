@@ -1,5 +1,9 @@
+pub mod chain;
+pub mod connection;
 pub mod crypto;
 pub mod handler;
+pub mod misbehaviour;
 pub mod types;
+pub mod verifier;
 
 pub type Handler = handler::CosmosHandler;
@@ -17,6 +17,14 @@ pub type StdFee = stdtx::StdFee;
 pub type DecCoin = stdtx::DecCoin;
 pub type TMUpdateClientPayload = tm::TMUpdateClientPayload;
 pub type TMCreateClientPayload = tm::TMCreateClientPayload;
+pub type TMClientInfo = tm::TMClientInfo;
+pub type TMMisbehaviour = tm::TMMisbehaviour;
 
 pub type AccountQueryResponse = responses::AccountQueryResponse;
+pub type BalanceQueryResponse = responses::BalanceQueryResponse;
+pub type ClientStateQueryResponse = responses::ClientStateQueryResponse;
+pub type ClientStatesQueryResponse = responses::ClientStatesQueryResponse;
+pub type ConsensusStateQueryResponse = responses::ConsensusStateQueryResponse;
+pub type WasmClientState = responses::WasmClientState;
+pub type WasmConsensusState = responses::WasmConsensusState;
 pub type TxRpcResponse = responses::TxRpcResponse;
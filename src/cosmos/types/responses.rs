@@ -1,3 +1,4 @@
+use crate::cosmos::types::Coins;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -40,6 +41,8 @@ pub struct TxRpcResponse {
     pub txhash: String,
     #[serde(default = "default_code")]
     pub code: u64,
+    #[serde(default)]
+    pub codespace: String,
     pub raw_log: String,
 }
 
@@ -47,3 +50,67 @@ pub struct TxRpcResponse {
 fn default_code() -> u64 {
     0
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BalanceQueryResponse {
+    #[serde(with = "crate::utils::from_str")]
+    pub height: u64,
+    pub result: Coins,
+}
+
+/// Amino JSON encoded value, tagged with its type.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TypedValue<T> {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: T,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientStateQueryResponse {
+    #[serde(with = "crate::utils::from_str")]
+    pub height: u64,
+    pub result: ClientStateQueryResponseResult,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientStatesQueryResponse {
+    #[serde(with = "crate::utils::from_str")]
+    pub height: u64,
+    pub result: Vec<TypedValue<WasmClientState>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientStateQueryResponseResult {
+    pub client_state: TypedValue<WasmClientState>,
+}
+
+/// State of wasm client stored in wormhole module.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WasmClientState {
+    pub id: String,
+    #[serde(with = "crate::utils::from_str")]
+    pub latest_height: u64,
+    /// Zero unless client is frozen.
+    #[serde(with = "crate::utils::from_str", default)]
+    pub frozen_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConsensusStateQueryResponse {
+    #[serde(with = "crate::utils::from_str")]
+    pub height: u64,
+    pub result: ConsensusStateQueryResponseResult,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConsensusStateQueryResponseResult {
+    pub consensus_state: TypedValue<WasmConsensusState>,
+}
+
+/// Consensus state of wasm client at a given height.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WasmConsensusState {
+    #[serde(default)]
+    pub timestamp: Option<tendermint::Time>,
+}
@@ -15,8 +15,8 @@ pub struct StdSignature {
 }
 
 impl StdSignature {
-    pub fn sign(signer: SigningKey, bytes_to_sign: Vec<u8>) -> Self {
-        let secret_key = SecretKey::from(&signer);
+    pub fn sign(signer: &SigningKey, bytes_to_sign: Vec<u8>) -> Self {
+        let secret_key = SecretKey::from(signer);
         let public_key = tendermint_light_client::PublicKey::from(Secp256k1::from_secret_key(&secret_key, true));
         let sig: Signature = signer.sign(bytes_to_sign.as_slice());
 
@@ -66,6 +66,14 @@ impl Coin {
             denom: caps.get(2).unwrap().as_str().to_string(),
         }
     }
+
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    pub fn denom(&self) -> &str {
+        &self.denom
+    }
 }
 
 #[derive(Clone, Debug)]
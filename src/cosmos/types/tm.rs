@@ -28,3 +28,23 @@ pub struct TMUpdateClientPayload {
     pub client_id: ClientId,
     pub next_validator_set: Vec<tendermint::validator::Info>,
 }
+
+/// Two conflicting headers at the same height, received from primary and witness node.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TMMisbehaviour {
+    pub header_1: TMHeader,
+    pub header_2: TMHeader,
+}
+
+/// Client info stored by tendermint light client module on substrate chain.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TMClientInfo {
+    pub client_id: ClientId,
+    pub latest_height: u64,
+    pub latest_time: tendermint::Time,
+    pub trusting_period: u64,
+    pub max_clock_drift: u64,
+    pub unbonding_period: u64,
+    #[serde(default)]
+    pub frozen_height: Option<u64>,
+}
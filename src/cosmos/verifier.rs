@@ -0,0 +1,165 @@
+use crate::cosmos::types::TMHeader;
+use crate::utils::to_string;
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::{Duration, SystemTime};
+use tendermint_light_client::{
+    verify_single, SignedHeader, TrustThresholdFraction, TrustedState, ValidatorSet,
+};
+
+/// In-process counterpart of tendermint light client running inside substrate
+/// chain. Headers are handed over to `tendermint_light_client` directly
+/// instead of being wrapped into extrinsics.
+pub struct TendermintVerifier {
+    trusted_state: TrustedState,
+    trusting_period: Duration,
+    max_clock_drift: Duration,
+}
+
+impl TendermintVerifier {
+    /// Trusts given header unconditionally, the same way client creation does.
+    pub fn new(
+        header: TMHeader,
+        next_validators: Vec<tendermint::validator::Info>,
+        trusting_period: Duration,
+        max_clock_drift: Duration,
+    ) -> Result<Self, String> {
+        let signed_header: SignedHeader = convert(&header.signed_header)?;
+        let next_validator_set = ValidatorSet::new(convert(&next_validators)?);
+        Ok(TendermintVerifier {
+            trusted_state: TrustedState::new(signed_header, next_validator_set),
+            trusting_period,
+            max_clock_drift,
+        })
+    }
+
+    /// Verifies header against trusted state at time `now` and, if valid,
+    /// advances it.
+    pub fn ingest(
+        &mut self,
+        header: TMHeader,
+        next_validators: Vec<tendermint::validator::Info>,
+        now: SystemTime,
+    ) -> Result<(), String> {
+        self.trusted_state = self.verified(&header, &next_validators, now)?;
+        Ok(())
+    }
+
+    /// Verifies header against trusted state at time `now`, leaving the state as is.
+    pub fn verify(
+        &self,
+        header: &TMHeader,
+        next_validators: &[tendermint::validator::Info],
+        now: SystemTime,
+    ) -> Result<(), String> {
+        self.verified(header, next_validators, now).map(|_| ())
+    }
+
+    fn verified(
+        &self,
+        header: &TMHeader,
+        next_validators: &[tendermint::validator::Info],
+        now: SystemTime,
+    ) -> Result<TrustedState, String> {
+        let signed_header: SignedHeader = convert(&header.signed_header)?;
+        let validator_set = ValidatorSet::new(convert(&header.validator_set)?);
+        let next_validator_set = ValidatorSet::new(convert(&next_validators)?);
+        verify_single(
+            self.trusted_state.clone(),
+            &signed_header,
+            &validator_set,
+            &next_validator_set,
+            TrustThresholdFraction::default(),
+            self.trusting_period,
+            self.max_clock_drift,
+            now,
+        )
+        .map_err(to_string)
+    }
+}
+
+/// `tendermint` and `tendermint_light_client` types are structurally
+/// compatible, so we move between them through their json representation.
+fn convert<T: Serialize, U: DeserializeOwned>(value: &T) -> Result<U, String> {
+    serde_json::from_value(serde_json::to_value(value).map_err(to_string)?).map_err(to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TendermintVerifier;
+    use crate::cosmos::types::simulation::Message;
+    use std::time::{Duration, SystemTime};
+
+    fn messages() -> Vec<Message> {
+        std::fs::read_to_string("test_data/cosmos_light_client_simulated.txt")
+            .unwrap()
+            .split("\n\n")
+            .map(|str| serde_json::from_str(str).unwrap())
+            .collect()
+    }
+
+    fn time_of(message: &Message) -> SystemTime {
+        message.header.signed_header.header.time.into()
+    }
+
+    #[test]
+    fn test_verifier_accepts_header_within_trusting_period() {
+        let mut messages = messages();
+        let second = messages.remove(1);
+        let first = messages.remove(0);
+        let now = time_of(&second) + Duration::from_secs(1);
+        let mut verifier = TendermintVerifier::new(
+            first.header,
+            first.next_validators,
+            Duration::from_secs(3600),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        verifier
+            .ingest(second.header, second.next_validators, now)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_verify_leaves_trusted_state_as_is() {
+        let mut messages = messages();
+        let second = messages.remove(1);
+        let first = messages.remove(0);
+        let now = time_of(&second) + Duration::from_secs(1);
+        let verifier = TendermintVerifier::new(
+            first.header,
+            first.next_validators,
+            Duration::from_secs(3600),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        verifier
+            .verify(&second.header, &second.next_validators, now)
+            .unwrap();
+        let mut forged = second.header.clone();
+        forged.signed_header.commit.block_id.hash = tendermint::Hash::None;
+        assert!(verifier
+            .verify(&forged, &second.next_validators, now)
+            .is_err());
+        verifier
+            .verify(&second.header, &second.next_validators, now)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_verifier_rejects_header_once_trust_expired() {
+        let mut messages = messages();
+        let second = messages.remove(1);
+        let first = messages.remove(0);
+        let now = time_of(&first) + Duration::from_secs(7200);
+        let mut verifier = TendermintVerifier::new(
+            first.header,
+            first.next_validators,
+            Duration::from_secs(3600),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(verifier
+            .ingest(second.header, second.next_validators, now)
+            .is_err());
+    }
+}
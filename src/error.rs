@@ -76,3 +76,148 @@ impl From<io::Error> for Error {
         ErrorKind::Io("".to_string()).context(err).into()
     }
 }
+
+/// ABCI codes of cosmos-sdk v0.39 errors, for which the same transaction may be accepted
+/// later: signature does not verify (4), which is how the ante handler reports an account
+/// sequence taken by another transaction, or mempool is full (20).
+const RETRIABLE_TX_CODES: &[u64] = &[4, 20];
+
+/// Codespace of errors raised by cosmos-sdk itself, rather than by one of its modules.
+const SDK_CODESPACE: &str = "sdk";
+
+/// Error of a chain handler, classified by whether trying again may help.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum RelayError {
+    /// Node could not be reached, or connection to it was lost. Raised for a transaction
+    /// or extrinsic only if it was never sent, so it can be sent to another node.
+    #[error("transport error: {0}")]
+    Transport(String),
+
+    /// Node responded with an error, or with a response which could not be parsed.
+    #[error("RPC error: {0}")]
+    Rpc(String),
+
+    /// Chain refused transaction or extrinsic. Cosmos reports ABCI `code` and its
+    /// `codespace` along with the log, substrate reports the dispatch error only.
+    #[error("transaction rejected: {log}{}", .code.map(|code| format!(" (code {})", code)).unwrap_or_default())]
+    TxRejected {
+        /// Codespace of the transaction result
+        codespace: Option<String>,
+        /// ABCI code of the transaction result
+        code: Option<u64>,
+        /// Log of the transaction, or the dispatch error of the extrinsic
+        log: String,
+    },
+
+    /// Transaction or extrinsic was taken by the chain, but its result could not be told.
+    /// It is never sent again, as it may have been applied already.
+    #[error("result unknown: {0}")]
+    Unconfirmed(String),
+
+    /// Light client refused the header, or can no longer be updated.
+    #[error("light client verification failed: {0}")]
+    Verification(String),
+
+    /// Chain was caught misbehaving, light client has to be frozen.
+    #[error("misbehaviour detected: {0}")]
+    Misbehaviour(String),
+
+    /// Configuration is invalid.
+    #[error("config error: {0}")]
+    Config(String),
+
+    /// Payload could not be encoded before it was sent.
+    #[error("encoding error: {0}")]
+    Encoding(String),
+
+    /// Relayer account cannot sign transactions or extrinsics.
+    #[error("signer error: {0}")]
+    Signer(String),
+
+    /// Handler on the other end of a channel has stopped.
+    #[error("channel disconnected: {0}")]
+    Disconnected(String),
+}
+
+impl RelayError {
+    /// Wraps error of reaching a node.
+    pub fn transport<T: Display>(err: T) -> Self {
+        RelayError::Transport(err.to_string())
+    }
+
+    /// Wraps error response of a node.
+    pub fn rpc<T: Display>(err: T) -> Self {
+        RelayError::Rpc(err.to_string())
+    }
+
+    /// Wraps refusal of light client.
+    pub fn verification<T: Display>(err: T) -> Self {
+        RelayError::Verification(err.to_string())
+    }
+
+    /// Wraps invalid configuration, including simulation data.
+    pub fn config<T: Display>(err: T) -> Self {
+        RelayError::Config(err.to_string())
+    }
+
+    /// Wraps error of encoding payload.
+    pub fn encoding<T: Display>(err: T) -> Self {
+        RelayError::Encoding(err.to_string())
+    }
+
+    /// Wraps error of deriving relayer account.
+    pub fn signer<T: Display>(err: T) -> Self {
+        RelayError::Signer(err.to_string())
+    }
+
+    /// Wraps error of passing data to another handler.
+    pub fn disconnected<T: Display>(err: T) -> Self {
+        RelayError::Disconnected(err.to_string())
+    }
+
+    /// Prefixes message of the error with `context`, keeping its kind.
+    pub fn context<T: Display>(self, context: T) -> Self {
+        let wrap = |message: String| format!("{}: {}", context, message);
+        match self {
+            RelayError::Transport(e) => RelayError::Transport(wrap(e)),
+            RelayError::Rpc(e) => RelayError::Rpc(wrap(e)),
+            RelayError::TxRejected {
+                codespace,
+                code,
+                log,
+            } => RelayError::TxRejected {
+                codespace,
+                code,
+                log: wrap(log),
+            },
+            RelayError::Unconfirmed(e) => RelayError::Unconfirmed(wrap(e)),
+            RelayError::Verification(e) => RelayError::Verification(wrap(e)),
+            RelayError::Misbehaviour(e) => RelayError::Misbehaviour(wrap(e)),
+            RelayError::Config(e) => RelayError::Config(wrap(e)),
+            RelayError::Encoding(e) => RelayError::Encoding(wrap(e)),
+            RelayError::Signer(e) => RelayError::Signer(wrap(e)),
+            RelayError::Disconnected(e) => RelayError::Disconnected(wrap(e)),
+        }
+    }
+
+    /// Whether the operation which failed may succeed if it is tried again.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            RelayError::Transport(_) | RelayError::Rpc(_) => true,
+            RelayError::TxRejected {
+                codespace: Some(codespace),
+                code: Some(code),
+                ..
+            } => codespace == SDK_CODESPACE && RETRIABLE_TX_CODES.contains(code),
+            _ => false,
+        }
+    }
+
+    /// Whether header reached the light client, which refused it.
+    pub fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            RelayError::TxRejected { .. } | RelayError::Verification(_)
+        )
+    }
+}
@@ -0,0 +1,215 @@
+//! Failover between endpoints of a chain. Each call goes to the endpoint which
+//! is preferred at the moment and moves on to the next one if the endpoint
+//! fails, so a single flaky node provider does not stall the relay. Subscriptions
+//! which stall or drop are resumed on the next endpoint after a delay.
+
+use crate::error::RelayError;
+use crate::metrics::Metrics;
+use lazy_static::lazy_static;
+use log::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Endpoint which failed is tried only after healthy ones for this long.
+pub const FAILED_ENDPOINT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Subscription which delivers no block for this long is taken as stalled, and resumed
+/// on another endpoint. Both chains produce blocks every few seconds.
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// First delay before reconnecting, doubled after every failed attempt.
+pub const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Delay before reconnecting never grows past this.
+pub const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref FAILED_AT: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+/// Errors which tell whether the endpoint is to blame, so another one should be tried.
+pub trait EndpointError: Display {
+    fn is_endpoint_failure(&self) -> bool;
+}
+
+impl EndpointError for RelayError {
+    // Header refused by the chain would be refused by any of its nodes, and a transaction
+    // which may have reached the node is not sent to another one.
+    fn is_endpoint_failure(&self) -> bool {
+        matches!(self, RelayError::Transport(_))
+    }
+}
+
+/// Orders endpoints for the next attempt: those which did not fail recently go first,
+/// in configured order, followed by failed ones, least recently failed first.
+fn order(addrs: &[String], failed_at: &HashMap<String, Instant>, now: Instant) -> Vec<String> {
+    let mut ordered: Vec<(Option<Instant>, &String)> = addrs
+        .iter()
+        .map(|addr| {
+            let recently_failed = failed_at
+                .get(addr)
+                .filter(|at| now.duration_since(**at) < FAILED_ENDPOINT_COOLDOWN)
+                .copied();
+            (recently_failed, addr)
+        })
+        .collect();
+    // Sort is stable, so configured order is kept among healthy endpoints.
+    ordered.sort_by_key(|(recently_failed, _)| *recently_failed);
+    ordered.into_iter().map(|(_, addr)| addr.clone()).collect()
+}
+
+/// Records that `addr` of `chain` responded, in `metrics` of the relayer calling it.
+pub fn succeeded(metrics: &Metrics, chain: &str, addr: &str) {
+    if FAILED_AT.lock().unwrap().remove(addr).is_some() {
+        info!("{} endpoint {} has recovered", chain, addr);
+    }
+    metrics.observe_endpoint(chain, addr, true);
+}
+
+/// Records that `addr` of `chain` failed, so other endpoints are preferred.
+pub fn failed<E: Display>(metrics: &Metrics, chain: &str, addr: &str, err: &E) {
+    warn!("{} endpoint {} failed: {}", chain, addr, err);
+    FAILED_AT
+        .lock()
+        .unwrap()
+        .insert(addr.to_owned(), Instant::now());
+    metrics.observe_endpoint(chain, addr, false);
+}
+
+/// Calls `f` with endpoints of `chain` in order of preference until one succeeds,
+/// returning error of the last one if all of them fail.
+pub async fn call<T, E, F, Fut>(
+    metrics: &Metrics,
+    chain: &str,
+    addrs: &[String],
+    mut f: F,
+) -> Result<T, E>
+where
+    E: EndpointError,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let ordered = order(addrs, &FAILED_AT.lock().unwrap(), Instant::now());
+    let (last, preferred) = ordered
+        .split_last()
+        .expect("at least one endpoint is configured");
+    for addr in preferred {
+        match f(addr.clone()).await {
+            Ok(value) => {
+                succeeded(metrics, chain, addr);
+                return Ok(value);
+            }
+            Err(e) if e.is_endpoint_failure() => failed(metrics, chain, addr, &e),
+            Err(e) => return Err(e),
+        }
+    }
+    let result = f(last.clone()).await;
+    match &result {
+        Ok(_) => succeeded(metrics, chain, last),
+        Err(e) if e.is_endpoint_failure() => failed(metrics, chain, last, e),
+        Err(_) => {}
+    }
+    result
+}
+
+/// Calls `f` with endpoints of `chain` in order of preference until one succeeds. When all
+/// of them fail, they are tried again after a delay growing with every round, so an outage
+/// of all endpoints stalls relaying only until one of them is back.
+pub async fn call_until_success<T, E, F, Fut>(
+    metrics: &Metrics,
+    chain: &str,
+    addrs: &[String],
+    mut f: F,
+) -> Result<T, E>
+where
+    E: EndpointError,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut backoff = Backoff::new();
+    loop {
+        match call(metrics, chain, addrs, &mut f).await {
+            Err(e) if e.is_endpoint_failure() => {
+                let delay = backoff.delay();
+                warn!(
+                    "All {} endpoints failed, last with: {}, trying again in {:?}",
+                    chain, e, delay
+                );
+                tokio::time::delay_for(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Delay before reconnecting, doubled after every attempt which did not last.
+pub struct Backoff {
+    next: Duration,
+}
+
+impl Backoff {
+    /// Starts with the shortest delay.
+    pub fn new() -> Self {
+        Backoff {
+            next: MIN_RECONNECT_DELAY,
+        }
+    }
+
+    /// Returns delay to wait before the next attempt.
+    pub fn delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = std::cmp::min(self.next * 2, MAX_RECONNECT_DELAY);
+        delay
+    }
+
+    /// Starts over from the shortest delay, once connection has proved to work.
+    pub fn reset(&mut self) {
+        self.next = MIN_RECONNECT_DELAY;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        order, Backoff, FAILED_ENDPOINT_COOLDOWN, MAX_RECONNECT_DELAY, MIN_RECONNECT_DELAY,
+    };
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_failed_endpoints_go_last() {
+        let addrs: Vec<String> = vec!["a".into(), "b".into(), "c".into()];
+        let now = Instant::now();
+        let mut failed_at = HashMap::new();
+        assert_eq!(order(&addrs, &failed_at, now), addrs);
+
+        failed_at.insert("a".to_owned(), now - Duration::from_secs(1));
+        failed_at.insert("b".to_owned(), now - Duration::from_secs(2));
+        assert_eq!(order(&addrs, &failed_at, now), vec!["c", "b", "a"]);
+
+        failed_at.insert("b".to_owned(), now - FAILED_ENDPOINT_COOLDOWN);
+        assert_eq!(order(&addrs, &failed_at, now), vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::new();
+        assert_eq!(backoff.delay(), MIN_RECONNECT_DELAY);
+        assert_eq!(backoff.delay(), MIN_RECONNECT_DELAY * 2);
+        for _ in 0..10 {
+            backoff.delay();
+        }
+        assert_eq!(backoff.delay(), MAX_RECONNECT_DELAY);
+        backoff.reset();
+        assert_eq!(backoff.delay(), MIN_RECONNECT_DELAY);
+    }
+}
@@ -0,0 +1,372 @@
+//! Status of chain handlers and light clients, reported by `/healthz` and
+//! `/readyz` endpoints so orchestrators can tell whether headers are flowing.
+//! The endpoints are served on their own address when `health` section is present
+//! in configuration, and along with metrics otherwise.
+
+use crate::config::{HealthConfig, WatchdogConfig};
+use crate::utils::to_string;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use log::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::{self, Debug, Display};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Handler, or light client, of `chain` along with the path it belongs to, if any.
+type Key = (String, Option<String>);
+
+fn key(chain: &str, path: Option<&str>) -> Key {
+    (chain.to_owned(), path.map(str::to_owned))
+}
+
+/// Status of handlers and light clients of a single relayer. Clones share the status,
+/// so handlers of a relayer report into the one its handle and endpoints read.
+#[derive(Clone, Default)]
+pub struct Health {
+    inner: Arc<Mutex<Status>>,
+}
+
+#[derive(Default)]
+struct Status {
+    handlers: BTreeMap<(Key, &'static str), HandlerStatus>,
+    clients: BTreeMap<Key, ClientStatus>,
+}
+
+impl Debug for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Health")
+    }
+}
+
+/// Lifecycle state of a handler.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandlerState {
+    /// Handler is connecting to its chain.
+    Starting,
+    /// Handler is connected and processing headers.
+    Running,
+    /// Handler stopped with an error.
+    Failed(String),
+    /// Handler stopped without an error.
+    Stopped,
+}
+
+struct HandlerStatus {
+    state: HandlerState,
+    last_activity: Option<Instant>,
+}
+
+struct ClientStatus {
+    trusting_period: Duration,
+    watchdog: WatchdogConfig,
+    last_submission: Option<Instant>,
+    tracked_since: Instant,
+}
+
+impl ClientStatus {
+    /// Time since light client was last updated, counted from the moment
+    /// relayer started tracking it if no header was submitted yet.
+    fn since_update(&self) -> Duration {
+        self.last_submission.unwrap_or(self.tracked_since).elapsed()
+    }
+
+    fn expiry_risk(&self) -> ExpiryRisk {
+        let fraction = self.since_update().as_secs_f64() / self.trusting_period.as_secs_f64();
+        if fraction >= self.watchdog.alert_fraction {
+            ExpiryRisk::Critical
+        } else if fraction >= self.watchdog.refresh_fraction {
+            ExpiryRisk::Warning
+        } else {
+            ExpiryRisk::Ok
+        }
+    }
+}
+
+/// How close light client is to the end of its trusting period.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryRisk {
+    /// Light client is updated often enough.
+    Ok,
+    /// Light client should be refreshed soon, past `refresh_fraction` of trusting period.
+    Warning,
+    /// Light client is about to expire, past `alert_fraction` of trusting period.
+    Critical,
+}
+
+/// Status of a single handler, as reported by endpoints.
+#[derive(Debug, Serialize)]
+pub struct HandlerReport {
+    /// Name of the chain handler talks to.
+    pub chain: String,
+    /// Path of the light client send handler updates, when relaying paths.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Either `recv` or `send`.
+    pub role: &'static str,
+    /// Lifecycle state of the handler.
+    pub state: HandlerState,
+    /// Seconds since handler last processed a header, if it did.
+    pub seconds_since_activity: Option<f64>,
+}
+
+/// Status of light client hosted on `chain`, as reported by endpoints.
+#[derive(Debug, Serialize)]
+pub struct ClientReport {
+    /// Name of the chain hosting the light client.
+    pub chain: String,
+    /// Path the light client belongs to, when relaying paths.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Seconds since a header was last submitted to the light client, if it was.
+    pub seconds_since_last_submission: Option<f64>,
+    /// Trusting period of the light client.
+    pub trusting_period_seconds: u64,
+    /// How close light client is to expiring.
+    pub expiry_risk: ExpiryRisk,
+}
+
+/// Body of `/healthz` and `/readyz` responses.
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    /// Whether no handler failed.
+    pub healthy: bool,
+    /// Whether every handler is running and no light client is about to expire.
+    pub ready: bool,
+    /// Status of every handler.
+    pub handlers: Vec<HandlerReport>,
+    /// Status of every tracked light client.
+    pub clients: Vec<ClientReport>,
+}
+
+impl Health {
+    fn set_state(&self, chain: &str, path: Option<&str>, role: &'static str, state: HandlerState) {
+        let mut status = self.inner.lock().unwrap();
+        let handler = status
+            .handlers
+            .entry((key(chain, path), role))
+            .or_insert(HandlerStatus {
+                state: HandlerState::Starting,
+                last_activity: None,
+            });
+        handler.state = state;
+    }
+
+    /// Registers handler which is connecting to `chain`; `role` is either `recv` or `send`.
+    /// Send handlers are registered with `path` of the light client they update, if any.
+    pub fn starting(&self, chain: &str, path: Option<&str>, role: &'static str) {
+        self.set_state(chain, path, role, HandlerState::Starting);
+    }
+
+    /// Marks handler as connected.
+    pub fn running(&self, chain: &str, path: Option<&str>, role: &'static str) {
+        self.set_state(chain, path, role, HandlerState::Running);
+    }
+
+    /// Records that handler processed a header.
+    pub fn activity(&self, chain: &str, path: Option<&str>, role: &'static str) {
+        let mut status = self.inner.lock().unwrap();
+        if let Some(handler) = status.handlers.get_mut(&(key(chain, path), role)) {
+            handler.last_activity = Some(Instant::now());
+        }
+    }
+
+    /// Records the way handler terminated.
+    pub fn stopped<T, E: Display>(
+        &self,
+        chain: &str,
+        path: Option<&str>,
+        role: &'static str,
+        result: &Result<T, E>,
+    ) {
+        let state = match result {
+            Ok(_) => HandlerState::Stopped,
+            Err(e) => HandlerState::Failed(e.to_string()),
+        };
+        self.set_state(chain, path, role, state);
+    }
+
+    /// Starts tracking expiry of light client of `path` hosted on `chain`; expiry risk is
+    /// graded by fractions of trusting period configured for its watchdog.
+    pub fn track_client(
+        &self,
+        chain: &str,
+        path: Option<&str>,
+        trusting_period: Duration,
+        watchdog: &WatchdogConfig,
+    ) {
+        self.inner.lock().unwrap().clients.insert(
+            key(chain, path),
+            ClientStatus {
+                trusting_period,
+                watchdog: watchdog.clone(),
+                last_submission: None,
+                tracked_since: Instant::now(),
+            },
+        );
+    }
+
+    /// Records that light client of `path` hosted on `chain` ingested a header.
+    pub fn client_updated(&self, chain: &str, path: Option<&str>) {
+        self.client_updated_at(chain, path, Instant::now());
+    }
+
+    /// Records that light client of `path` hosted on `chain` ingested a header at `at`.
+    pub fn client_updated_at(&self, chain: &str, path: Option<&str>, at: Instant) {
+        let mut status = self.inner.lock().unwrap();
+        if let Some(client) = status.clients.get_mut(&key(chain, path)) {
+            client.last_submission = Some(at);
+        }
+    }
+
+    /// Snapshot of handler and client status. Relayer is healthy as long as no
+    /// handler failed, and ready once every handler is running and no client is
+    /// about to expire.
+    pub fn report(&self) -> HealthReport {
+        let status = self.inner.lock().unwrap();
+        let handlers: Vec<HandlerReport> = status
+            .handlers
+            .iter()
+            .map(|(((chain, path), role), status)| HandlerReport {
+                chain: chain.clone(),
+                path: path.clone(),
+                role: *role,
+                state: status.state.clone(),
+                seconds_since_activity: status.last_activity.map(|at| at.elapsed().as_secs_f64()),
+            })
+            .collect();
+        let clients: Vec<ClientReport> = status
+            .clients
+            .iter()
+            .map(|((chain, path), client)| ClientReport {
+                chain: chain.clone(),
+                path: path.clone(),
+                seconds_since_last_submission: client
+                    .last_submission
+                    .map(|at| at.elapsed().as_secs_f64()),
+                trusting_period_seconds: client.trusting_period.as_secs(),
+                expiry_risk: client.expiry_risk(),
+            })
+            .collect();
+
+        let healthy = !handlers
+            .iter()
+            .any(|h| matches!(h.state, HandlerState::Failed(_)));
+        let ready = healthy
+            && handlers.iter().all(|h| h.state == HandlerState::Running)
+            && clients
+                .iter()
+                .all(|c| c.expiry_risk != ExpiryRisk::Critical);
+        HealthReport {
+            healthy,
+            ready,
+            handlers,
+            clients,
+        }
+    }
+}
+
+/// Answers `/healthz` and `/readyz` with report of `health`, and anything else with 404.
+pub fn respond(req: Request<Body>, health: &Health) -> Response<Body> {
+    match req.uri().path() {
+        "/healthz" => health_check(health, |report| report.healthy),
+        "/readyz" => health_check(health, |report| report.ready),
+        _ => {
+            let mut response = Response::new(Body::from("not found"));
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            response
+        }
+    }
+}
+
+/// Responds with health report; status is 503 unless `passed` holds for it.
+fn health_check(health: &Health, passed: fn(&HealthReport) -> bool) -> Response<Body> {
+    let report = health.report();
+    let status = if passed(&report) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = serde_json::to_vec_pretty(&report).unwrap_or_default();
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+/// Serves health checks of `health` at `/healthz` and `/readyz` until the server fails.
+pub async fn serve(cfg: HealthConfig, health: Health) -> Result<(), String> {
+    let addr: SocketAddr = cfg.listen_addr.parse().map_err(to_string)?;
+    let make_svc = make_service_fn(move |_| {
+        let health = health.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let health = health.clone();
+                async move { Ok::<_, Infallible>(respond(req, &health)) }
+            }))
+        }
+    });
+    info!("serving health checks on http://{}", addr);
+    Server::bind(&addr).serve(make_svc).await.map_err(to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{respond, ClientStatus, ExpiryRisk, HandlerState, Health};
+    use crate::config::WatchdogConfig;
+    use hyper::{Body, Request, StatusCode};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_expiry_risk_grows_with_time_since_update() {
+        let now = Instant::now();
+        let client = |since_update: u64| ClientStatus {
+            trusting_period: Duration::from_secs(100),
+            watchdog: WatchdogConfig::default(),
+            last_submission: Some(now - Duration::from_secs(since_update)),
+            tracked_since: now,
+        };
+        assert_eq!(client(10).expiry_risk(), ExpiryRisk::Ok);
+        assert_eq!(client(60).expiry_risk(), ExpiryRisk::Warning);
+        assert_eq!(client(95).expiry_risk(), ExpiryRisk::Critical);
+    }
+
+    #[test]
+    fn test_relayers_report_their_own_handlers() {
+        let first = Health::default();
+        let second = Health::default();
+        first.running("cosmos", None, "recv");
+        second.stopped("cosmos", None, "recv", &Err::<(), _>("node unreachable"));
+        assert!(first.report().healthy);
+        assert!(first.report().ready);
+        let report = second.report();
+        assert!(!report.healthy);
+        assert_eq!(
+            report.handlers[0].state,
+            HandlerState::Failed("node unreachable".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_probes_report_status_of_handlers() {
+        let health = Health::default();
+        health.starting("cosmos", None, "recv");
+        let probe = |path: &str| {
+            let req = Request::get(path).body(Body::empty()).unwrap();
+            respond(req, &health).status()
+        };
+        assert_eq!(probe("/healthz"), StatusCode::OK);
+        assert_eq!(probe("/readyz"), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(probe("/metrics"), StatusCode::NOT_FOUND);
+        health.running("cosmos", None, "recv");
+        assert_eq!(probe("/readyz"), StatusCode::OK);
+    }
+}
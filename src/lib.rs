@@ -19,10 +19,27 @@
 extern crate substrate_subxt_proc_macro;
 
 pub mod application;
+mod chain;
+mod client_id;
+mod client_state;
 pub mod commands;
 pub mod config;
 mod cosmos;
 pub mod error;
+mod failover;
+mod health;
+mod metrics;
+mod monitoring;
 pub mod prelude;
+mod progress;
+mod relayer;
+mod report;
+mod retry;
+mod shutdown;
 mod substrate;
 mod utils;
+mod watchdog;
+
+pub use health::{ClientReport, ExpiryRisk, HandlerReport, HandlerState, HealthReport};
+pub use relayer::{Relayer, RelayerBuilder, RelayerHandle};
+pub use report::HeaderRecord;
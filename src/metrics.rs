@@ -0,0 +1,428 @@
+//! Prometheus metrics describing health of the relay, served over HTTP when
+//! `metrics` section is present in configuration, along with health checks.
+
+use crate::config::MetricsConfig;
+use crate::health::{self, Health};
+use crate::utils::to_string;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use log::*;
+use prometheus::core::Collector;
+use prometheus::{
+    CounterVec, Encoder, GaugeVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::{self, Debug};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Metrics of a single relayer, kept in a registry of its own. Clones share the registry,
+/// so handlers of a relayer record into the one its endpoint serves, and relayers running
+/// side by side do not mix their series.
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    registry: Registry,
+    latest_height: IntGaugeVec,
+    relayed_height: IntGaugeVec,
+    relay_lag_blocks: IntGaugeVec,
+    relay_lag_seconds: GaugeVec,
+    submissions: IntCounterVec,
+    fees_spent: CounterVec,
+    signer_balance: GaugeVec,
+    queue_depth: IntGaugeVec,
+    endpoint_up: IntGaugeVec,
+    client_expiry_seconds: GaugeVec,
+    last_relayed_at: Mutex<HashMap<Direction, Instant>>,
+}
+
+impl Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Registers `metric` with `registry`. Names are fixed and each registry is new, so
+/// registration cannot fail.
+fn register<T: Collector + Clone + 'static>(registry: &Registry, metric: T) -> T {
+    registry.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+/// Source chain, destination chain and path, empty unless relaying paths, of relayed headers.
+type Direction = (String, String, String);
+
+/// Named function returning number of messages waiting in a channel.
+pub type QueueDepth = (String, Box<dyn Fn() -> usize + Send + Sync>);
+
+/// Labels of headers travelling from `source` chain to light client on `destination` chain.
+fn labels((source, destination, path): &Direction) -> [String; 2] {
+    [format!("{}_to_{}", source, destination), path.clone()]
+}
+
+fn with_labels<'a>(labels: &'a [String; 2]) -> [&'a str; 2] {
+    [labels[0].as_str(), labels[1].as_str()]
+}
+
+impl Metrics {
+    /// Creates metrics in a registry of their own.
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let int_gauge = |name: &str, help: &str, labels: &[&str]| {
+            register(
+                &registry,
+                IntGaugeVec::new(Opts::new(name, help), labels).unwrap(),
+            )
+        };
+        let gauge = |name: &str, help: &str, labels: &[&str]| {
+            register(
+                &registry,
+                GaugeVec::new(Opts::new(name, help), labels).unwrap(),
+            )
+        };
+        let inner = Inner {
+            latest_height: int_gauge(
+                "quantum_tunnel_latest_height",
+                "Height of the latest header seen on chain",
+                &["chain"],
+            ),
+            relayed_height: int_gauge(
+                "quantum_tunnel_relayed_height",
+                "Height of the last header accepted by light client on the opposite chain",
+                &["direction", "path"],
+            ),
+            relay_lag_blocks: int_gauge(
+                "quantum_tunnel_relay_lag_blocks",
+                "Number of headers seen on source chain but not yet relayed",
+                &["direction", "path"],
+            ),
+            relay_lag_seconds: gauge(
+                "quantum_tunnel_relay_lag_seconds",
+                "Seconds since light client was last updated, while headers are waiting to be relayed",
+                &["direction", "path"],
+            ),
+            submissions: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new(
+                        "quantum_tunnel_submissions_total",
+                        "Transactions and extrinsics carrying headers, by outcome",
+                    ),
+                    &["chain", "path", "result"],
+                )
+                .unwrap(),
+            ),
+            fees_spent: register(
+                &registry,
+                CounterVec::new(
+                    Opts::new("quantum_tunnel_fees_spent", "Fees paid by relayer account"),
+                    &["chain", "denom"],
+                )
+                .unwrap(),
+            ),
+            signer_balance: gauge(
+                "quantum_tunnel_signer_balance",
+                "Balance of relayer account",
+                &["chain", "denom"],
+            ),
+            queue_depth: int_gauge(
+                "quantum_tunnel_queue_depth",
+                "Headers waiting in channel between receive and send handlers",
+                &["channel"],
+            ),
+            endpoint_up: int_gauge(
+                "quantum_tunnel_endpoint_up",
+                "Whether the last call to chain endpoint succeeded",
+                &["chain", "addr"],
+            ),
+            client_expiry_seconds: gauge(
+                "quantum_tunnel_client_expiry_seconds",
+                "Seconds left until light client expires unless a header is relayed to it",
+                &["chain", "path"],
+            ),
+            last_relayed_at: Mutex::new(HashMap::new()),
+            registry,
+        };
+        Metrics {
+            inner: Arc::new(inner),
+        }
+    }
+
+    fn update_lag(&self, direction: &Direction) {
+        let labels = labels(direction);
+        let relayed = self
+            .inner
+            .relayed_height
+            .with_label_values(&with_labels(&labels))
+            .get();
+        if relayed == 0 {
+            return;
+        }
+        let latest = self
+            .inner
+            .latest_height
+            .with_label_values(&[&direction.0])
+            .get();
+        self.inner
+            .relay_lag_blocks
+            .with_label_values(&with_labels(&labels))
+            .set((latest - relayed).max(0));
+    }
+
+    /// Records header at `height` seen on `chain`, and updates lag of every light
+    /// client its headers have been relayed to.
+    pub fn observe_header(&self, chain: &str, height: u64) {
+        self.inner
+            .latest_height
+            .with_label_values(&[chain])
+            .set(height as i64);
+        let directions: Vec<Direction> = self
+            .inner
+            .last_relayed_at
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(source, _, _)| source == chain)
+            .cloned()
+            .collect();
+        for direction in directions {
+            self.update_lag(&direction);
+        }
+    }
+
+    /// Records outcome of submitting header at `height` of `source` chain to light
+    /// client of `path`, if relaying paths, on `destination` chain.
+    pub fn observe_submission(
+        &self,
+        source: &str,
+        destination: &str,
+        path: Option<&str>,
+        height: u64,
+        success: bool,
+    ) {
+        let path = path.unwrap_or_default();
+        let result = if success { "success" } else { "failure" };
+        self.inner
+            .submissions
+            .with_label_values(&[destination, path, result])
+            .inc();
+        if !success {
+            return;
+        }
+        let direction = (source.to_owned(), destination.to_owned(), path.to_owned());
+        self.inner
+            .relayed_height
+            .with_label_values(&with_labels(&labels(&direction)))
+            .set(height as i64);
+        self.inner
+            .last_relayed_at
+            .lock()
+            .unwrap()
+            .insert(direction.clone(), Instant::now());
+        self.update_lag(&direction);
+    }
+
+    /// Records fee paid by relayer account on `chain`.
+    pub fn observe_fee(&self, chain: &str, denom: &str, amount: f64) {
+        self.inner
+            .fees_spent
+            .with_label_values(&[chain, denom])
+            .inc_by(amount);
+    }
+
+    /// Records current balance of relayer account on `chain`.
+    pub fn observe_balance(&self, chain: &str, denom: &str, amount: f64) {
+        self.inner
+            .signer_balance
+            .with_label_values(&[chain, denom])
+            .set(amount);
+    }
+
+    /// Records time left until light client of `path`, if relaying paths, hosted on `chain`
+    /// expires.
+    pub fn observe_client_expiry(&self, chain: &str, path: Option<&str>, remaining: Duration) {
+        self.inner
+            .client_expiry_seconds
+            .with_label_values(&[chain, path.unwrap_or_default()])
+            .set(remaining.as_secs_f64());
+    }
+
+    /// Records whether the last call to endpoint `addr` of `chain` succeeded.
+    pub fn observe_endpoint(&self, chain: &str, addr: &str, up: bool) {
+        self.inner
+            .endpoint_up
+            .with_label_values(&[chain, addr])
+            .set(if up { 1 } else { 0 });
+    }
+
+    /// Gauges which depend on the time of the scrape are computed here.
+    fn refresh(&self, queues: &[QueueDepth]) {
+        for (channel, depth) in queues {
+            self.inner
+                .queue_depth
+                .with_label_values(&[channel.as_str()])
+                .set(depth() as i64);
+        }
+        for (direction, relayed_at) in self.inner.last_relayed_at.lock().unwrap().iter() {
+            let labels = labels(direction);
+            let lag = if self
+                .inner
+                .relay_lag_blocks
+                .with_label_values(&with_labels(&labels))
+                .get()
+                > 0
+            {
+                relayed_at.elapsed().as_secs_f64()
+            } else {
+                0.0
+            };
+            self.inner
+                .relay_lag_seconds
+                .with_label_values(&with_labels(&labels))
+                .set(lag);
+        }
+    }
+}
+
+fn respond(
+    req: Request<Body>,
+    metrics: &Metrics,
+    queues: &[QueueDepth],
+    health: &Health,
+) -> Response<Body> {
+    match req.uri().path() {
+        "/metrics" => encode(metrics, queues),
+        _ => health::respond(req, health),
+    }
+}
+
+fn encode(metrics: &Metrics, queues: &[QueueDepth]) -> Response<Body> {
+    metrics.refresh(queues);
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(e) = encoder.encode(&metrics.inner.registry.gather(), &mut buffer) {
+        let mut response = Response::new(Body::from(e.to_string()));
+        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        return response;
+    }
+    let mut response = Response::new(Body::from(buffer));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    response
+}
+
+/// Serves `metrics` at `/metrics` and health checks of `health` at `/healthz` and
+/// `/readyz` until the server fails.
+pub async fn serve(
+    cfg: MetricsConfig,
+    metrics: Metrics,
+    queues: Vec<QueueDepth>,
+    health: Health,
+) -> Result<(), String> {
+    let addr: SocketAddr = cfg.listen_addr.parse().map_err(to_string)?;
+    let queues = Arc::new(queues);
+    let make_svc = make_service_fn(move |_| {
+        let (metrics, queues, health) = (metrics.clone(), queues.clone(), health.clone());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let (metrics, queues, health) = (metrics.clone(), queues.clone(), health.clone());
+                async move { Ok::<_, Infallible>(respond(req, &metrics, &queues, &health)) }
+            }))
+        }
+    });
+    info!("serving metrics and health checks on http://{}", addr);
+    Server::bind(&addr).serve(make_svc).await.map_err(to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+
+    #[test]
+    fn test_lag_is_distance_between_seen_and_relayed_height() {
+        let metrics = Metrics::new();
+        metrics.observe_header("cosmos", 10);
+        metrics.observe_submission("cosmos", "substrate", None, 7, true);
+        metrics.observe_header("cosmos", 12);
+        let lag = metrics
+            .inner
+            .relay_lag_blocks
+            .with_label_values(&["cosmos_to_substrate", ""])
+            .get();
+        assert_eq!(lag, 5);
+    }
+
+    #[test]
+    fn test_lag_is_kept_per_destination() {
+        let metrics = Metrics::new();
+        metrics.observe_submission("solo", "first", None, 3, true);
+        metrics.observe_submission("solo", "second", None, 5, true);
+        metrics.observe_header("solo", 8);
+        for (direction, lag) in &[("solo_to_first", 5), ("solo_to_second", 3)] {
+            assert_eq!(
+                metrics
+                    .inner
+                    .relay_lag_blocks
+                    .with_label_values(&[direction, ""])
+                    .get(),
+                *lag
+            );
+        }
+        assert_eq!(
+            metrics
+                .inner
+                .relayed_height
+                .with_label_values(&["solo_to_first", ""])
+                .get(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_paths_between_the_same_chains_are_kept_apart() {
+        let metrics = Metrics::new();
+        metrics.observe_submission("hub", "parachain", Some("a"), 4, true);
+        metrics.observe_submission("hub", "parachain", Some("b"), 6, true);
+        metrics.observe_header("hub", 9);
+        for (path, lag) in &[("a", 5), ("b", 3)] {
+            assert_eq!(
+                metrics
+                    .inner
+                    .relay_lag_blocks
+                    .with_label_values(&["hub_to_parachain", path])
+                    .get(),
+                *lag
+            );
+        }
+    }
+
+    #[test]
+    fn test_relayers_keep_their_own_series() {
+        let first = Metrics::new();
+        let second = Metrics::new();
+        first.observe_header("cosmos", 10);
+        second.observe_header("cosmos", 20);
+        let height = |metrics: &Metrics| {
+            metrics
+                .inner
+                .latest_height
+                .with_label_values(&["cosmos"])
+                .get()
+        };
+        assert_eq!(height(&first), 10);
+        assert_eq!(height(&second), 20);
+    }
+}
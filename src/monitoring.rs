@@ -0,0 +1,166 @@
+//! Events sent by send handlers to simulation receive handler of opposite
+//! chain, used to decide whether a simulation succeeded.
+
+use crate::error::RelayError;
+use log::*;
+
+/// Progress of a send handler, as observed by simulation receive handler.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MonitoringEvent {
+    /// Light client ingested header at `height`.
+    Ingested {
+        height: u64,
+        tx_hash: Option<String>,
+    },
+    /// Light client refused header at `height`.
+    Rejected { height: u64, reason: String },
+    /// Header at `height` could not be delivered to light client.
+    TransportError { height: u64, reason: String },
+    /// Send handler stopped and will not report any more events.
+    Terminated,
+}
+
+impl MonitoringEvent {
+    /// Builds an event out of the result of submitting header at `height`.
+    pub fn from_result(height: u64, result: &Result<Option<String>, RelayError>) -> Self {
+        match result {
+            Ok(tx_hash) => MonitoringEvent::Ingested {
+                height,
+                tx_hash: tx_hash.clone(),
+            },
+            Err(e) if e.is_rejection() => MonitoringEvent::Rejected {
+                height,
+                reason: e.to_string(),
+            },
+            Err(e) => MonitoringEvent::TransportError {
+                height,
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Keeps track of monitoring events for a simulation and decides its outcome.
+pub struct SimulationMonitor {
+    chain: &'static str,
+    number_of_simulated_headers: usize,
+    should_run_till_height: u64,
+    expected_rejection: Option<String>,
+    number_of_headers_ingested_till: usize,
+    successfully_ingested_till: u64,
+    rejection: Option<(u64, String)>,
+    transport_error: Option<(u64, String)>,
+}
+
+impl SimulationMonitor {
+    pub fn new(
+        chain: &'static str,
+        number_of_simulated_headers: usize,
+        should_run_till_height: u64,
+        expected_rejection: Option<String>,
+    ) -> Self {
+        SimulationMonitor {
+            chain,
+            number_of_simulated_headers,
+            should_run_till_height,
+            expected_rejection,
+            number_of_headers_ingested_till: 0,
+            successfully_ingested_till: 0,
+            rejection: None,
+            transport_error: None,
+        }
+    }
+
+    /// Records an event. Returns outcome of the simulation once it is over,
+    /// i.e. height light client ingested headers till, or the reason of failure.
+    pub fn observe(&mut self, event: MonitoringEvent) -> Option<Result<u64, String>> {
+        match event {
+            MonitoringEvent::Ingested { height, tx_hash } => {
+                self.successfully_ingested_till = height;
+                self.number_of_headers_ingested_till += 1;
+                info!(
+                    "{} light client has successfully ingested header at: {}, tx: {:?}",
+                    self.chain, height, tx_hash
+                );
+                if self.number_of_headers_ingested_till == self.number_of_simulated_headers {
+                    return Some(self.outcome());
+                }
+                None
+            }
+            MonitoringEvent::Rejected { height, reason } => {
+                info!(
+                    "{} light client rejected header at: {}, reason: {}",
+                    self.chain, height, reason
+                );
+                self.rejection = Some((height, reason));
+                None
+            }
+            MonitoringEvent::TransportError { height, reason } => {
+                error!(
+                    "header at: {} could not be delivered to {} light client: {}",
+                    height, self.chain, reason
+                );
+                self.transport_error = Some((height, reason));
+                None
+            }
+            MonitoringEvent::Terminated => Some(self.outcome()),
+        }
+    }
+
+    fn outcome(&self) -> Result<u64, String> {
+        if let Some((height, reason)) = &self.transport_error {
+            return Err(format!("Ingesting simulation data on {} chain was aborted at height: {} by transport error: {}", self.chain, height, reason));
+        }
+        if self.successfully_ingested_till != self.should_run_till_height {
+            return Err(format!("Ingesting simulation data failed on {} chain. Expected to ingest headers till height: {}, ingested till: {}, rejection: {:?}", self.chain, self.should_run_till_height, self.successfully_ingested_till, self.rejection));
+        }
+        match (&self.expected_rejection, &self.rejection) {
+            (None, _) => Ok(self.successfully_ingested_till),
+            (Some(expected), Some((_, reason))) if reason.contains(expected.as_str()) => {
+                Ok(self.successfully_ingested_till)
+            }
+            (Some(expected), rejection) => Err(format!("Ingesting simulation data on {} chain expected to be rejected with: {}, rejection: {:?}", self.chain, expected, rejection)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MonitoringEvent, SimulationMonitor};
+
+    fn ingested(height: u64) -> MonitoringEvent {
+        MonitoringEvent::Ingested {
+            height,
+            tx_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_monitor_succeeds_on_expected_rejection() {
+        let mut monitor =
+            SimulationMonitor::new("cosmos", 3, 2, Some("invalid signature".to_owned()));
+        assert_eq!(monitor.observe(ingested(1)), None);
+        assert_eq!(monitor.observe(ingested(2)), None);
+        let rejected = MonitoringEvent::Rejected {
+            height: 3,
+            reason: "invalid signature of validator".to_owned(),
+        };
+        assert_eq!(monitor.observe(rejected), None);
+        assert_eq!(monitor.observe(MonitoringEvent::Terminated), Some(Ok(2)));
+    }
+
+    #[test]
+    fn test_monitor_fails_on_transport_error() {
+        let mut monitor = SimulationMonitor::new("substrate", 3, 1, None);
+        assert_eq!(monitor.observe(ingested(1)), None);
+        let error = MonitoringEvent::TransportError {
+            height: 2,
+            reason: "connection refused".to_owned(),
+        };
+        assert_eq!(monitor.observe(error), None);
+        assert!(monitor
+            .observe(MonitoringEvent::Terminated)
+            .unwrap()
+            .is_err());
+    }
+}
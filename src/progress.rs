@@ -0,0 +1,119 @@
+//! Progress of relaying, kept on disk so that whatever way the relayer stops,
+//! there is a record of the last header each light client was sent. The record is
+//! read back on start, so send handlers resuming a client whose state cannot be
+//! queried carry on after the header it was last sent.
+
+use crate::utils::to_string;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::sync::Mutex;
+
+/// Last header relayed to every light client, rewritten after each of them.
+pub const PROGRESS_FILE: &str = "quantum_tunnel_progress.json";
+
+lazy_static! {
+    static ref PROGRESS: Mutex<BTreeMap<String, ClientProgress>> = Mutex::new(BTreeMap::new());
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct ClientProgress {
+    chain: String,
+    client_id: String,
+    height: u64,
+    hash: String,
+    relayed_at: String,
+}
+
+fn key(chain: &str, client_id: &str) -> String {
+    format!("{}/{}", chain, client_id)
+}
+
+/// Reads progress recorded in `PROGRESS_FILE` by earlier runs, if any. Clients relayed
+/// to since the process started keep their latest progress.
+pub fn load() -> Result<(), String> {
+    let recorded = read(PROGRESS_FILE)?;
+    let mut progress = PROGRESS.lock().unwrap();
+    for client in recorded {
+        progress
+            .entry(key(&client.chain, &client.client_id))
+            .or_insert(client);
+    }
+    Ok(())
+}
+
+/// Height of the last header light client `client_id` hosted on `chain` is recorded
+/// to have ingested.
+pub fn last_relayed(chain: &str, client_id: &str) -> Option<u64> {
+    PROGRESS
+        .lock()
+        .unwrap()
+        .get(&key(chain, client_id))
+        .map(|client| client.height)
+}
+
+/// Records that light client `client_id` hosted on `chain` ingested header at `height`
+/// in transaction or extrinsic `hash`, and writes progress of all clients to `PROGRESS_FILE`.
+pub fn relayed(chain: &str, client_id: &str, height: u64, hash: &str) -> Result<(), String> {
+    // Lock is held while writing, so handlers of several clients take turns.
+    let mut progress = PROGRESS.lock().unwrap();
+    progress.insert(
+        key(chain, client_id),
+        ClientProgress {
+            chain: chain.to_owned(),
+            client_id: client_id.to_owned(),
+            height,
+            hash: hash.to_owned(),
+            relayed_at: tendermint::Time::now().to_rfc3339(),
+        },
+    );
+    write(PROGRESS_FILE, &progress)
+}
+
+fn read(path: &str) -> Result<Vec<ClientProgress>, String> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(to_string),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn write(path: &str, progress: &BTreeMap<String, ClientProgress>) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(&progress.values().collect::<Vec<_>>()).map_err(to_string)?;
+    // File is replaced at once, so it is never left half written.
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, json).map_err(to_string)?;
+    fs::rename(&tmp, path).map_err(to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{key, read, write, ClientProgress};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_progress_is_read_back() {
+        let path = std::env::temp_dir().join(format!(
+            "quantum_tunnel_progress_{}.json",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        assert_eq!(read(path), Ok(vec![]));
+
+        let client = ClientProgress {
+            chain: "node-a".to_owned(),
+            client_id: "abcdefghij".to_owned(),
+            height: 42,
+            hash: "AB12".to_owned(),
+            relayed_at: "2020-10-01T10:00:00Z".to_owned(),
+        };
+        let mut progress = BTreeMap::new();
+        progress.insert(key(&client.chain, &client.client_id), client.clone());
+        write(path, &progress).unwrap();
+        assert_eq!(read(path), Ok(vec![client]));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,745 @@
+//! Relayer which can be embedded into other services. It is configured with a
+//! `QuantumTunnelConfig` and signer seeds given programmatically, instead of the
+//! application configuration and environment variables `start` reads them from.
+//!
+//! ```ignore
+//! let mut relayer = Relayer::builder(config)
+//!     .cosmos_signer_seed(cosmos_seed)
+//!     .substrate_signer_seed(substrate_seed)
+//!     .build()?;
+//! let handle = relayer.handle();
+//! let events = relayer.subscribe();
+//! // Handlers are not `Send`, so relayer runs on the current thread of caller's runtime.
+//! relayer.run().await?;
+//! ```
+
+use crate::chain::{self, HandlerFuture, Relay};
+use crate::config::{
+    ChainConfig, CosmosChainConfig, HealthConfig, MetricsConfig, QuantumTunnelConfig, RelayPath,
+    SubstrateChainConfig,
+};
+use crate::cosmos;
+use crate::cosmos::connection::CosmosConnection;
+use crate::error::RelayError;
+use crate::health::{self, Health, HealthReport};
+use crate::metrics::{self, Metrics};
+use crate::progress;
+use crate::report::HeaderRecord;
+use crate::shutdown::Shutdown;
+use crate::substrate;
+use crate::substrate::connection::SubstrateConnection;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use futures::channel::oneshot;
+use futures::future::{self, Either, FutureExt, LocalBoxFuture, Shared};
+use log::*;
+use parse_duration::parse;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Builder of `Relayer`.
+pub struct RelayerBuilder {
+    config: QuantumTunnelConfig,
+    cosmos_signer_seed: Option<String>,
+    substrate_signer_seed: Option<String>,
+    chain_signer_seeds: BTreeMap<String, String>,
+    cosmos_client_id: Option<String>,
+    substrate_client_id: Option<String>,
+}
+
+impl RelayerBuilder {
+    /// Bip39 seed of relayer account on the live cosmos chain.
+    pub fn cosmos_signer_seed(mut self, seed: String) -> Self {
+        self.cosmos_signer_seed = Some(seed);
+        self
+    }
+
+    /// Bip39 seed of relayer account on the live substrate chain.
+    pub fn substrate_signer_seed(mut self, seed: String) -> Self {
+        self.substrate_signer_seed = Some(seed);
+        self
+    }
+
+    /// Bip39 seed of relayer account on chain `name` of `chains`, when relaying paths.
+    pub fn chain_signer_seed(mut self, name: String, seed: String) -> Self {
+        self.chain_signer_seeds.insert(name, seed);
+        self
+    }
+
+    /// Substrate light client on cosmos chain to resume, instead of creating a new one.
+    pub fn cosmos_client_id(mut self, client_id: String) -> Self {
+        self.cosmos_client_id = Some(client_id);
+        self
+    }
+
+    /// Cosmos light client on substrate chain to resume, instead of creating a new one.
+    pub fn substrate_client_id(mut self, client_id: String) -> Self {
+        self.substrate_client_id = Some(client_id);
+        self
+    }
+
+    /// Checks configuration and applies signer seeds to it.
+    pub fn build(self) -> Result<Relayer, RelayError> {
+        let mut config = self.config;
+        // Live chains report status of their handlers and metrics to this relayer only,
+        // and stop along with it.
+        let health = Health::default();
+        let shutdown = Shutdown::default();
+        let metrics = Metrics::default();
+        if let Some(CosmosChainConfig::Real(cfg)) = &mut config.cosmos {
+            cfg.health = health.clone();
+            cfg.shutdown = shutdown.clone();
+            cfg.metrics = metrics.clone();
+        }
+        if let Some(SubstrateChainConfig::Real(cfg)) = &mut config.substrate {
+            cfg.health = health.clone();
+            cfg.shutdown = shutdown.clone();
+            cfg.metrics = metrics.clone();
+        }
+        for chain in config.chains.values_mut() {
+            match chain {
+                ChainConfig::Cosmos(cfg) => {
+                    cfg.health = health.clone();
+                    cfg.shutdown = shutdown.clone();
+                    cfg.metrics = metrics.clone();
+                }
+                ChainConfig::Substrate(cfg) => {
+                    cfg.health = health.clone();
+                    cfg.shutdown = shutdown.clone();
+                    cfg.metrics = metrics.clone();
+                }
+            }
+        }
+        if config.paths.is_empty() && !self.chain_signer_seeds.is_empty() {
+            return Err(RelayError::config(
+                "signer seeds of chains are given, but no paths are configured",
+            ));
+        }
+        if !config.paths.is_empty()
+            && (self.cosmos_signer_seed.is_some()
+                || self.substrate_signer_seed.is_some()
+                || self.cosmos_client_id.is_some()
+                || self.substrate_client_id.is_some())
+        {
+            // Seeds and clients of paths are given per chain and path, so these would
+            // have nothing to apply to.
+            return Err(RelayError::config(
+                "cosmos and substrate signer seeds and client ids cannot be given along with paths, use signer seeds of chains and client ids of paths instead",
+            ));
+        }
+        if config.same_kind_simulation() {
+            // Simulated chain signs nothing and has no other chain to link to.
+        } else if config.paths.is_empty() {
+            let (cosmos, substrate) = config.pair_mut().map_err(RelayError::Config)?;
+            if let (CosmosChainConfig::Real(cfg), Some(seed)) = (cosmos, self.cosmos_signer_seed) {
+                cfg.signer_seed = seed;
+            }
+            if let (SubstrateChainConfig::Real(cfg), Some(seed)) =
+                (substrate, self.substrate_signer_seed)
+            {
+                cfg.signer_seed = seed;
+            }
+            config.link_chains();
+        } else {
+            for (name, seed) in self.chain_signer_seeds {
+                match config.chains.get_mut(&name) {
+                    Some(ChainConfig::Cosmos(cfg)) => cfg.signer_seed = seed,
+                    Some(ChainConfig::Substrate(cfg)) => cfg.signer_seed = seed,
+                    None => return Err(RelayError::Config(format!("unknown chain: {}", name))),
+                }
+            }
+            config.relay_paths().map_err(RelayError::Config)?;
+        }
+        let drain_timeout = parse(&config.shutdown.drain_timeout).map_err(RelayError::config)?;
+
+        let (stop_tx, stop_rx) = oneshot::channel();
+        Ok(Relayer {
+            config,
+            drain_timeout,
+            cosmos_client_id: self.cosmos_client_id,
+            substrate_client_id: self.substrate_client_id,
+            subscribers: vec![],
+            metrics,
+            handle: RelayerHandle {
+                stop: Arc::new(Mutex::new(Some(stop_tx))),
+                health,
+                shutdown,
+            },
+            stop_rx,
+        })
+    }
+}
+
+/// Relayer of headers between chains of a `QuantumTunnelConfig`.
+pub struct Relayer {
+    config: QuantumTunnelConfig,
+    drain_timeout: Duration,
+    cosmos_client_id: Option<String>,
+    substrate_client_id: Option<String>,
+    subscribers: Vec<Sender<HeaderRecord>>,
+    metrics: Metrics,
+    handle: RelayerHandle,
+    stop_rx: oneshot::Receiver<()>,
+}
+
+/// Handle to stop a running relayer and to inspect it.
+#[derive(Clone)]
+pub struct RelayerHandle {
+    stop: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    health: Health,
+    shutdown: Shutdown,
+}
+
+impl RelayerHandle {
+    /// Stops the relayer. Receive handlers stop at once, while send handlers finish the
+    /// transaction or extrinsic they have in flight first.
+    pub fn stop(&self) {
+        if let Some(stop) = self.stop.lock().unwrap().take() {
+            let _ = stop.send(());
+        }
+    }
+
+    /// State of handlers and light clients of the relayer, as reported by `/healthz`
+    /// and `/readyz`.
+    pub fn status(&self) -> HealthReport {
+        self.health.report()
+    }
+}
+
+impl Relayer {
+    /// Starts building a relayer of chains configured in `config`.
+    pub fn builder(config: QuantumTunnelConfig) -> RelayerBuilder {
+        RelayerBuilder {
+            config,
+            cosmos_signer_seed: None,
+            substrate_signer_seed: None,
+            chain_signer_seeds: BTreeMap::new(),
+            cosmos_client_id: None,
+            substrate_client_id: None,
+        }
+    }
+
+    /// Handle to stop the relayer and query its status.
+    pub fn handle(&self) -> RelayerHandle {
+        self.handle.clone()
+    }
+
+    /// Receives outcome of every header sent to a light client. Events queue up until
+    /// they are received, so the receiver should be drained or dropped.
+    pub fn subscribe(&mut self) -> Receiver<HeaderRecord> {
+        let (events_tx, events_rx) = unbounded();
+        self.subscribers.push(events_tx);
+        events_rx
+    }
+
+    /// Relays until a handler stops, which for live chains means an error, or until the
+    /// relayer is stopped through its handle. Either way, send handlers of live chains
+    /// are given `drain_timeout` of the shutdown configuration to finish submissions in
+    /// flight. Has to be run within a tokio runtime.
+    pub async fn run(self) -> Result<(), RelayError> {
+        // Send handlers resuming light clients fall back on progress of earlier runs.
+        if let Err(e) = progress::load() {
+            warn!(
+                "Unable to read relaying progress from {}: {}",
+                progress::PROGRESS_FILE,
+                e
+            );
+        }
+        let (report_tx, report_rx) = unbounded();
+        let events = chain::fan_out(report_rx, self.subscribers);
+
+        let metrics_config = self.config.metrics.clone();
+        let health_config = self.config.health.clone();
+        let shutdown = self.handle.shutdown.clone();
+        let relay = if self.config.same_kind_simulation() {
+            relay_same_kind(self.config, report_tx)
+        } else if self.config.paths.is_empty() {
+            relay_pair(
+                self.config,
+                self.cosmos_client_id,
+                self.substrate_client_id,
+                report_tx,
+            )
+        } else {
+            relay_paths(self.config, report_tx)
+        };
+        let result = match relay {
+            Ok(handlers) => {
+                let stop_rx = self.stop_rx;
+                // Dropping every handle without stopping leaves the relayer running.
+                let stopped = async move {
+                    if stop_rx.await.is_err() {
+                        future::pending::<()>().await;
+                    }
+                };
+                let serving = serve_endpoints(
+                    metrics_config,
+                    health_config,
+                    self.metrics,
+                    handlers.queues,
+                    self.handle.health,
+                );
+                let relaying = relay_until_done(handlers.running);
+                let result = match future::select(
+                    Box::pin(relaying),
+                    future::select(Box::pin(stopped), serving),
+                )
+                .await
+                {
+                    Either::Left((result, _)) => result,
+                    Either::Right(_) => Ok(()),
+                };
+                // Receive handlers are dropped along with the rest of relaying by now.
+                shutdown.request();
+                let drained = drain(handlers.draining, self.drain_timeout).await;
+                result.and(drained)
+            }
+            Err(e) => Err(e),
+        };
+        // Handlers are dropped by now, so every event is passed on once this resolves.
+        let _ = events.await;
+        result
+    }
+}
+
+/// Handlers of a relayer, ready to run.
+#[derive(Default)]
+struct Handlers {
+    /// Every handler, along with what it does, for error messages, and whether it runs
+    /// out of headers.
+    running: Vec<(String, HandlerFuture, bool)>,
+    /// Send handlers of live chains, also in `running`, which are awaited on shutdown.
+    draining: Vec<Shared<HandlerFuture>>,
+    /// Number of headers waiting for each send handler.
+    queues: Vec<metrics::QueueDepth>,
+}
+
+impl Handlers {
+    /// Adds handlers of `relay`, whose source handler is described by `source`, and each
+    /// destination handler by a description along with the name of its queue.
+    fn add(&mut self, relay: Relay, source: String, destinations: Vec<(String, String)>) {
+        self.running.push((source, relay.source, relay.finite));
+        let sends = relay
+            .destinations
+            .into_iter()
+            .zip(relay.live)
+            .zip(relay.queues);
+        for ((description, queue_name), ((send, live), queue)) in
+            destinations.into_iter().zip(sends)
+        {
+            let send = send.shared();
+            if live {
+                self.draining.push(send.clone());
+            }
+            self.running.push((description, Box::pin(send), false));
+            self.queues.push((queue_name, queue));
+        }
+    }
+}
+
+/// Runs `running` handlers until relaying is over: once any of them fails, once one
+/// which does not run out of headers stops, or once every one which does has read all
+/// its headers.
+async fn relay_until_done(running: Vec<(String, HandlerFuture, bool)>) -> Result<(), RelayError> {
+    let mut finite = running.iter().filter(|(_, _, finite)| *finite).count();
+    let (mut descriptions, mut handlers): (Vec<(String, bool)>, Vec<HandlerFuture>) = running
+        .into_iter()
+        .map(|(description, handler, finite)| ((description, finite), handler))
+        .unzip();
+    loop {
+        let (result, index, rest) = future::select_all(handlers).await;
+        // Stopped handler is swapped with the last one, as in `select_all`.
+        let (description, is_finite) = descriptions.swap_remove(index);
+        if let Err(e) = result {
+            return Err(e.context(format!("Error occurred while {}", description)));
+        }
+        if !is_finite {
+            return Ok(());
+        }
+        finite -= 1;
+        if finite == 0 {
+            return Ok(());
+        }
+        handlers = rest;
+    }
+}
+
+/// Handlers relaying between the single pair of chains configured by `cosmos` and
+/// `substrate`.
+fn relay_pair(
+    config: QuantumTunnelConfig,
+    cosmos_client_id: Option<String>,
+    substrate_client_id: Option<String>,
+    report_tx: Sender<HeaderRecord>,
+) -> Result<Handlers, RelayError> {
+    let (cosmos_cfg, substrate_cfg) = config.pair().map_err(RelayError::Config)?;
+
+    // Each relay carries headers of one chain to light client on the other one.
+    let cosmos_to_substrate = chain::relay(
+        cosmos::chain::source(cosmos_cfg.clone()),
+        vec![(
+            substrate::chain::destination(substrate_cfg.clone(), None),
+            substrate_client_id,
+        )],
+        report_tx.clone(),
+    );
+    let substrate_to_cosmos = chain::relay(
+        substrate::chain::source(substrate_cfg.clone()),
+        vec![(
+            cosmos::chain::destination(cosmos_cfg.clone(), None),
+            cosmos_client_id,
+        )],
+        report_tx,
+    );
+
+    let mut handlers = Handlers::default();
+    handlers.add(
+        cosmos_to_substrate,
+        "receiving data from cosmos chain".to_owned(),
+        vec![(
+            "sending data to substrate chain".to_owned(),
+            "cosmos_headers".to_owned(),
+        )],
+    );
+    handlers.add(
+        substrate_to_cosmos,
+        "receiving data from substrate chain".to_owned(),
+        vec![(
+            "sending data to cosmos chain".to_owned(),
+            "substrate_headers".to_owned(),
+        )],
+    );
+    Ok(handlers)
+}
+
+/// Handlers relaying headers of the single simulated chain to an in-process light client
+/// of its own kind, in place of another chain of that kind.
+fn relay_same_kind(
+    config: QuantumTunnelConfig,
+    report_tx: Sender<HeaderRecord>,
+) -> Result<Handlers, RelayError> {
+    let (kind, relay) = match (config.cosmos, config.substrate) {
+        (Some(CosmosChainConfig::Simulation(cfg)), None) => {
+            let client = cosmos::chain::same_kind_client(&cfg);
+            let relay = chain::relay(Box::new(cfg), vec![(client, None)], report_tx);
+            ("cosmos", relay)
+        }
+        (None, Some(SubstrateChainConfig::Simulation(cfg))) => {
+            let client = substrate::chain::same_kind_client(&cfg);
+            let relay = chain::relay(Box::new(cfg), vec![(client, None)], report_tx);
+            ("substrate", relay)
+        }
+        _ => {
+            return Err(RelayError::config(
+                "a single simulated chain has to be configured",
+            ))
+        }
+    };
+
+    let mut handlers = Handlers::default();
+    handlers.add(
+        relay,
+        format!("running {} simulation", kind),
+        vec![(
+            format!("verifying simulated {} headers", kind),
+            format!("{}_headers", kind),
+        )],
+    );
+    Ok(handlers)
+}
+
+/// Handlers relaying every configured path. Each chain is read by a single receive
+/// handler, whose headers are passed to send handlers of all paths of that chain; each
+/// path has its own pair of send handlers, one per light client.
+fn relay_paths(
+    config: QuantumTunnelConfig,
+    report_tx: Sender<HeaderRecord>,
+) -> Result<Handlers, RelayError> {
+    let paths = config.relay_paths().map_err(RelayError::Config)?;
+
+    // Paths hosting light clients on the same chain share its relayer account.
+    let mut cosmos_connections: BTreeMap<String, Rc<CosmosConnection>> = BTreeMap::new();
+    let mut substrate_connections: BTreeMap<String, Rc<SubstrateConnection>> = BTreeMap::new();
+    for path in &paths {
+        if !cosmos_connections.contains_key(&path.cosmos_chain) {
+            let connection = CosmosConnection::new(&path.cosmos)?;
+            cosmos_connections.insert(path.cosmos_chain.clone(), Rc::new(connection));
+        }
+        if !substrate_connections.contains_key(&path.substrate_chain) {
+            let connection = SubstrateConnection::new(&path.substrate)?;
+            substrate_connections.insert(path.substrate_chain.clone(), Rc::new(connection));
+        }
+    }
+
+    let mut paths_by_cosmos: BTreeMap<String, Vec<RelayPath>> = BTreeMap::new();
+    let mut paths_by_substrate: BTreeMap<String, Vec<RelayPath>> = BTreeMap::new();
+    for path in paths {
+        paths_by_cosmos
+            .entry(path.cosmos_chain.clone())
+            .or_default()
+            .push(path.clone());
+        paths_by_substrate
+            .entry(path.substrate_chain.clone())
+            .or_default()
+            .push(path);
+    }
+
+    let mut handlers = Handlers::default();
+    for (name, paths) in paths_by_cosmos {
+        let destinations = paths
+            .iter()
+            .map(|path| {
+                (
+                    substrate::chain::destination(
+                        SubstrateChainConfig::Real(path.substrate.clone()),
+                        Some(substrate_connections[&path.substrate_chain].clone()),
+                    ),
+                    path.substrate_client_id.clone(),
+                )
+            })
+            .collect();
+        let relay = chain::relay(
+            cosmos::chain::source(CosmosChainConfig::Real(paths[0].cosmos.clone())),
+            destinations,
+            report_tx.clone(),
+        );
+        let sends = paths
+            .iter()
+            .map(|path| {
+                (
+                    format!(
+                        "sending data to substrate chain {} on path {}",
+                        path.substrate_chain, path.name
+                    ),
+                    format!("{}/cosmos_headers", path.name),
+                )
+            })
+            .collect();
+        handlers.add(
+            relay,
+            format!("receiving data from cosmos chain {}", name),
+            sends,
+        );
+    }
+    for (name, paths) in paths_by_substrate {
+        let destinations = paths
+            .iter()
+            .map(|path| {
+                (
+                    cosmos::chain::destination(
+                        CosmosChainConfig::Real(path.cosmos.clone()),
+                        Some(cosmos_connections[&path.cosmos_chain].clone()),
+                    ),
+                    path.cosmos_client_id.clone(),
+                )
+            })
+            .collect();
+        let relay = chain::relay(
+            substrate::chain::source(SubstrateChainConfig::Real(paths[0].substrate.clone())),
+            destinations,
+            report_tx.clone(),
+        );
+        let sends = paths
+            .iter()
+            .map(|path| {
+                (
+                    format!(
+                        "sending data to cosmos chain {} on path {}",
+                        path.cosmos_chain, path.name
+                    ),
+                    format!("{}/substrate_headers", path.name),
+                )
+            })
+            .collect();
+        handlers.add(
+            relay,
+            format!("receiving data from substrate chain {}", name),
+            sends,
+        );
+    }
+    // Like with a single pair of chains, relaying stops once any handler stops.
+    Ok(handlers)
+}
+
+/// Waits for send handlers of live chains to finish submissions they have in flight.
+async fn drain(draining: Vec<Shared<HandlerFuture>>, timeout: Duration) -> Result<(), RelayError> {
+    if draining.is_empty() {
+        return Ok(());
+    }
+    info!(
+        "Waiting up to {:?} for transactions in flight to be confirmed",
+        timeout
+    );
+    match tokio::time::timeout(timeout, future::join_all(draining)).await {
+        Ok(results) => {
+            for e in results.into_iter().filter_map(Result::err) {
+                warn!("Send handler stopped with an error: {}", e);
+            }
+            Ok(())
+        }
+        Err(_) => Err(RelayError::Unconfirmed(format!(
+            "transactions in flight were not confirmed within {:?}, they may or may not be included",
+            timeout
+        ))),
+    }
+}
+
+/// Serves metrics and health checks, if configured, for as long as relayer runs. Health
+/// checks are served along with metrics, and on their own address if one is configured.
+fn serve_endpoints(
+    metrics_config: Option<MetricsConfig>,
+    health_config: Option<HealthConfig>,
+    metrics: Metrics,
+    queues: Vec<metrics::QueueDepth>,
+    health: Health,
+) -> LocalBoxFuture<'static, ()> {
+    // Endpoints are best effort, failing to serve them does not stop the relay.
+    let serving_health = {
+        let health = health.clone();
+        async move {
+            if let Some(health_config) = health_config {
+                if let Err(e) = health::serve(health_config, health).await {
+                    error!("Health check server terminated: {}", e);
+                }
+            }
+        }
+    };
+    let serving_metrics = async move {
+        if let Some(metrics_config) = metrics_config {
+            if let Err(e) = metrics::serve(metrics_config, metrics, queues, health).await {
+                error!("Metrics server terminated: {}", e);
+            }
+        }
+    };
+    Box::pin(async move {
+        future::join(serving_health, serving_metrics).await;
+        future::pending::<()>().await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{drain, relay_until_done, Relayer};
+    use crate::chain::HandlerFuture;
+    use crate::config::{ChainConfig, QuantumTunnelConfig};
+    use crate::error::RelayError;
+    use futures::future::{self, FutureExt};
+    use std::time::Duration;
+
+    fn done() -> HandlerFuture {
+        future::ready(Ok(())).boxed_local()
+    }
+
+    fn running() -> HandlerFuture {
+        future::pending().boxed_local()
+    }
+
+    #[test]
+    fn test_relaying_waits_for_every_finite_source() {
+        let relaying = relay_until_done(vec![
+            ("first simulation".to_owned(), done(), true),
+            ("second simulation".to_owned(), running(), true),
+            ("in-process client".to_owned(), running(), false),
+        ]);
+        assert!(relaying.now_or_never().is_none());
+
+        let relaying = relay_until_done(vec![
+            ("first simulation".to_owned(), done(), true),
+            ("second simulation".to_owned(), done(), true),
+            ("in-process client".to_owned(), running(), false),
+        ]);
+        assert_eq!(relaying.now_or_never(), Some(Ok(())));
+
+        // Live chain never runs out of headers, so it stops relaying as soon as it stops.
+        let relaying = relay_until_done(vec![
+            ("simulation".to_owned(), running(), true),
+            ("live chain".to_owned(), done(), false),
+        ]);
+        assert_eq!(relaying.now_or_never(), Some(Ok(())));
+
+        let relaying = relay_until_done(vec![
+            ("simulation".to_owned(), running(), true),
+            (
+                "live chain".to_owned(),
+                future::ready(Err(RelayError::Transport("unreachable".to_owned()))).boxed_local(),
+                false,
+            ),
+        ]);
+        let err = relaying.now_or_never().unwrap().unwrap_err();
+        assert!(matches!(err, RelayError::Transport(_)));
+        assert!(err.to_string().contains("live chain"), "{}", err);
+    }
+
+    #[test]
+    fn test_drain_waits_for_submissions_in_flight() {
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_time()
+            .build()
+            .unwrap();
+        let timeout = Duration::from_millis(50);
+        assert_eq!(runtime.block_on(drain(vec![], timeout)), Ok(()));
+
+        // Send handler which stopped with an error has nothing left in flight.
+        let stopped = vec![
+            done().shared(),
+            future::ready(Err(RelayError::Transport("unreachable".to_owned())))
+                .boxed_local()
+                .shared(),
+        ];
+        assert_eq!(runtime.block_on(drain(stopped, timeout)), Ok(()));
+
+        let stuck = vec![done().shared(), running().shared()];
+        let err = runtime.block_on(drain(stuck, timeout)).unwrap_err();
+        assert!(err.to_string().contains("not confirmed"), "{}", err);
+    }
+
+    #[test]
+    fn test_shutdown_is_requested_of_chains_of_stopped_relayer_only() {
+        let data = std::fs::read_to_string("test_data/paths_config.json").unwrap();
+        let config: QuantumTunnelConfig = serde_json::from_str(&data).unwrap();
+        let stopped = Relayer::builder(config.clone()).build().unwrap();
+        let running = Relayer::builder(config).build().unwrap();
+        stopped.handle().shutdown.request();
+        let requested = |relayer: &Relayer| -> Vec<bool> {
+            relayer
+                .config
+                .chains
+                .values()
+                .map(|chain| match chain {
+                    ChainConfig::Cosmos(cfg) => cfg.shutdown.requested(),
+                    ChainConfig::Substrate(cfg) => cfg.shutdown.requested(),
+                })
+                .collect()
+        };
+        assert_eq!(requested(&stopped), vec![true; 2]);
+        assert_eq!(requested(&running), vec![false; 2]);
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_settings() {
+        let data = std::fs::read_to_string("test_data/paths_config.json").unwrap();
+        let config: QuantumTunnelConfig = serde_json::from_str(&data).unwrap();
+        assert!(Relayer::builder(config.clone())
+            .chain_signer_seed("node-a".to_owned(), "seed".to_owned())
+            .build()
+            .is_ok());
+        assert!(Relayer::builder(config.clone())
+            .chain_signer_seed("node-c".to_owned(), "seed".to_owned())
+            .build()
+            .is_err());
+        assert!(Relayer::builder(config.clone())
+            .cosmos_signer_seed("seed".to_owned())
+            .build()
+            .is_err());
+        assert!(Relayer::builder(config.clone())
+            .substrate_client_id("testingclient".to_owned())
+            .build()
+            .is_err());
+
+        let mut config = config;
+        config.shutdown.drain_timeout = "soon".to_owned();
+        assert!(Relayer::builder(config).build().is_err());
+    }
+}
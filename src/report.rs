@@ -0,0 +1,191 @@
+//! Machine readable report of a simulation run, written in JSON and,
+//! optionally, JUnit XML so CI can show exactly which header failed.
+
+use crate::config::QuantumTunnelConfig;
+use crate::utils::to_string;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::time::Duration;
+
+/// Outcome of sending a single header to a light client.
+#[derive(Clone, Debug, Serialize)]
+pub struct HeaderRecord {
+    /// Chain whose light client received the header.
+    pub chain: String,
+    /// Height of the header.
+    pub height: u64,
+    /// Hash of the transaction or extrinsic carrying the header, if any.
+    pub hash: Option<String>,
+    /// Whether light client accepted the header.
+    pub accepted: bool,
+    /// Error returned while sending the header.
+    pub error: Option<String>,
+    /// Time spent sending the header, in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl HeaderRecord {
+    /// Builds a record from the result of sending a header.
+    pub fn new(
+        chain: &str,
+        height: u64,
+        result: &Result<Option<String>, String>,
+        duration: Duration,
+    ) -> Self {
+        HeaderRecord {
+            chain: chain.to_owned(),
+            height,
+            hash: result.as_ref().ok().cloned().flatten(),
+            accepted: result.is_ok(),
+            error: result.as_ref().err().cloned(),
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+}
+
+/// Report of a whole quantum tunnel run.
+#[derive(Debug, Serialize)]
+pub struct SimulationReport<'a> {
+    /// Configuration the run was started with.
+    pub config: &'a QuantumTunnelConfig,
+    /// Whether the run finished successfully.
+    pub success: bool,
+    /// Error which terminated the run.
+    pub error: Option<String>,
+    /// Duration of the whole run, in milliseconds.
+    pub duration_ms: u64,
+    /// Every header sent to a light client, in order.
+    pub headers: Vec<HeaderRecord>,
+}
+
+impl<'a> SimulationReport<'a> {
+    /// Creates a report out of the run result and collected header records.
+    pub fn new(
+        config: &'a QuantumTunnelConfig,
+        result: &Result<(), String>,
+        duration: Duration,
+        headers: Vec<HeaderRecord>,
+    ) -> Self {
+        SimulationReport {
+            config,
+            success: result.is_ok(),
+            error: result.as_ref().err().cloned(),
+            duration_ms: duration.as_millis() as u64,
+            headers,
+        }
+    }
+
+    /// Writes report as pretty printed JSON.
+    pub fn write_json(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(to_string)?;
+        let mut file = File::create(path).map_err(to_string)?;
+        file.write_all(json.as_bytes()).map_err(to_string)
+    }
+
+    /// Writes report as JUnit XML, one test suite per chain and one test case
+    /// per header.
+    pub fn write_junit(&self, path: &str) -> Result<(), String> {
+        let mut file = File::create(path).map_err(to_string)?;
+        file.write_all(self.to_junit().as_bytes())
+            .map_err(to_string)
+    }
+
+    fn to_junit(&self) -> String {
+        let mut chains: Vec<&str> = self.headers.iter().map(|h| h.chain.as_str()).collect();
+        chains.sort();
+        chains.dedup();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"quantum-tunnel\" tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+            self.headers.len(),
+            self.headers.iter().filter(|h| !h.accepted).count(),
+            seconds(self.duration_ms)
+        ));
+        for chain in chains {
+            let headers: Vec<&HeaderRecord> =
+                self.headers.iter().filter(|h| h.chain == chain).collect();
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+                escape(chain),
+                headers.len(),
+                headers.iter().filter(|h| !h.accepted).count(),
+                seconds(headers.iter().map(|h| h.duration_ms).sum())
+            ));
+            for header in headers {
+                xml.push_str(&format!(
+                    "    <testcase classname=\"{}\" name=\"height {}\" time=\"{}\"",
+                    escape(chain),
+                    header.height,
+                    seconds(header.duration_ms)
+                ));
+                match &header.error {
+                    Some(error) => xml.push_str(&format!(
+                        ">\n      <failure message=\"{}\"/>\n    </testcase>\n",
+                        escape(error)
+                    )),
+                    None => xml.push_str("/>\n"),
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        if let Some(error) = &self.error {
+            xml.push_str(&format!("  <system-err>{}</system-err>\n", escape(error)));
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+}
+
+fn seconds(millis: u64) -> String {
+    format!("{:.3}", millis as f64 / 1000.0)
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeaderRecord, SimulationReport};
+    use crate::config::QuantumTunnelConfig;
+    use std::time::Duration;
+
+    #[test]
+    fn test_junit_report_lists_failed_header() {
+        let config = QuantumTunnelConfig::default();
+        let headers = vec![
+            HeaderRecord::new(
+                "substrate",
+                10,
+                &Ok(Some("0x01".to_owned())),
+                Duration::from_millis(1500),
+            ),
+            HeaderRecord::new(
+                "substrate",
+                11,
+                &Err("height < \"trusted\"".to_owned()),
+                Duration::from_millis(20),
+            ),
+        ];
+        let report = SimulationReport::new(
+            &config,
+            &Err("simulation failed".to_owned()),
+            Duration::from_secs(2),
+            headers,
+        );
+        let xml = report.to_junit();
+        assert!(xml
+            .contains("<testsuite name=\"substrate\" tests=\"2\" failures=\"1\" time=\"1.520\">"));
+        assert!(xml.contains("<failure message=\"height &lt; &quot;trusted&quot;\"/>"));
+        assert!(
+            xml.contains("<testcase classname=\"substrate\" name=\"height 10\" time=\"1.500\"/>")
+        );
+    }
+}
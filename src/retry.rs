@@ -0,0 +1,189 @@
+//! Policy shared by send handlers, deciding what to do once a submission fails:
+//! errors which may go away are retried with backoff, and once retries run out the
+//! header is skipped if the light client can do without it. Anything else halts.
+
+use crate::config::RetryConfig;
+use crate::error::RelayError;
+use log::*;
+use parse_duration::parse;
+use std::future::Future;
+use std::time::Duration;
+
+/// What a send handler does after an attempt failed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decision {
+    /// Try again after the delay.
+    Retry(Duration),
+    /// Give up on the header and relay the next one.
+    Skip,
+    /// Stop relaying.
+    Halt,
+}
+
+/// Retry policy of a send handler.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    skippable: bool,
+}
+
+impl RetryPolicy {
+    /// Policy configured by `cfg`. `skippable` tells whether the light client verifies
+    /// headers which do not follow the last one it ingested, so some can be skipped.
+    pub fn new(cfg: &RetryConfig, skippable: bool) -> Result<Self, RelayError> {
+        Ok(Self {
+            max_attempts: cfg.max_attempts.max(1),
+            initial_backoff: parse(&cfg.initial_backoff).map_err(RelayError::config)?,
+            max_backoff: parse(&cfg.max_backoff).map_err(RelayError::config)?,
+            skippable,
+        })
+    }
+
+    /// Decides what to do after `attempt`, counted from one, failed with `err`.
+    pub fn decide(&self, err: &RelayError, attempt: u32) -> Decision {
+        if !err.is_retriable() {
+            Decision::Halt
+        } else if attempt < self.max_attempts {
+            Decision::Retry(self.backoff(attempt))
+        } else if self.skippable {
+            Decision::Skip
+        } else {
+            Decision::Halt
+        }
+    }
+
+    /// Calls `f` until it succeeds, or until the policy halts or would skip, so `f`
+    /// has nothing to skip to. Failed attempts are logged as attempts to do `what`.
+    pub async fn call<T, F, Fut>(&self, what: &str, mut f: F) -> Result<T, RelayError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, RelayError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let err = match f().await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            match self.decide(&err, attempt) {
+                Decision::Retry(backoff) => {
+                    warn!(
+                        "Attempt {} to {} failed: {}, retrying in {:?}",
+                        attempt, what, err, backoff
+                    );
+                    tokio::time::delay_for(backoff).await;
+                    attempt += 1;
+                }
+                Decision::Skip | Decision::Halt => return Err(err),
+            }
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decision, RetryPolicy};
+    use crate::config::RetryConfig;
+    use crate::error::RelayError;
+    use std::time::Duration;
+
+    #[test]
+    fn test_retries_until_attempts_run_out() {
+        let cfg = RetryConfig {
+            max_attempts: 4,
+            initial_backoff: "1s".to_owned(),
+            max_backoff: "3s".to_owned(),
+        };
+        let policy = RetryPolicy::new(&cfg, false).unwrap();
+        let transport = RelayError::Transport("connection refused".to_owned());
+        assert_eq!(
+            policy.decide(&transport, 1),
+            Decision::Retry(Duration::from_secs(1))
+        );
+        assert_eq!(
+            policy.decide(&transport, 2),
+            Decision::Retry(Duration::from_secs(2))
+        );
+        assert_eq!(
+            policy.decide(&transport, 3),
+            Decision::Retry(Duration::from_secs(3))
+        );
+        assert_eq!(policy.decide(&transport, 4), Decision::Halt);
+        assert_eq!(
+            RetryPolicy::new(&cfg, true).unwrap().decide(&transport, 4),
+            Decision::Skip
+        );
+
+        let wrong_sequence = RelayError::TxRejected {
+            codespace: Some("sdk".to_owned()),
+            code: Some(4),
+            log: "signature verification failed; verify correct account sequence and chain-id: unauthorized".to_owned(),
+        };
+        assert_eq!(
+            policy.decide(&wrong_sequence, 1),
+            Decision::Retry(Duration::from_secs(1))
+        );
+        let rejected = RelayError::TxRejected {
+            codespace: Some("sdk".to_owned()),
+            code: Some(5),
+            log: "insufficient funds".to_owned(),
+        };
+        assert_eq!(policy.decide(&rejected, 1), Decision::Halt);
+        // Module errors reuse codes of the sdk ones.
+        let rejected = RelayError::TxRejected {
+            codespace: Some("wasm".to_owned()),
+            code: Some(4),
+            log: "invalid header".to_owned(),
+        };
+        assert_eq!(policy.decide(&rejected, 1), Decision::Halt);
+        let verification = RelayError::Verification("invalid commit".to_owned());
+        assert_eq!(policy.decide(&verification, 1), Decision::Halt);
+        // Sending it again might apply it twice.
+        let unconfirmed = RelayError::Unconfirmed("connection closed".to_owned());
+        assert_eq!(policy.decide(&unconfirmed, 1), Decision::Halt);
+    }
+
+    #[test]
+    fn test_call_is_retried_until_it_succeeds_or_halts() {
+        let cfg = RetryConfig {
+            max_attempts: 3,
+            initial_backoff: "1ms".to_owned(),
+            max_backoff: "1ms".to_owned(),
+        };
+        let policy = RetryPolicy::new(&cfg, true).unwrap();
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut run = |failures: u32, err: RelayError| {
+            let mut attempts = 0;
+            let result = runtime.block_on(policy.call("list clients", || {
+                attempts += 1;
+                let result = if attempts > failures {
+                    Ok(attempts)
+                } else {
+                    Err(err.clone())
+                };
+                async move { result }
+            }));
+            (result, attempts)
+        };
+
+        let outage = RelayError::Transport("connection refused".to_owned());
+        assert_eq!(run(2, outage.clone()), (Ok(3), 3));
+        // Call has no header to skip, so it fails once attempts run out.
+        assert_eq!(run(3, outage.clone()), (Err(outage), 3));
+        let invalid = RelayError::Config("invalid client id".to_owned());
+        assert_eq!(run(1, invalid.clone()), (Err(invalid), 1));
+    }
+}
@@ -0,0 +1,40 @@
+//! Graceful shutdown of send handlers. Once shutdown is requested, send handlers
+//! finish the transaction or extrinsic they have in flight and stop before taking
+//! the next header, instead of being dropped in the middle of a broadcast.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shutdown of a relayer, shared by its handle and send handlers of its chains.
+#[derive(Clone, Debug, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    /// Asks send handlers to stop once their submission in flight is confirmed.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether send handlers should stop instead of taking the next header.
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shutdown;
+
+    #[test]
+    fn test_shutdown_is_requested_of_one_relayer_only() {
+        let relayer = Shutdown::default();
+        let handler = relayer.clone();
+        let other = Shutdown::default();
+        assert!(!handler.requested());
+        relayer.request();
+        assert!(handler.requested());
+        assert!(!other.requested());
+    }
+}
@@ -0,0 +1,208 @@
+//! Substrate chain as a source of GRANDPA finalized headers and as a host of tendermint
+//! light clients.
+
+use crate::chain::{ClientStateQuerier, DestinationChain, HandlerFuture, SourceChain};
+use crate::client_state::ClientState;
+use crate::config::{
+    SimulationTiming, SubstrateChainConfig, SubstrateConfig, SubstrateSimulationConfig,
+};
+use crate::cosmos::chain::InProcessGrandpaClient;
+use crate::cosmos::types::TMHeader;
+use crate::error::RelayError;
+use crate::failover;
+use crate::monitoring::MonitoringEvent;
+use crate::report::HeaderRecord;
+use crate::substrate::connection::SubstrateConnection;
+use crate::substrate::types::SignedBlockWithAuthoritySet;
+use crate::substrate::Handler as SubstrateHandler;
+use crossbeam_channel::{Receiver, Sender};
+use futures::future::{FutureExt, LocalBoxFuture};
+use parse_duration::parse;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Substrate chain configured by `cfg`, live or simulated, as a source of headers.
+pub fn source(
+    cfg: SubstrateChainConfig,
+) -> Box<dyn SourceChain<Header = SignedBlockWithAuthoritySet>> {
+    match cfg {
+        SubstrateChainConfig::Real(cfg) => Box::new(cfg),
+        SubstrateChainConfig::Simulation(cfg) => Box::new(cfg),
+    }
+}
+
+/// Substrate chain configured by `cfg`, live or simulated, as a host of tendermint light
+/// client. Live chain is submitted to through `connection` if given, which may be shared
+/// with other paths. If both sides are simulation, headers are verified by in-process
+/// light client.
+pub fn destination(
+    cfg: SubstrateChainConfig,
+    connection: Option<Rc<SubstrateConnection>>,
+) -> Box<dyn DestinationChain<Header = (TMHeader, Vec<tendermint::validator::Info>)>> {
+    match cfg {
+        SubstrateChainConfig::Real(cfg) => Box::new(SubstrateDestination { cfg, connection }),
+        SubstrateChainConfig::Simulation(cfg) if cfg.is_other_side_simulation => {
+            Box::new(InProcessTendermintClient {
+                chain: "substrate".to_string(),
+                trusting_period: cfg.trusting_period,
+                max_clock_drift: cfg.max_clock_drift,
+                timing: cfg.timing,
+            })
+        }
+        SubstrateChainConfig::Simulation(cfg) => Box::new(cfg),
+    }
+}
+
+/// Substrate light client verifying headers of simulated substrate chain configured by
+/// `cfg` in-process, in place of another substrate chain hosting it.
+pub fn same_kind_client(
+    cfg: &SubstrateSimulationConfig,
+) -> Box<dyn DestinationChain<Header = SignedBlockWithAuthoritySet>> {
+    Box::new(InProcessGrandpaClient {
+        chain: "substrate".to_string(),
+        timing: cfg.timing.clone(),
+    })
+}
+
+/// Live substrate chain read by the relayer.
+impl SourceChain for SubstrateConfig {
+    type Header = SignedBlockWithAuthoritySet;
+
+    fn read_headers(
+        self: Box<Self>,
+        outchan: Sender<Self::Header>,
+        _monitoring_inchan: Receiver<MonitoringEvent>,
+    ) -> HandlerFuture {
+        SubstrateHandler::recv_handler(*self, outchan).boxed_local()
+    }
+}
+
+/// Simulated substrate chain, whose headers are read from the simulation file. It
+/// finishes once light client of opposite chain is done with them.
+impl SourceChain for SubstrateSimulationConfig {
+    type Header = SignedBlockWithAuthoritySet;
+
+    fn read_headers(
+        self: Box<Self>,
+        outchan: Sender<Self::Header>,
+        monitoring_inchan: Receiver<MonitoringEvent>,
+    ) -> HandlerFuture {
+        SubstrateHandler::simulate_recv_handler(
+            self.simulation_file_path,
+            self.should_run_till_height,
+            self.expected_rejection,
+            self.timing,
+            outchan,
+            monitoring_inchan,
+        )
+        .boxed_local()
+    }
+
+    fn is_finite(&self) -> bool {
+        true
+    }
+}
+
+/// Live substrate chain hosting tendermint light client. The chain is submitted to
+/// through `connection` if given, which may be shared with other paths.
+pub struct SubstrateDestination {
+    pub cfg: SubstrateConfig,
+    pub connection: Option<Rc<SubstrateConnection>>,
+}
+
+impl DestinationChain for SubstrateDestination {
+    type Header = (TMHeader, Vec<tendermint::validator::Info>);
+
+    fn update_client(
+        self: Box<Self>,
+        client_id: Option<String>,
+        inchan: Receiver<Self::Header>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> HandlerFuture {
+        SubstrateHandler::send_handler(
+            self.cfg,
+            self.connection,
+            client_id,
+            inchan,
+            monitoring_outchan,
+            report_outchan,
+        )
+        .boxed_local()
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+}
+
+/// Simulated substrate chain opposite a live cosmos chain, which cannot ingest its
+/// headers, so they are drained.
+impl DestinationChain for SubstrateSimulationConfig {
+    type Header = (TMHeader, Vec<tendermint::validator::Info>);
+
+    fn update_client(
+        self: Box<Self>,
+        _client_id: Option<String>,
+        inchan: Receiver<Self::Header>,
+        _monitoring_outchan: Sender<MonitoringEvent>,
+        _report_outchan: Sender<HeaderRecord>,
+    ) -> HandlerFuture {
+        SubstrateHandler::drain_handler(*self, inchan).boxed_local()
+    }
+}
+
+/// Tendermint light client verifying headers in-process, in place of the chain hosting
+/// it, which is simulated as well, or not configured at all when a simulated cosmos chain
+/// is relayed to one of its own kind.
+pub struct InProcessTendermintClient {
+    /// Chain the client stands in for, as named in relay events.
+    pub chain: String,
+    /// Trusting period of the client, e.g. 72h.
+    pub trusting_period: String,
+    /// Clock drift tolerance of the client.
+    pub max_clock_drift: String,
+    /// Timing of the simulation run.
+    pub timing: SimulationTiming,
+}
+
+impl DestinationChain for InProcessTendermintClient {
+    type Header = (TMHeader, Vec<tendermint::validator::Info>);
+
+    fn update_client(
+        self: Box<Self>,
+        _client_id: Option<String>,
+        inchan: Receiver<Self::Header>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> HandlerFuture {
+        SubstrateHandler::in_process_send_handler(*self, inchan, monitoring_outchan, report_outchan)
+            .boxed_local()
+    }
+}
+
+impl ClientStateQuerier for SubstrateConfig {
+    fn trusting_period(&self) -> Result<Duration, RelayError> {
+        parse(&self.trusting_period).map_err(RelayError::config)
+    }
+
+    fn client_state(
+        &self,
+        client_id: String,
+    ) -> LocalBoxFuture<'static, Result<ClientState, RelayError>> {
+        let (metrics, name, addrs) = (self.metrics.clone(), self.name.clone(), self.rpc_addrs());
+        async move {
+            failover::call(&metrics, &name, &addrs, |rpc_addr| {
+                SubstrateHandler::query_client_state(rpc_addr, client_id.clone())
+            })
+            .await
+        }
+        .boxed_local()
+    }
+
+    fn client_ids(&self) -> LocalBoxFuture<'static, Result<Vec<String>, RelayError>> {
+        let (metrics, name, addrs) = (self.metrics.clone(), self.name.clone(), self.rpc_addrs());
+        async move { failover::call(&metrics, &name, &addrs, SubstrateHandler::query_client_ids).await }
+            .boxed_local()
+    }
+}
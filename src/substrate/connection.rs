@@ -0,0 +1,221 @@
+//! Relayer account on a substrate chain. Send handlers of every path hosting light
+//! clients on the chain share one connection, so they sign with the same key, submit
+//! through the same node and take turns submitting extrinsics.
+
+use crate::config::SubstrateConfig;
+use crate::error::RelayError;
+use crate::failover;
+use crate::metrics::Metrics;
+use crate::substrate::handler::{RelayerSigner, SubxtClient};
+use futures::lock::{Mutex, MutexGuard};
+use log::*;
+use sp_core::sr25519::Pair as Sr25519Pair;
+use sp_core::Pair;
+use substrate_subxt::system::{AccountStoreExt, System};
+use substrate_subxt::{ClientBuilder, NodeTemplateRuntime, PairSigner, Signer};
+
+/// Nonce of relayer account.
+pub type Nonce = <NodeTemplateRuntime as System>::Index;
+
+/// Relayer account on a substrate chain, along with the node it submits extrinsics to.
+pub struct SubstrateConnection {
+    /// Name of the chain in metrics and logs.
+    chain: String,
+    /// Metrics of the relayer relaying the chain.
+    metrics: Metrics,
+    ws_addrs: Vec<String>,
+    signer: RelayerSigner,
+    /// Connected node, none until extrinsics are submitted. Extrinsics are submitted
+    /// under the lock, or they would race on the account nonce.
+    node: Mutex<Option<Node>>,
+}
+
+/// Node extrinsics are submitted to.
+struct Node {
+    client: SubxtClient,
+    ws_addr: String,
+    /// Free balance of relayer account after the last extrinsic, if known.
+    free_balance: Option<u128>,
+}
+
+/// Turn of a handler to submit extrinsics, which lasts until it is dropped.
+pub struct Submission<'a> {
+    connection: &'a SubstrateConnection,
+    node: MutexGuard<'a, Option<Node>>,
+}
+
+impl SubstrateConnection {
+    /// Derives relayer account of the chain configured by `cfg` from its signer seed.
+    /// Node is connected to once extrinsics are submitted.
+    pub fn new(cfg: &SubstrateConfig) -> Result<Self, RelayError> {
+        let (pair, _) = Sr25519Pair::from_phrase(cfg.signer_seed.as_str(), None)
+            .map_err(|e| RelayError::Signer(format!("{:?}", e)))?;
+        Ok(SubstrateConnection {
+            chain: cfg.name.clone(),
+            metrics: cfg.metrics.clone(),
+            ws_addrs: cfg.ws_addrs(),
+            signer: PairSigner::new(pair),
+            node: Mutex::new(None),
+        })
+    }
+
+    /// Connects to the preferred node of the chain.
+    async fn connect(&self) -> Result<Node, RelayError> {
+        let (client, ws_addr) = failover::call(
+            &self.metrics,
+            &self.chain,
+            &self.ws_addrs,
+            |ws_addr| async move {
+                let client = ClientBuilder::<NodeTemplateRuntime>::new()
+                    .set_url(ws_addr.clone())
+                    .build()
+                    .await
+                    .map_err(RelayError::transport)?;
+                Ok::<_, RelayError>((client, ws_addr))
+            },
+        )
+        .await?;
+        // Balance before the first extrinsic, so that its fee is counted as well.
+        let free_balance = match client.account(self.signer.account_id(), None).await {
+            Ok(account) => Some(account.data.free),
+            Err(e) => {
+                warn!("Unable to query balance of relayer account: {}", e);
+                None
+            }
+        };
+        Ok(Node {
+            client,
+            ws_addr,
+            free_balance,
+        })
+    }
+
+    /// Waits for the turn to submit extrinsics, connecting to the chain unless connected.
+    pub async fn lock(&self) -> Result<Submission<'_>, RelayError> {
+        let mut node = self.node.lock().await;
+        if node.is_none() {
+            *node = Some(self.connect().await?);
+        }
+        Ok(Submission {
+            connection: self,
+            node,
+        })
+    }
+
+    /// Connects to the chain unless connected, trying all nodes again after a delay while
+    /// none of them can be reached.
+    pub async fn connect_until_success(&self) -> Result<(), RelayError> {
+        let mut backoff = failover::Backoff::new();
+        loop {
+            match self.lock().await {
+                Err(RelayError::Transport(e)) => {
+                    let delay = backoff.delay();
+                    warn!(
+                        "Unable to connect to {} chain: {}, trying again in {:?}",
+                        self.chain, e, delay
+                    );
+                    tokio::time::delay_for(delay).await;
+                }
+                result => return result.map(|_| ()),
+            }
+        }
+    }
+}
+
+impl<'a> Submission<'a> {
+    fn node(&self) -> &Node {
+        self.node.as_ref().expect("node is connected while locked")
+    }
+
+    /// Client connected to the node extrinsics are submitted to.
+    pub fn client(&self) -> &SubxtClient {
+        &self.node().client
+    }
+
+    /// Relayer account signing extrinsics.
+    pub fn signer(&self) -> &RelayerSigner {
+        &self.connection.signer
+    }
+
+    /// Moves over to another node if extrinsic did not reach the chain because of
+    /// `err`, so the next attempt goes elsewhere if one is configured.
+    pub async fn reconnect(&mut self, err: &RelayError) {
+        if !matches!(err, RelayError::Transport(_)) {
+            return;
+        }
+        failover::failed(
+            &self.connection.metrics,
+            &self.connection.chain,
+            &self.node().ws_addr,
+            err,
+        );
+        match self.connection.connect().await {
+            Ok(node) => *self.node = Some(node),
+            Err(e) => warn!(
+                "Unable to reconnect to {} chain: {}",
+                self.connection.chain, e
+            ),
+        }
+    }
+
+    /// Nonce the next extrinsic of relayer account is signed with.
+    pub async fn nonce(&self) -> Result<Nonce, RelayError> {
+        self.client()
+            .account(self.connection.signer.account_id(), None)
+            .await
+            .map(|account| account.nonce)
+            .map_err(RelayError::transport)
+    }
+
+    /// Tells what became of extrinsic signed with account `nonce`, which failed with `err`
+    /// after it may have reached the node. Once the chain includes the extrinsic, nonce of
+    /// the account moves past it, and the extrinsic must not be sent again.
+    pub async fn unconfirmed(&self, nonce: Nonce, err: RelayError) -> RelayError {
+        // Dispatch error is reported only for extrinsic included in a block.
+        if let RelayError::TxRejected { .. } = err {
+            return err;
+        }
+        match self.nonce().await {
+            Ok(current) if current > nonce => RelayError::Unconfirmed(format!(
+                "{}, extrinsic with account nonce {} was included by the chain nevertheless",
+                err, nonce
+            )),
+            Ok(_) => err,
+            Err(e) => RelayError::Unconfirmed(format!(
+                "{}, unable to tell whether extrinsic with account nonce {} was included by the chain: {}",
+                err, nonce, e
+            )),
+        }
+    }
+
+    /// Publishes balance of relayer account, and fee spent since it was last known.
+    pub async fn observe_balance(&mut self) {
+        let chain = &self.connection.chain;
+        let node = self.node.as_mut().expect("node is connected while locked");
+        match node
+            .client
+            .account(self.connection.signer.account_id(), None)
+            .await
+        {
+            Ok(account) => {
+                let free = account.data.free;
+                // Relayer account only pays for extrinsics, and they are submitted one at
+                // a time, so any decrease of its balance is the fee spent.
+                if let Some(previous) = node.free_balance {
+                    if free < previous {
+                        self.connection.metrics.observe_fee(
+                            chain,
+                            "native",
+                            (previous - free) as f64,
+                        );
+                    }
+                }
+                node.free_balance = Some(free);
+                self.connection
+                    .metrics
+                    .observe_balance(chain, "native", free as f64);
+            }
+            Err(e) => warn!("Unable to query balance of relayer account: {}", e),
+        }
+    }
+}
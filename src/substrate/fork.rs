@@ -0,0 +1,307 @@
+//! Detection of GRANDPA equivocations and forks. Every finalized block received
+//! from the primary node is compared with the block finalized at the same height
+//! by witness nodes; GRANDPA finalizes a single block per height, so any
+//! difference means authorities equivocated or one of the nodes is lying. A lying
+//! node cannot forge justifications, so a fork is declared once both blocks are
+//! proven finalized with the checks light client applies. A different block which
+//! cannot be proven either way halts relaying all the same.
+
+use crate::config::SubstrateConfig;
+use crate::error::RelayError;
+use crate::failover;
+use crate::metrics::Metrics;
+use crate::substrate::handler::{
+    classify_query_error, get_authset_with_id, get_block_at_height, get_finalized_block_number,
+};
+use crate::substrate::types::{
+    AuthorityList, GrandpaJustification, Header, SignedBlock, SignedBlockWithAuthoritySet,
+};
+use crate::substrate::verifier::verify_justification;
+use log::*;
+use parity_scale_codec::Decode;
+use sp_core::H256;
+use sp_finality_grandpa::AuthorityId;
+use sp_runtime::traits::Header as HeaderT;
+use std::fmt;
+
+/// Blocks following a block without justification which are searched for one whose
+/// justification proves it finalized. GRANDPA attaches a justification at least to every
+/// block changing authority set, and nodes keep one every `justification_period` blocks,
+/// which is 512 in the node template.
+const MAX_JUSTIFICATION_DISTANCE: u32 = 512;
+
+/// Conflicting finalized blocks at the same height.
+#[derive(Debug)]
+pub struct ForkEvidence {
+    pub height: u32,
+    pub primary_hash: H256,
+    pub witness_rpc_addr: String,
+    pub witness_hash: H256,
+    /// Authorities which signed justifications of both blocks.
+    pub equivocators: Vec<AuthorityId>,
+}
+
+impl fmt::Display for ForkEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "primary and witness {} finalized different blocks at height: {}, primary: {:?}, witness: {:?}, equivocating authorities: {:?}",
+            self.witness_rpc_addr, self.height, self.primary_hash, self.witness_hash, self.equivocators
+        )
+    }
+}
+
+/// Justification proving a block finalized, which targets the block itself or one of its
+/// descendants, along with the authority set the node reports at its target.
+struct FinalityProof {
+    target: Header,
+    justification: Vec<u8>,
+    authority_set: AuthorityList,
+    set_id: u64,
+}
+
+pub struct ForkMonitor {
+    metrics: Metrics,
+    chain: String,
+    rpc_addrs: Vec<String>,
+    witness_rpc_addrs: Vec<String>,
+}
+
+impl ForkMonitor {
+    /// Monitor of the chain configured by `cfg`, whose primary nodes are asked to prove
+    /// finality of blocks without justification.
+    pub fn new(cfg: &SubstrateConfig) -> Self {
+        ForkMonitor {
+            metrics: cfg.metrics.clone(),
+            chain: cfg.name.clone(),
+            rpc_addrs: cfg.rpc_addrs(),
+            witness_rpc_addrs: cfg.witness_rpc_addrs.clone(),
+        }
+    }
+
+    /// Checks that justification of `block` targets it and that every witness
+    /// which finalized its height finalized the same block. A different block is
+    /// a fork if justifications proving both finalized verify against the authority
+    /// set of `block`. Witnesses which cannot be reached or are behind are skipped with
+    /// a warning, as are those whose justification does not verify, which a lying node
+    /// could not forge. A different block whose finality cannot be proven either way is
+    /// reported as misbehaviour, as is a fork.
+    pub async fn check(&self, block: &SignedBlockWithAuthoritySet) -> Result<(), RelayError> {
+        let header = &block.block.block.header;
+        let primary_hash = header.hash();
+        if let Some(encoded) = &block.block.justification {
+            let justification = GrandpaJustification::decode(&mut &encoded[..]).map_err(|e| {
+                RelayError::Misbehaviour(format!(
+                    "primary node attached justification which cannot be decoded to block at height: {}: {}",
+                    header.number, e
+                ))
+            })?;
+            if justification.commit.target_hash != primary_hash
+                || justification.commit.target_number != header.number
+            {
+                return Err(RelayError::Misbehaviour(format!(
+                    "primary node attached justification of a different block to block at height: {}",
+                    header.number
+                )));
+            }
+        }
+
+        for witness_rpc_addr in &self.witness_rpc_addrs {
+            match self
+                .check_witness(witness_rpc_addr, block, primary_hash)
+                .await
+            {
+                Ok(None) => {}
+                Ok(Some(evidence)) => return Err(RelayError::Misbehaviour(evidence.to_string())),
+                Err(e @ RelayError::Misbehaviour(_)) => return Err(e),
+                Err(e) => warn!(
+                    "Unable to cross-check block at height: {} with witness {}: {}",
+                    header.number, witness_rpc_addr, e
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_witness(
+        &self,
+        witness_rpc_addr: &str,
+        block: &SignedBlockWithAuthoritySet,
+        primary_hash: H256,
+    ) -> Result<Option<ForkEvidence>, RelayError> {
+        let number = block.block.block.header.number;
+        let finalized = get_finalized_block_number(witness_rpc_addr.to_owned())
+            .await
+            .map_err(RelayError::rpc)?;
+        let finalized =
+            u32::from_str_radix(finalized.trim_start_matches("0x"), 16).map_err(RelayError::rpc)?;
+        if finalized < number {
+            return Err(RelayError::Rpc(format!(
+                "witness has finalized only up to height: {}",
+                finalized
+            )));
+        }
+
+        let (witness_hash, witness_block) =
+            get_block_at_height(witness_rpc_addr.to_owned(), format!("0x{:x}", number))
+                .await
+                .map_err(RelayError::rpc)?;
+        let witness_hash_bytes =
+            hex::decode(witness_hash.trim_start_matches("0x")).map_err(RelayError::rpc)?;
+        if witness_hash_bytes.len() != H256::len_bytes() {
+            return Err(RelayError::Rpc(format!(
+                "invalid block hash: {}",
+                witness_hash
+            )));
+        }
+        let witness_hash = H256::from_slice(&witness_hash_bytes);
+        if witness_hash == primary_hash {
+            return Ok(None);
+        }
+
+        // Differing blocks prove a fork only if the authority set of `block` signed
+        // justifications proving both finalized.
+        let unverifiable = |e: RelayError| {
+            RelayError::Misbehaviour(format!(
+                "witness {} reports different block: {:?} at height: {}, which cannot be verified: {}, relaying halted",
+                witness_rpc_addr, witness_hash, number, e
+            ))
+        };
+        let primary_proof =
+            failover::call(&self.metrics, &self.chain, &self.rpc_addrs, |rpc_addr| {
+                prove_finality(rpc_addr, block.block.clone(), None)
+            })
+            .await
+            .map_err(|e| unverifiable(e.context("finality of primary block")))?;
+        let witness_proof =
+            prove_finality(witness_rpc_addr.to_owned(), witness_block, Some(finalized))
+                .await
+                .map_err(|e| unverifiable(e.context("finality of witness block")))?;
+        for (node, proof) in &[("primary", &primary_proof), ("witness", &witness_proof)] {
+            if proof.set_id != block.set_id || proof.authority_set != block.authority_set {
+                return Err(unverifiable(RelayError::Verification(format!(
+                    "{} block is finalized by authority set: {}, while primary reports set: {} at height: {}",
+                    node, proof.set_id, block.set_id, number
+                ))));
+            }
+        }
+        let primary_justification = verify_justification(
+            &primary_proof.target,
+            &primary_proof.justification,
+            &block.authority_set,
+            block.set_id,
+        )
+        .map_err(|e| RelayError::Verification(format!("primary block does not verify: {}", e)))?;
+        let witness_justification = verify_justification(
+            &witness_proof.target,
+            &witness_proof.justification,
+            &block.authority_set,
+            block.set_id,
+        )
+        .map_err(|e| {
+            RelayError::Verification(format!(
+                "witness block: {:?} does not verify: {}",
+                witness_hash, e
+            ))
+        })?;
+        Ok(Some(ForkEvidence {
+            height: number,
+            primary_hash,
+            witness_rpc_addr: witness_rpc_addr.to_owned(),
+            witness_hash,
+            equivocators: find_equivocators(&primary_justification, &witness_justification),
+        }))
+    }
+}
+
+/// Proves `block` of node at `rpc_addr` finalized with its own justification, or with that
+/// of the first block descending from it which carries one, up to height `finalized` if
+/// the node is known to have finalized only so far.
+async fn prove_finality(
+    rpc_addr: String,
+    block: SignedBlock,
+    finalized: Option<u32>,
+) -> Result<FinalityProof, RelayError> {
+    let number = block.block.header.number;
+    let mut target = block.block.header;
+    let mut justification = block.justification;
+    while justification.is_none() {
+        if target.number - number >= MAX_JUSTIFICATION_DISTANCE
+            || finalized.map_or(false, |finalized| target.number >= finalized)
+        {
+            return Err(RelayError::Rpc(format!(
+                "no finalized block up to height: {} carries justification",
+                target.number
+            )));
+        }
+        let (_, next) = get_block_at_height(rpc_addr.clone(), format!("0x{:x}", target.number + 1))
+            .await
+            .map_err(|e| classify_query_error("Unable to get block", e))?;
+        // Node could otherwise prove finality of a block of another branch.
+        if next.block.header.parent_hash != target.hash() {
+            return Err(RelayError::Rpc(format!(
+                "block at height: {} does not descend from block at height: {}",
+                target.number + 1,
+                target.number
+            )));
+        }
+        target = next.block.header;
+        justification = next.justification;
+    }
+    let (authority_set, set_id) =
+        get_authset_with_id(rpc_addr, format!("0x{}", hex::encode(target.hash())))
+            .await
+            .map_err(|e| classify_query_error("Unable to fetch authority set", e))?;
+    Ok(FinalityProof {
+        target,
+        justification: justification.expect("loop ends once justification is found"),
+        authority_set,
+        set_id,
+    })
+}
+
+/// Authorities whose precommits appear in justifications of two different blocks.
+fn find_equivocators(a: &GrandpaJustification, b: &GrandpaJustification) -> Vec<AuthorityId> {
+    if a.commit.target_hash == b.commit.target_hash {
+        return vec![];
+    }
+    let b_voters: Vec<&AuthorityId> = b.commit.precommits.iter().map(|p| &p.id).collect();
+    a.commit
+        .precommits
+        .iter()
+        .map(|p| &p.id)
+        .filter(|id| b_voters.contains(id))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_equivocators;
+    use crate::substrate::generator::{AuthorityChange, ChainGenerator, ChangeKind};
+    use crate::substrate::types::GrandpaJustification;
+    use parity_scale_codec::Decode;
+
+    fn justification(generator: &mut ChainGenerator) -> GrandpaJustification {
+        let block = generator.next_block().unwrap();
+        GrandpaJustification::decode(&mut &block.block.justification.unwrap()[..]).unwrap()
+    }
+
+    #[test]
+    fn test_equivocating_authorities_are_found() {
+        let mut honest = ChainGenerator::new(1, 4, 0, 1);
+        let mut same = ChainGenerator::new(1, 4, 0, 1);
+        // Announcing a change alters the block, while the same authorities sign it.
+        let mut forked = ChainGenerator::new(1, 4, 0, 1);
+        forked
+            .schedule_change(AuthorityChange::parse(ChangeKind::Scheduled, "1:5:4").unwrap())
+            .unwrap();
+
+        let honest = justification(&mut honest);
+        assert!(find_equivocators(&honest, &justification(&mut same)).is_empty());
+        assert_eq!(
+            find_equivocators(&honest, &justification(&mut forked)).len(),
+            4
+        );
+    }
+}
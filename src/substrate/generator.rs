@@ -0,0 +1,335 @@
+use crate::substrate::types::{
+    AuthorityList, Block, BlockNumber, Commit, GrandpaJustification, Header, Precommit,
+    SignedBlock, SignedPrecommit,
+};
+use parity_scale_codec::Encode;
+use serde_json::json;
+use sp_core::{ed25519, Pair, H256};
+use sp_finality_grandpa::{
+    AuthorityId, AuthoritySignature, ConsensusLog, ScheduledChange, GRANDPA_ENGINE_ID,
+};
+use sp_runtime::generic::{Digest, DigestItem};
+use sp_runtime::traits::{BlakeTwo256, Hash, Header as HeaderT};
+use std::collections::BTreeMap;
+
+/// Kind of authority set change announced in a header digest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChangeKind {
+    /// Change is enacted once the announcing block is finalized and `delay`
+    /// blocks have passed.
+    Scheduled,
+    /// Change is enacted after `delay` blocks regardless of finality.
+    Forced,
+}
+
+/// Authority set change requested while generating a chain.
+#[derive(Clone, Debug)]
+pub struct AuthorityChange {
+    pub kind: ChangeKind,
+    /// Height of the block which announces the change in its digest.
+    pub announced_at: BlockNumber,
+    /// Number of blocks after `announced_at` at which new set takes over.
+    pub delay: BlockNumber,
+    /// Size of the new authority set.
+    pub authorities: usize,
+}
+
+impl AuthorityChange {
+    /// Parses a change given as `<height>:<delay>:<authorities>`.
+    pub fn parse(kind: ChangeKind, value: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() != 3 {
+            return Err(format!(
+                "invalid authority change: {}, expected <height>:<delay>:<authorities>",
+                value
+            ));
+        }
+        let parse_part = |part: &str| {
+            part.parse::<u64>()
+                .map_err(|e| format!("invalid authority change: {}, error: {}", value, e))
+        };
+        let authorities = parse_part(parts[2])? as usize;
+        if authorities == 0 {
+            return Err(format!(
+                "invalid authority change: {}, new set cannot be empty",
+                value
+            ));
+        }
+        Ok(AuthorityChange {
+            kind,
+            announced_at: parse_part(parts[0])? as BlockNumber,
+            delay: parse_part(parts[1])? as BlockNumber,
+            authorities,
+        })
+    }
+}
+
+/// Generated block along with authority set which would be returned by
+/// `state_getStorage` at that block, i.e. what `chain_recv_handler` would
+/// read from a live node.
+pub struct GeneratedBlock {
+    pub block: SignedBlock,
+    pub authority_set: AuthorityList,
+    pub set_id: u64,
+}
+
+impl GeneratedBlock {
+    /// Serializes block into the format read by substrate `simulate_recv_handler`.
+    pub fn to_simulation_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&json!({
+            "block": &self.block,
+            "authority_set": &self.authority_set,
+            "set_id": self.set_id,
+        }))
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// Builds a synthetic substrate chain, finalized by a GRANDPA authority set
+/// whose keys are derived deterministically, so it can be fed to substrate
+/// light client without running a node.
+pub struct ChainGenerator {
+    next_number: BlockNumber,
+    parent_hash: H256,
+    authorities: Vec<ed25519::Pair>,
+    set_id: u64,
+    round: u64,
+    justification_period: BlockNumber,
+    /// Height of the latest block carrying a justification.
+    last_finalized: BlockNumber,
+    changes: BTreeMap<BlockNumber, AuthorityChange>,
+    pending: Option<(BlockNumber, Vec<ed25519::Pair>)>,
+    generated_keys: usize,
+}
+
+impl ChainGenerator {
+    pub fn new(
+        start_height: BlockNumber,
+        authorities: usize,
+        set_id: u64,
+        justification_period: BlockNumber,
+    ) -> Self {
+        let mut generator = ChainGenerator {
+            next_number: start_height,
+            parent_hash: H256::default(),
+            authorities: vec![],
+            set_id,
+            round: 1,
+            justification_period: justification_period.max(1),
+            // Chain is assumed to be finalized up to the block preceding the first one generated.
+            last_finalized: start_height.saturating_sub(1),
+            changes: BTreeMap::new(),
+            pending: None,
+            generated_keys: 0,
+        };
+        generator.authorities = generator.new_authorities(authorities);
+        generator
+    }
+
+    /// Registers an authority set change to be announced while generating.
+    pub fn schedule_change(&mut self, change: AuthorityChange) -> Result<(), String> {
+        if change.announced_at < self.next_number {
+            return Err(format!(
+                "authority change at height {} is below starting height {}",
+                change.announced_at, self.next_number
+            ));
+        }
+        if self.changes.contains_key(&change.announced_at) {
+            return Err(format!(
+                "more than one authority change announced at height {}",
+                change.announced_at
+            ));
+        }
+        self.changes.insert(change.announced_at, change);
+        Ok(())
+    }
+
+    /// Generates the next block of the chain.
+    pub fn next_block(&mut self) -> Result<GeneratedBlock, String> {
+        let number = self.next_number;
+        let mut digest = Digest::<H256> { logs: vec![] };
+
+        if let Some(change) = self.changes.remove(&number) {
+            if self.pending.is_some() {
+                return Err(format!(
+                    "authority change announced at height {} while another one is pending",
+                    number
+                ));
+            }
+            let next_authorities = self.new_authorities(change.authorities);
+            let scheduled = ScheduledChange {
+                next_authorities: Self::authority_list(&next_authorities),
+                delay: change.delay,
+            };
+            let log = match change.kind {
+                ChangeKind::Scheduled => ConsensusLog::ScheduledChange(scheduled),
+                // Forced change is enacted relative to the best finalized block
+                // known to the announcing block, not the announcing block itself.
+                ChangeKind::Forced => ConsensusLog::ForcedChange(self.last_finalized, scheduled),
+            };
+            digest
+                .logs
+                .push(DigestItem::Consensus(GRANDPA_ENGINE_ID, log.encode()));
+            self.pending = Some((number + change.delay, next_authorities));
+        }
+
+        let header = Header::new(
+            number,
+            BlakeTwo256::ordered_trie_root(vec![]),
+            BlakeTwo256::hash(&(b"state", number).encode()),
+            self.parent_hash,
+            digest,
+        );
+        let hash = header.hash();
+
+        // Block enacting the change is still finalized by the old set, but
+        // the runtime storage at that block already reports the new one.
+        let enacts_change = matches!(&self.pending, Some((at, _)) if *at == number);
+        let justification = if enacts_change || number % self.justification_period == 0 {
+            self.last_finalized = number;
+            Some(self.justify(hash, number).encode())
+        } else {
+            None
+        };
+        if enacts_change {
+            let (_, next_authorities) = self.pending.take().unwrap();
+            self.authorities = next_authorities;
+            self.set_id += 1;
+            self.round = 1;
+        }
+
+        self.parent_hash = hash;
+        self.next_number += 1;
+
+        Ok(GeneratedBlock {
+            block: SignedBlock {
+                block: Block {
+                    header,
+                    extrinsics: vec![],
+                },
+                justification,
+            },
+            authority_set: Self::authority_list(&self.authorities),
+            set_id: self.set_id,
+        })
+    }
+
+    /// Signs a commit for given block with every authority of the current set.
+    fn justify(&mut self, target_hash: H256, target_number: BlockNumber) -> GrandpaJustification {
+        let round = self.round;
+        self.round += 1;
+        let precommit = Precommit {
+            target_hash,
+            target_number,
+        };
+        let payload = precommit.signing_payload(round, self.set_id);
+        let precommits = self
+            .authorities
+            .iter()
+            .map(|pair| SignedPrecommit {
+                precommit: precommit.clone(),
+                signature: AuthoritySignature::from(pair.sign(&payload)),
+                id: AuthorityId::from(pair.public()),
+            })
+            .collect();
+
+        GrandpaJustification {
+            round,
+            commit: Commit {
+                target_hash,
+                target_number,
+                precommits,
+            },
+            votes_ancestries: vec![],
+        }
+    }
+
+    fn new_authorities(&mut self, count: usize) -> Vec<ed25519::Pair> {
+        let first = self.generated_keys;
+        self.generated_keys += count;
+        (first..self.generated_keys)
+            .map(|idx| {
+                ed25519::Pair::from_string(&format!("//QuantumTunnel//Authority{}", idx), None)
+                    .expect("static derivation path is always valid; qed")
+            })
+            .collect()
+    }
+
+    fn authority_list(authorities: &[ed25519::Pair]) -> AuthorityList {
+        authorities
+            .iter()
+            .map(|pair| (AuthorityId::from(pair.public()), 1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthorityChange, ChainGenerator, ChangeKind};
+    use crate::substrate::types::{BlockNumber, SignedBlockWithAuthoritySet};
+    use parity_scale_codec::Decode;
+    use sp_finality_grandpa::{ConsensusLog, GRANDPA_ENGINE_ID};
+    use sp_runtime::generic::DigestItem;
+    use sp_runtime::traits::Header as HeaderT;
+
+    #[test]
+    fn test_generated_chain_links_parents_and_bumps_set_id() {
+        let mut generator = ChainGenerator::new(10, 3, 5, 1);
+        generator
+            .schedule_change(AuthorityChange::parse(ChangeKind::Scheduled, "11:2:4").unwrap())
+            .unwrap();
+
+        let blocks: Vec<_> = (0..5).map(|_| generator.next_block().unwrap()).collect();
+        for pair in blocks.windows(2) {
+            assert_eq!(
+                pair[1].block.block.header.parent_hash,
+                pair[0].block.block.header.hash()
+            );
+        }
+        assert!(blocks.iter().all(|b| b.block.justification.is_some()));
+        assert_eq!(blocks[2].set_id, 5);
+        assert_eq!(blocks[2].authority_set.len(), 3);
+        assert_eq!(blocks[3].set_id, 6);
+        assert_eq!(blocks[3].authority_set.len(), 4);
+
+        let serialized = blocks[3].to_simulation_json().unwrap();
+        let parsed: SignedBlockWithAuthoritySet = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(parsed.set_id, 6);
+    }
+
+    #[test]
+    fn test_forced_change_refers_to_last_finalized_block() {
+        let mut generator = ChainGenerator::new(10, 3, 5, 5);
+        generator
+            .schedule_change(AuthorityChange::parse(ChangeKind::Forced, "12:1:2").unwrap())
+            .unwrap();
+
+        let blocks: Vec<_> = (0..4).map(|_| generator.next_block().unwrap()).collect();
+        let log = blocks[2]
+            .block
+            .block
+            .header
+            .digest
+            .logs
+            .iter()
+            .find_map(|item| match item {
+                DigestItem::Consensus(id, data) if *id == GRANDPA_ENGINE_ID => {
+                    ConsensusLog::<BlockNumber>::decode(&mut &data[..]).ok()
+                }
+                _ => None,
+            })
+            .unwrap();
+        match log {
+            ConsensusLog::ForcedChange(median, change) => {
+                assert_eq!(median, 10);
+                assert_eq!(change.delay, 1);
+                assert_eq!(change.next_authorities.len(), 2);
+            }
+            other => panic!("expected forced change, got: {:?}", other),
+        }
+        assert!(blocks[1].block.justification.is_none());
+        assert_eq!(blocks[2].set_id, 5);
+        assert_eq!(blocks[3].set_id, 6);
+        assert_eq!(blocks[3].authority_set.len(), 2);
+    }
+}
@@ -1,27 +1,48 @@
-use crate::config::{SubstrateChainConfig, SubstrateConfig};
-use crate::cosmos::types::{TMCreateClientPayload, TMHeader, TMUpdateClientPayload};
+use crate::client_id;
+use crate::client_state::{self, ClientState, StoredState, CLIENT_STATE_QUERY_INTERVAL};
+use crate::config::{SimulationTiming, SubstrateConfig, SubstrateSimulationConfig};
+use crate::cosmos::types::{TMClientInfo, TMCreateClientPayload, TMHeader, TMUpdateClientPayload};
+use crate::cosmos::verifier::TendermintVerifier;
+use crate::error::RelayError;
+use crate::failover;
+use crate::monitoring::{MonitoringEvent, SimulationMonitor};
+use crate::progress;
+use crate::report::HeaderRecord;
+use crate::retry::{Decision, RetryPolicy};
+use crate::substrate::chain::InProcessTendermintClient;
+use crate::substrate::connection::{Submission, SubstrateConnection};
+use crate::substrate::fork::ForkMonitor;
 use crate::substrate::types::{
-    AuthSetIdRpcResponse, AuthSetRpcResponse, BlockRpcResponse, HashRpcResponse, SignedBlock,
-    SignedBlockWithAuthoritySet,
+    AuthSetIdRpcResponse, AuthSetRpcResponse, BlockRpcResponse, HashRpcResponse,
+    MetadataRpcResponse, SignedBlock, SignedBlockWithAuthoritySet, StorageKeysRpcResponse,
+    StorageRpcResponse,
 };
-use crate::utils::{generate_client_id, to_string};
+use crate::utils::clock::VirtualClock;
+use crate::utils::to_string;
+use crate::watchdog::ExpiryWatchdog;
 use bytes::buf::Buf;
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use frame_metadata::{
+    DecodeDifferent, DecodeDifferentStr, RuntimeMetadata, RuntimeMetadataPrefixed,
+    RuntimeMetadataV12, StorageEntryType, StorageHasher, META_RESERVED,
+};
 use futures::{SinkExt, StreamExt};
 use hyper::{body::aggregate, Body, Client, Method, Request};
 use log::*;
 use parity_scale_codec::{Decode, Encode};
 use parse_duration::parse;
 use serde_json::{from_str, Value};
+use sp_core::hashing::{blake2_128, blake2_256, twox_128, twox_256, twox_64};
 use sp_core::sr25519::Pair as Sr25519Pair;
-use sp_core::Pair;
 use sp_finality_grandpa::AuthorityList;
 use std::error::Error;
 use std::marker::PhantomData;
 use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
 use substrate_subxt::balances::{Balances, BalancesEventsDecoder};
 use substrate_subxt::system::{System, SystemEventsDecoder};
-use substrate_subxt::{ClientBuilder, NodeTemplateRuntime, PairSigner};
+use substrate_subxt::{NodeTemplateRuntime, PairSigner};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
 #[module]
@@ -49,29 +70,28 @@ impl TendermintClientModule for NodeTemplateRuntime {
     type Height = u64;
 }
 
+pub(crate) type SubxtClient = substrate_subxt::Client<NodeTemplateRuntime>;
+pub(crate) type RelayerSigner = PairSigner<NodeTemplateRuntime, Sr25519Pair>;
+
+/// Tendermint light client module keeps client info in a map keyed by client id. Its
+/// source is not published, so prefix and hasher of the map are read from runtime
+/// metadata of the chain, see [`ClientInfoStorage`].
+const CLIENT_INFO_MODULE: &str = "TendermintClientModule";
+const CLIENT_INFO_STORAGE_ITEM: &str = "ClientInfoMap";
+
 pub struct SubstrateHandler {}
 
 impl SubstrateHandler {
-    /// Receive handler entrypoint
-    /// Branches to different internal methods depending upon whether
-    /// configuration is `Real` or `Simulation`
+    /// Receive handler of live substrate chain.
     pub async fn recv_handler(
-        cfg: SubstrateChainConfig,
+        cfg: SubstrateConfig,
         outchan: Sender<SignedBlockWithAuthoritySet>,
-        monitoring_inchan: Receiver<(bool, u64)>,
-    ) -> Result<(), String> {
-        match cfg {
-            SubstrateChainConfig::Real(cfg) => Self::chain_recv_handler(cfg, outchan).await,
-            SubstrateChainConfig::Simulation(cfg) => {
-                Self::simulate_recv_handler(
-                    cfg.simulation_file_path,
-                    cfg.should_run_till_height,
-                    outchan,
-                    monitoring_inchan,
-                )
-                .await
-            }
-        }
+    ) -> Result<(), RelayError> {
+        let (chain, health) = (cfg.name.clone(), cfg.health.clone());
+        health.starting(&chain, None, "recv");
+        let result = Self::chain_recv_handler(cfg, outchan).await;
+        health.stopped(&chain, None, "recv", &result);
+        result
     }
 
     /// Simulation receive handler, which as the name suggests
@@ -81,175 +101,344 @@ impl SubstrateHandler {
     pub async fn simulate_recv_handler(
         test_file: String,
         should_run_till_height: u64,
+        expected_rejection: Option<String>,
+        timing: SimulationTiming,
         outchan: Sender<SignedBlockWithAuthoritySet>,
-        monitoring_inchan: Receiver<(bool, u64)>,
-    ) -> Result<(), String> {
+        monitoring_inchan: Receiver<MonitoringEvent>,
+    ) -> Result<(), RelayError> {
+        let clock = VirtualClock::from_config(&timing).map_err(RelayError::Config)?;
         let simulation_data =
-            std::fs::read_to_string(Path::new(test_file.as_str())).map_err(to_string)?;
+            std::fs::read_to_string(Path::new(test_file.as_str())).map_err(RelayError::config)?;
         let stringified_headers: Vec<&str> = simulation_data.split("\n\n").collect();
         let number_of_simulated_headers = stringified_headers.len();
         for str in stringified_headers {
-            let payload: SignedBlockWithAuthoritySet = from_str(str).map_err(to_string)?;
-            outchan.try_send(payload).map_err(to_string)?;
+            let payload: SignedBlockWithAuthoritySet = from_str(str).map_err(RelayError::config)?;
+            outchan
+                .try_send(payload)
+                .map_err(RelayError::disconnected)?;
         }
 
-        let mut number_of_headers_ingested_till = 0;
-        let mut successfully_ingested_till = 0;
+        let mut monitor = SimulationMonitor::new(
+            "substrate",
+            number_of_simulated_headers,
+            should_run_till_height,
+            expected_rejection,
+        );
         // Let's wait for the receive handler on other side to catch up
         loop {
-            let result = monitoring_inchan.try_recv();
-            if result.is_err() {
-                match result.err().unwrap() {
-                    TryRecvError::Empty => {
-                        // Let's wait for data to appear
-                        tokio::time::delay_for(core::time::Duration::new(1, 0)).await;
-                    }
-                    TryRecvError::Disconnected => {
-                        return Err(
-                            "monitoring channel of cosmos send handler is disconnected".to_string()
-                        );
-                    }
+            let event = match monitoring_inchan.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Empty) => {
+                    // Let's wait for data to appear
+                    clock.tick().await;
+                    continue;
                 }
-                continue;
-            }
-
-            let (terminated, reported_height) = result.unwrap();
-            if !terminated {
-                successfully_ingested_till = reported_height;
-                number_of_headers_ingested_till += 1;
-            }
-
-            if terminated || (number_of_headers_ingested_till == number_of_simulated_headers) {
-                if successfully_ingested_till != should_run_till_height {
-                    return Err(format!("Ingesting simulation data failed on substrate chain. Expected to ingest headers till height: {}, ingested till: {}", should_run_till_height, successfully_ingested_till));
-                } else {
-                    info!("Substrate headers simulated successfully. Ingested headers till height: {}", successfully_ingested_till);
+                Err(TryRecvError::Disconnected) => {
+                    return Err(RelayError::disconnected(
+                        "monitoring channel of cosmos send handler is disconnected",
+                    ));
                 }
-                break;
-            } else {
+            };
+
+            if let Some(outcome) = monitor.observe(event) {
+                let successfully_ingested_till = outcome.map_err(RelayError::Verification)?;
                 info!(
-                    "substrate light client has successfully ingested header at: {}",
+                    "Substrate headers simulated successfully. Ingested headers till height: {}",
                     successfully_ingested_till
                 );
+                return Ok(());
             }
         }
-        Ok(())
     }
 
     /// Chain receive handler connects to live chain.
     /// It subscribes to finalized headers from Websocket, retrieves block and authority set for
     /// each header and pass it to substrate light client.
+    /// When subscription ends, it is resumed on the next configured node.
     pub async fn chain_recv_handler(
         cfg: SubstrateConfig,
         outchan: Sender<SignedBlockWithAuthoritySet>,
-    ) -> Result<(), String> {
-        let (mut socket, _) = connect_async(&cfg.ws_addr).await.map_err(to_string)?;
-        info!("connected websocket to {:?}", &cfg.ws_addr);
-        let subscribe_message = Message::Text(r#"{"jsonrpc":"2.0", "method":"chain_subscribeFinalizedHeads", "params":[], "id": "0"}"#.to_string());
-        socket.send(subscribe_message).await.map_err(to_string)?;
+    ) -> Result<(), RelayError> {
+        let monitor = if cfg.witness_rpc_addrs.is_empty() {
+            None
+        } else {
+            Some(ForkMonitor::new(&cfg))
+        };
+        cfg.health.running(&cfg.name, None, "recv");
 
         async fn process_msg(
             cfg: &SubstrateConfig,
             msg: Message,
-        ) -> Result<SignedBlockWithAuthoritySet, String> {
-            let msgtext = msg.to_text().map_err(to_string)?;
-            let json = from_str::<Value>(msgtext).map_err(to_string)?;
+        ) -> Result<SignedBlockWithAuthoritySet, RelayError> {
+            let msgtext = msg.to_text().map_err(RelayError::rpc)?;
+            let json = from_str::<Value>(msgtext).map_err(RelayError::rpc)?;
             let blocknum = json["params"]["result"]["number"]
                 .as_str()
                 .map(|str| str.to_string())
-                .ok_or_else(|| format!("ignoring json since it did not include the block number. Received json:{:?}", json))?;
-
-            let (blockhash, block) = get_block_at_height(cfg.rpc_addr.clone(), blocknum.clone())
-                .await
-                .map_err(|e| {
-                    format!("Unable to get block at height: {}, error: {}", blocknum, e)
-                })?;
+                .ok_or_else(|| RelayError::Rpc(format!("ignoring json since it did not include the block number. Received json:{:?}", json)))?;
 
-            let (authority_set, set_id) =
-                get_authset_with_id(cfg.rpc_addr.clone(), blockhash.clone())
-                    .await
-                    .map_err(|e| {
-                        format!(
-                            "Unable to fetch authority set at height: {}, error: {}",
-                            blocknum, e
-                        )
-                    })?;
-
-            Ok(SignedBlockWithAuthoritySet::from_parts(
-                block,
-                authority_set,
-                set_id,
-            ))
+            get_block_with_authset(cfg, blocknum).await
         }
 
-        while let Some(msg) = socket.next().await {
-            if let Ok(msg) = msg {
-                info!("Received message from substrate chain: {:?}", msg);
-                match process_msg(&cfg, msg.clone()).await {
-                    Ok(signed_block_with_authset) => outchan
-                        .try_send(signed_block_with_authset)
-                        .map_err(to_string)?,
-                    Err(err) => error!("Error: {}", err),
+        async fn relay_block(
+            cfg: &SubstrateConfig,
+            signed_block_with_authset: SignedBlockWithAuthoritySet,
+            monitor: &Option<ForkMonitor>,
+            outchan: &Sender<SignedBlockWithAuthoritySet>,
+        ) -> Result<(), RelayError> {
+            cfg.metrics.observe_header(
+                &cfg.name,
+                signed_block_with_authset.block.block.header.number as u64,
+            );
+            cfg.health.activity(&cfg.name, None, "recv");
+            // Wormhole module cannot be handed evidence of equivocation, so
+            // relaying stops before a block of a possible fork reaches it.
+            if let Some(monitor) = monitor {
+                if let Err(e) = monitor.check(&signed_block_with_authset).await {
+                    error!("ALERT: {}, relaying halted", e);
+                    return Err(e);
                 }
             }
+            outchan
+                .try_send(signed_block_with_authset)
+                .map_err(RelayError::disconnected)
         }
 
-        Ok(())
+        let mut last_relayed: Option<u32> = None;
+        let mut backoff = failover::Backoff::new();
+        loop {
+            let (ws_addr, mut socket) =
+                failover::call_until_success(&cfg.metrics, &cfg.name, &cfg.ws_addrs(), |ws_addr| async move {
+                    let (mut socket, _) = connect_async(&ws_addr).await.map_err(RelayError::transport)?;
+                    info!("connected websocket to {:?}", &ws_addr);
+                    let subscribe_message = Message::Text(r#"{"jsonrpc":"2.0", "method":"chain_subscribeFinalizedHeads", "params":[], "id": "0"}"#.to_string());
+                    socket.send(subscribe_message).await.map_err(RelayError::transport)?;
+                    Ok::<_, RelayError>((ws_addr, socket))
+                })
+                .await?;
+
+            let ended = loop {
+                let msg = match tokio::time::timeout(failover::STALL_TIMEOUT, socket.next()).await {
+                    Ok(Some(msg)) => msg,
+                    Ok(None) => break "subscription to finalized heads ended".to_owned(),
+                    Err(_) => {
+                        break format!(
+                            "no finalized head received for {:?}",
+                            failover::STALL_TIMEOUT
+                        )
+                    }
+                };
+                if let Ok(msg) = msg {
+                    info!("Received message from substrate chain: {:?}", msg);
+                    match process_msg(&cfg, msg.clone()).await {
+                        Ok(signed_block_with_authset) => {
+                            backoff.reset();
+                            let number = signed_block_with_authset.block.block.header.number;
+                            // Substrate light client accepts only contiguous headers, so blocks
+                            // finalized while subscription was being resumed, or along with
+                            // another one, are fetched one by one.
+                            let mut unfetched = None;
+                            for missing in last_relayed.map_or(number, |last| last + 1)..number {
+                                match get_block_with_authset(&cfg, format!("0x{:x}", missing)).await
+                                {
+                                    Ok(missing_block) => {
+                                        relay_block(&cfg, missing_block, &monitor, &outchan)
+                                            .await?;
+                                        last_relayed = Some(missing);
+                                    }
+                                    Err(e) => {
+                                        unfetched = Some(e);
+                                        break;
+                                    }
+                                }
+                            }
+                            // Blocks left are fetched once subscription is resumed.
+                            if let Some(e) = unfetched {
+                                break format!(
+                                    "unable to fetch finalized block at height: {}: {}",
+                                    last_relayed.map_or(number, |last| last + 1),
+                                    e
+                                );
+                            }
+                            if last_relayed.map_or(false, |last| number <= last) {
+                                continue;
+                            }
+                            relay_block(&cfg, signed_block_with_authset, &monitor, &outchan)
+                                .await?;
+                            last_relayed = Some(number);
+                        }
+                        Err(err) => error!("Error: {}", err),
+                    }
+                }
+            };
+
+            failover::failed(&cfg.metrics, &cfg.name, &ws_addr, &ended);
+            // Node which drops every subscription right away is not hammered with new ones.
+            tokio::time::delay_for(backoff.delay()).await;
+        }
     }
 
-    /// Send handler entrypoint
-    /// Branches to different internal methods depending upon whether
-    /// configuration is `Real` or `Simulation`
+    /// Send handler of live substrate chain.
     /// If other side is simulation, some additional bookkeeping is done to
     /// make sure `simulation_recv_handler` gets accurate data.
+    /// Live chain is submitted to through `connection` shared with other paths, or
+    /// through a connection of its own if none is given.
     pub async fn send_handler(
-        cfg: SubstrateChainConfig,
+        cfg: SubstrateConfig,
+        connection: Option<Rc<SubstrateConnection>>,
         client_id: Option<String>,
         inchan: Receiver<(TMHeader, Vec<tendermint::validator::Info>)>,
-        monitoring_outchan: Sender<(bool, u64)>,
-    ) -> Result<(), String> {
-        match cfg {
-            SubstrateChainConfig::Real(cfg) => {
-                if cfg.is_other_side_simulation {
-                    // Swallow up the error to prevent quantum tunnel to terminate. This will give simulation data reader the chance to print the result.
-                    let result = Self::chain_send_handler(
-                        cfg,
-                        client_id,
-                        inchan,
-                        monitoring_outchan.clone(),
-                    )
-                    .await;
-                    // Send signal to simulation_recv_handler that receive handler is terminated
-                    monitoring_outchan.try_send((true, 0)).map_err(to_string)?;
-                    if result.is_err() {
-                        error!("Error occurred while trying to send simulated cosmos data to substrate chain: {}", result.err().unwrap());
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> Result<(), RelayError> {
+        let (chain, path, health) = (cfg.name.clone(), cfg.path.clone(), cfg.health.clone());
+        health.starting(&chain, path.as_deref(), "send");
+        let is_other_side_simulation = cfg.is_other_side_simulation;
+        let result = match connection {
+            Some(connection) => Ok(connection),
+            None => SubstrateConnection::new(&cfg).map(Rc::new),
+        };
+        let result = match result {
+            Ok(connection) => {
+                Self::chain_send_handler(
+                    cfg,
+                    connection,
+                    client_id,
+                    inchan,
+                    monitoring_outchan.clone(),
+                    report_outchan,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        health.stopped(&chain, path.as_deref(), "send", &result);
+        if is_other_side_simulation {
+            // Swallow up the error to prevent quantum tunnel to terminate. This will give simulation data reader the chance to print the result.
+            // Send signal to simulation_recv_handler that receive handler is terminated
+            monitoring_outchan
+                .try_send(MonitoringEvent::Terminated)
+                .map_err(RelayError::disconnected)?;
+            if result.is_err() {
+                error!("Error occurred while trying to send simulated cosmos data to substrate chain: {}", result.err().unwrap());
+            }
+            // This gives simulation_recv_handler time to print result and then exit.
+            futures::future::pending::<()>().await;
+            Ok(())
+        } else {
+            result
+        }
+    }
+
+    /// Send handler of simulated substrate chain, which cannot ingest any headers of
+    /// live cosmos chain, so it just drains them.
+    pub async fn drain_handler(
+        cfg: SubstrateSimulationConfig,
+        inchan: Receiver<(TMHeader, Vec<tendermint::validator::Info>)>,
+    ) -> Result<(), RelayError> {
+        let clock = VirtualClock::from_config(&cfg.timing).map_err(RelayError::Config)?;
+        loop {
+            let result = inchan.try_recv();
+            if result.is_err() {
+                match result.err().unwrap() {
+                    TryRecvError::Disconnected => {
+                        return Err(RelayError::disconnected(
+                            "cosmos chain-data channel's input end is disconnected.",
+                        ));
                     }
-                    // This gives simulation_recv_handler time to print result and then exit.
-                    futures::future::pending::<()>().await;
-                    Ok(())
-                } else {
-                    Self::chain_send_handler(cfg, client_id, inchan, monitoring_outchan).await
+                    _ => {}
                 }
             }
-            // If we are running simulation, we cannot ingest any headers.
-            SubstrateChainConfig::Simulation(_cfg) => {
-                loop {
-                    let result = inchan.try_recv();
-                    if result.is_err() {
-                        match result.err().unwrap() {
-                            TryRecvError::Disconnected => {
-                                return Err(
-                                    "cosmos chain-data channel's input end is disconnected."
-                                        .to_string(),
-                                );
-                            }
-                            _ => {}
-                        }
-                    }
-                    // Compulsory delay of 1 second to not enter in busy loop.
-                    tokio::time::delay_for(core::time::Duration::new(1, 0)).await;
+            // Compulsory delay to not enter in busy loop.
+            clock.tick().await;
+        }
+    }
+
+    /// Verifies headers of simulated cosmos chain with in-process tendermint light client
+    /// until it has no more headers to send, then signals `simulate_recv_handler` of the
+    /// cosmos chain to decide the outcome. Never returns afterwards, so the outcome is
+    /// reported by `simulate_recv_handler` even if verification failed.
+    pub async fn in_process_send_handler(
+        client: InProcessTendermintClient,
+        inchan: Receiver<(TMHeader, Vec<tendermint::validator::Info>)>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> Result<(), RelayError> {
+        let result =
+            Self::simulate_send_handler(client, inchan, monitoring_outchan.clone(), report_outchan)
+                .await;
+        // Send signal to simulation_recv_handler that receive handler is terminated
+        monitoring_outchan
+            .try_send(MonitoringEvent::Terminated)
+            .map_err(RelayError::disconnected)?;
+        if result.is_err() {
+            error!("Error occurred while trying to send simulated cosmos data to in-process cosmos light client: {}", result.err().unwrap());
+        }
+        // This gives simulation_recv_handler time to print result and then exit.
+        futures::future::pending::<()>().await;
+        Ok(())
+    }
+
+    /// Feeds header data received from opposite chain to tendermint light client
+    /// verification logic running inside quantum tunnel, in place of `client.chain`.
+    /// Returns once opposite chain has no more headers to send.
+    pub async fn simulate_send_handler(
+        client: InProcessTendermintClient,
+        inchan: Receiver<(TMHeader, Vec<tendermint::validator::Info>)>,
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> Result<(), RelayError> {
+        let trusting_period = parse(client.trusting_period.as_str()).map_err(RelayError::config)?;
+        let max_clock_drift = parse(client.max_clock_drift.as_str()).map_err(RelayError::config)?;
+        let clock = VirtualClock::from_config(&client.timing).map_err(RelayError::Config)?;
+        let mut verifier: Option<TendermintVerifier> = None;
+        loop {
+            let msg = match inchan.try_recv() {
+                Ok(msg) => msg,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+                Err(TryRecvError::Empty) => {
+                    clock.tick().await;
+                    continue;
                 }
+            };
+
+            let current_height = msg.0.signed_header.header.height.value();
+            let now = clock.now_for_header(msg.0.signed_header.header.time.into());
+            let started = Instant::now();
+            let result = match verifier.as_mut() {
+                None => TendermintVerifier::new(msg.0, msg.1, trusting_period, max_clock_drift)
+                    .map(|created| {
+                        verifier = Some(created);
+                        info!(
+                            "Created in-process cosmos light client at height: {}",
+                            current_height
+                        );
+                        None
+                    }),
+                Some(verifier) => verifier.ingest(msg.0, msg.1, now).map(|_| None),
             }
+            .map_err(|e| {
+                RelayError::Verification(format!(
+                    "in-process cosmos light client failed to ingest header at height: {}, error: {}",
+                    current_height, e
+                ))
+            });
+            report_outchan
+                .try_send(HeaderRecord::new(
+                    &client.chain,
+                    current_height,
+                    &result.clone().map_err(to_string),
+                    started.elapsed(),
+                ))
+                .map_err(RelayError::disconnected)?;
+            monitoring_outchan
+                .try_send(MonitoringEvent::from_result(current_height, &result))
+                .map_err(RelayError::disconnected)?;
+            result?;
+            info!(
+                "In-process cosmos light client verified header at height: {}",
+                current_height
+            );
         }
     }
 
@@ -257,105 +446,738 @@ impl SubstrateHandler {
     /// light client payload and sends it to tendermint light client running in
     /// substrate chain.
     /// If client id is not passed, first payload sent would be for creating the client.
+    /// Failed submissions are retried as `cfg.retry` allows, after which the header is
+    /// skipped, as tendermint light client verifies the next one by skipping.
     pub async fn chain_send_handler(
         cfg: SubstrateConfig,
+        connection: Rc<SubstrateConnection>,
         client_id: Option<String>,
         inchan: Receiver<(TMHeader, Vec<tendermint::validator::Info>)>,
-        monitoring_outchan: Sender<(bool, u64)>,
-    ) -> Result<(), String> {
+        monitoring_outchan: Sender<MonitoringEvent>,
+        report_outchan: Sender<HeaderRecord>,
+    ) -> Result<(), RelayError> {
+        let retry = RetryPolicy::new(&cfg.retry, true)?;
         let mut new_client = false;
         let id = if client_id.is_none() {
             new_client = true;
-            generate_client_id()
+            retry
+                .call("list cosmos light clients", || Self::new_client_id(&cfg))
+                .await?
         } else {
             client_id.unwrap()
         };
         let trusting_period = parse(cfg.trusting_period.as_str())
-            .map_err(to_string)?
+            .map_err(RelayError::config)?
             .as_secs();
-        let max_clock_drift = parse(cfg.max_clock_drift.as_str())
-            .map_err(to_string)?
-            .as_secs();
-        let unbonding_period = parse(cfg.unbonding_period.as_str())
-            .map_err(to_string)?
-            .as_secs();
-        let client_id = id.clone().parse().map_err(to_string)?;
-        let (pair, _) = Sr25519Pair::from_phrase(cfg.signer_seed.as_str(), None)
-            .map_err(|e| format!("{:?}", e))?;
-        let signer = PairSigner::new(pair);
-        let client = ClientBuilder::<NodeTemplateRuntime>::new()
-            .set_url(cfg.ws_addr)
-            .build()
-            .await
-            .map_err(to_string)?;
+        connection.connect_until_success().await?;
+        let mut watchdog = ExpiryWatchdog::new(
+            &cfg.health,
+            &cfg.metrics,
+            &cfg.name,
+            cfg.path.as_deref(),
+            Duration::from_secs(trusting_period),
+            cfg.watchdog.clone(),
+        );
+        cfg.health.running(&cfg.name, cfg.path.as_deref(), "send");
+
+        // Headers already ingested by client, e.g. before relayer restarted, are skipped.
+        let mut client_state = None;
+        let mut client_state_queried_at = Instant::now();
+        if !new_client {
+            client_state = Self::refresh_client_state(&cfg, &id, trusting_period).await?;
+            if let Some(state) = &client_state {
+                watchdog.seed(state);
+                info!(
+                    "Cosmos light client {} is at height: {}, relaying headers from height: {}",
+                    id,
+                    state.latest_height,
+                    state.latest_height + 1
+                );
+            }
+        }
+        // Client whose state cannot be queried is resumed after the header it was last
+        // sent, as recorded in progress file.
+        let recorded = match &client_state {
+            None if !new_client => progress::last_relayed(&cfg.name, &id),
+            _ => None,
+        };
+        if let Some(height) = recorded {
+            info!(
+                "Cosmos light client {} was last sent header at height: {} according to {}, relaying headers from height: {}",
+                id,
+                height,
+                progress::PROGRESS_FILE,
+                height + 1
+            );
+        }
+        // Header whose submission failed, along with the number of the next attempt.
+        let mut retrying: Option<((TMHeader, Vec<tendermint::validator::Info>), u32)> = None;
         loop {
-            let result = inchan.try_recv();
-            let msg = if result.is_err() {
-                match result.err().unwrap() {
-                    TryRecvError::Disconnected => {
-                        return Err(
-                            "cosmos chain-data channel's input end is disconnected.".to_string()
-                        );
+            watchdog.check();
+            // Submission in flight has been confirmed by now, queued headers are left.
+            if cfg.shutdown.requested() {
+                info!(
+                    "Shutting down, cosmos light client {} is no longer updated",
+                    id
+                );
+                return Ok(());
+            }
+            let (mut msg, attempt) = match retrying.take() {
+                Some(retried) => retried,
+                None => match inchan.try_recv() {
+                    Ok(msg) => (msg, 1),
+                    Err(TryRecvError::Disconnected) => {
+                        return Err(RelayError::disconnected(
+                            "cosmos chain-data channel's input end is disconnected.",
+                        ));
                     }
-                    _ => {
+                    Err(TryRecvError::Empty) => {
                         warn!("Did not receive any data from Cosmos chain-data channel. Retrying in a second ...");
                         tokio::time::delay_for(core::time::Duration::new(1, 0)).await;
                         continue;
                     }
-                }
-            } else {
-                result.unwrap()
+                },
             };
+            // Relaying a backlog header by header may take longer than the client has
+            // left, while tendermint light client can verify the latest header directly.
+            if !new_client && watchdog.refresh_due() {
+                let mut skipped = 0;
+                while let Ok(latest) = inchan.try_recv() {
+                    msg = latest;
+                    skipped += 1;
+                }
+                if skipped > 0 {
+                    warn!(
+                        "Cosmos light client refresh is due, forcing relay of latest header at height: {}, skipped {} queued headers",
+                        msg.0.signed_header.header.height, skipped
+                    );
+                }
+            }
             let current_height = msg.0.signed_header.header.height.value();
-            if new_client {
-                new_client = false;
-                let create_client_payload = TMCreateClientPayload {
-                    header: msg.0,
-                    trusting_period,
-                    max_clock_drift,
-                    unbonding_period,
-                    client_id,
-                };
-                client
-                    .init_client_and_watch(
-                        &signer,
-                        serde_json::to_vec(&create_client_payload).map_err(to_string)?,
-                    )
-                    .await
-                    .map_err(to_string)?;
-                info!("Created Cosmos light client");
-            } else {
-                let update_client_payload = TMUpdateClientPayload {
-                    header: msg.0,
-                    client_id: id.clone().parse().map_err(to_string)?,
-                    next_validator_set: msg.1,
-                };
+            // Failed attempt may have made it into a block nevertheless, so client state
+            // is queried again before the header is submitted once more.
+            if !new_client
+                && (attempt > 1 || client_state_queried_at.elapsed() >= CLIENT_STATE_QUERY_INTERVAL)
+            {
+                client_state = Self::refresh_client_state(&cfg, &id, trusting_period)
+                    .await?
+                    .or(client_state);
+                client_state_queried_at = Instant::now();
+            }
+            let ingested = match &client_state {
+                Some(state) => state.knows(current_height),
+                None => recorded.map_or(false, |height| current_height <= height),
+            };
+            if ingested {
                 info!(
-                    "{}",
-                    format!(
-                        "Updating Cosmos light client with block at height: {}",
-                        update_client_payload.header.signed_header.header.height
-                    )
+                    "Skipping header at height: {}, cosmos light client {} has ingested it already",
+                    current_height, id
                 );
-                client
-                    .update_client_and_watch(
-                        &signer,
-                        serde_json::to_vec(&update_client_payload).map_err(to_string)?,
-                    )
-                    .await
-                    .map_err(to_string)?;
-                info!("Updated Cosmos light client");
+                continue;
             }
+            let started = Instant::now();
+            if !new_client {
+                info!(
+                    "Updating Cosmos light client with block at height: {}",
+                    current_height
+                );
+            }
+            let result = match connection.lock().await {
+                Ok(mut submission) => {
+                    let result =
+                        Self::submit_header(&cfg, &submission, &id, new_client, msg.clone()).await;
+                    if let Err(e) = &result {
+                        submission.reconnect(e).await;
+                    }
+                    submission.observe_balance().await;
+                    result
+                }
+                Err(e) => Err(e),
+            };
+            // Extrinsic included by the chain is not sent again, client tells whether it
+            // was applied.
+            let result = match result {
+                Err(RelayError::Unconfirmed(e)) => {
+                    Self::confirm_submission(&cfg, &retry, &id, new_client, current_height, &e)
+                        .await
+                }
+                result => result,
+            };
+            cfg.metrics.observe_submission(
+                &cfg.counterparty_name,
+                &cfg.name,
+                cfg.path.as_deref(),
+                current_height,
+                result.is_ok(),
+            );
+            let decision = result.as_ref().err().map(|e| retry.decide(e, attempt));
+            if let Some(Decision::Retry(backoff)) = decision {
+                warn!(
+                    "Attempt {} to relay header at height: {} to cosmos light client {} failed: {}, retrying in {:?}",
+                    attempt,
+                    current_height,
+                    id,
+                    result.unwrap_err(),
+                    backoff
+                );
+                tokio::time::delay_for(backoff).await;
+                retrying = Some((msg, attempt + 1));
+                continue;
+            }
+            if new_client {
+                if let Ok(hash) = &result {
+                    new_client = false;
+                    Self::record_client(&cfg, &id, hash);
+                }
+            }
+            if let Ok(hash) = &result {
+                watchdog.updated();
+                cfg.health.activity(&cfg.name, cfg.path.as_deref(), "send");
+                if let Err(e) = progress::relayed(&cfg.name, &id, current_height, hash) {
+                    warn!(
+                        "Unable to write relaying progress to {}: {}",
+                        progress::PROGRESS_FILE,
+                        e
+                    );
+                }
+            }
+
+            let record = HeaderRecord::new(
+                &cfg.name,
+                current_height,
+                &result.clone().map(Some).map_err(to_string),
+                started.elapsed(),
+            );
             if cfg.is_other_side_simulation {
+                let result = result.clone().map(Some);
+                report_outchan
+                    .try_send(record)
+                    .map_err(RelayError::disconnected)?;
                 monitoring_outchan
-                    .try_send((false, current_height))
-                    .map_err(to_string)?;
+                    .try_send(MonitoringEvent::from_result(current_height, &result))
+                    .map_err(RelayError::disconnected)?;
+            } else {
+                // Nobody may be listening to relay events of live chains.
+                let _ = report_outchan.try_send(record);
+            }
+            if let Err(e) = result {
+                if decision != Some(Decision::Skip) {
+                    return Err(e);
+                }
+                warn!(
+                    "Skipping header at height: {}, cosmos light client {} could not ingest it: {}",
+                    current_height, id, e
+                );
+            }
+        }
+    }
+
+    /// Creates tendermint light client with header of `msg` if `create` is set, updates it otherwise.
+    async fn submit_header(
+        cfg: &SubstrateConfig,
+        submission: &Submission<'_>,
+        client_id: &str,
+        create: bool,
+        msg: (TMHeader, Vec<tendermint::validator::Info>),
+    ) -> Result<String, RelayError> {
+        if create {
+            Self::submit_create_client(cfg, submission, client_id, msg.0).await
+        } else {
+            Self::submit_update_client(submission, client_id, msg.0, msg.1).await
+        }
+    }
+
+    /// Creates tendermint light client trusting `header` with extrinsic signed by relayer
+    /// account of `submission`, returns hash of the extrinsic.
+    async fn submit_create_client(
+        cfg: &SubstrateConfig,
+        submission: &Submission<'_>,
+        client_id: &str,
+        header: TMHeader,
+    ) -> Result<String, RelayError> {
+        let create_client_payload = TMCreateClientPayload {
+            header,
+            trusting_period: parse(cfg.trusting_period.as_str())
+                .map_err(RelayError::config)?
+                .as_secs(),
+            max_clock_drift: parse(cfg.max_clock_drift.as_str())
+                .map_err(RelayError::config)?
+                .as_secs(),
+            unbonding_period: parse(cfg.unbonding_period.as_str())
+                .map_err(RelayError::config)?
+                .as_secs(),
+            client_id: client_id.parse().map_err(RelayError::config)?,
+        };
+        let payload = serde_json::to_vec(&create_client_payload).map_err(RelayError::encoding)?;
+        let nonce = submission.nonce().await?;
+        let success = match submission
+            .client()
+            .init_client_and_watch(submission.signer(), payload)
+            .await
+        {
+            Ok(success) => success,
+            Err(e) => return Err(submission.unconfirmed(nonce, classify_error(e)).await),
+        };
+        info!("Created Cosmos light client");
+        Ok(format!("{:?}", success.extrinsic))
+    }
+
+    /// Updates tendermint light client with `header` with extrinsic signed by relayer
+    /// account of `submission`, returns hash of the extrinsic.
+    async fn submit_update_client(
+        submission: &Submission<'_>,
+        client_id: &str,
+        header: TMHeader,
+        next_validator_set: Vec<tendermint::validator::Info>,
+    ) -> Result<String, RelayError> {
+        let update_client_payload = TMUpdateClientPayload {
+            header,
+            client_id: client_id.parse().map_err(RelayError::config)?,
+            next_validator_set,
+        };
+        let payload = serde_json::to_vec(&update_client_payload).map_err(RelayError::encoding)?;
+        let nonce = submission.nonce().await?;
+        let success = match submission
+            .client()
+            .update_client_and_watch(submission.signer(), payload)
+            .await
+        {
+            Ok(success) => success,
+            Err(e) => return Err(submission.unconfirmed(nonce, classify_error(e)).await),
+        };
+        info!("Updated Cosmos light client");
+        Ok(format!("{:?}", success.extrinsic))
+    }
+
+    /// Tells whether header at `height`, whose extrinsic was included by the chain with
+    /// result unknown as `err` tells, reached client `client_id`, which the extrinsic was
+    /// to create if `create` is set. Result stays unknown if the client cannot be queried.
+    async fn confirm_submission(
+        cfg: &SubstrateConfig,
+        retry: &RetryPolicy,
+        client_id: &str,
+        create: bool,
+        height: u64,
+        err: &str,
+    ) -> Result<String, RelayError> {
+        let addrs = cfg.rpc_addrs();
+        let ingested = if create {
+            retry
+                .call("list cosmos light clients", || {
+                    failover::call(&cfg.metrics, &cfg.name, &addrs, Self::query_client_ids)
+                })
+                .await
+                .map(|ids| ids.iter().any(|id| id == client_id))
+        } else {
+            retry
+                .call("query state of cosmos light client", || {
+                    failover::call(&cfg.metrics, &cfg.name, &addrs, |rpc_addr| {
+                        Self::query_client_state(rpc_addr, client_id.to_owned())
+                    })
+                })
+                .await
+                .map(|state| state.knows(height))
+        };
+        match ingested {
+            Ok(ingested) => client_state::confirm(client_id, height, ingested, err),
+            Err(e) => Err(RelayError::Unconfirmed(format!(
+                "{}, unable to query cosmos light client {}: {}",
+                err, client_id, e
+            ))),
+        }
+    }
+
+    /// Chooses identifier of a new tendermint light client, making sure it is not taken.
+    pub async fn new_client_id(cfg: &SubstrateConfig) -> Result<String, RelayError> {
+        // Id which looks free only because clients could not be listed may be taken.
+        let existing = failover::call(
+            &cfg.metrics,
+            &cfg.name,
+            &cfg.rpc_addrs(),
+            Self::query_client_ids,
+        )
+        .await?;
+        client_id::choose(
+            cfg.client_id.clone(),
+            &cfg.client_id_scheme,
+            &cfg.counterparty_chain_id,
+            &existing,
+        )
+        .map_err(RelayError::Config)
+    }
+
+    /// Logs and persists identifier of created tendermint light client, so it can be
+    /// passed to subsequent runs.
+    pub fn record_client(cfg: &SubstrateConfig, client_id: &str, hash: &str) {
+        info!(
+            "Created cosmos light client with id: {}, pass it with --substrate-client to resume relaying",
+            client_id
+        );
+        if let Err(e) = client_id::record(
+            &cfg.clients_file,
+            "substrate",
+            client_id,
+            &cfg.counterparty_chain_id,
+            hash,
+        ) {
+            warn!(
+                "Unable to record client {} in {}: {}",
+                client_id, cfg.clients_file, e
+            );
+        }
+    }
+
+    /// Creates tendermint light client trusting `header`, returns hash of the extrinsic.
+    pub async fn create_client(
+        cfg: &SubstrateConfig,
+        client_id: &str,
+        header: TMHeader,
+    ) -> Result<String, RelayError> {
+        let connection = SubstrateConnection::new(cfg)?;
+        let submission = connection.lock().await?;
+        Self::submit_create_client(cfg, &submission, client_id, header).await
+    }
+
+    /// Updates tendermint light client with `header`, returns hash of the extrinsic.
+    pub async fn update_client(
+        cfg: &SubstrateConfig,
+        client_id: &str,
+        header: TMHeader,
+        next_validator_set: Vec<tendermint::validator::Info>,
+    ) -> Result<String, RelayError> {
+        let connection = SubstrateConnection::new(cfg)?;
+        let submission = connection.lock().await?;
+        Self::submit_update_client(&submission, client_id, header, next_validator_set).await
+    }
+
+    /// Fetches finalized block at `height`, or the latest finalized block, along
+    /// with authority set which finalized it.
+    pub async fn fetch_header(
+        cfg: &SubstrateConfig,
+        height: Option<u64>,
+    ) -> Result<SignedBlockWithAuthoritySet, RelayError> {
+        failover::call(
+            &cfg.metrics,
+            &cfg.name,
+            &cfg.rpc_addrs(),
+            |rpc_addr| async move {
+                let finalized = get_finalized_block_number(rpc_addr.clone())
+                    .await
+                    .map_err(|e| classify_query_error("Unable to get latest finalized block", e))?;
+                let blocknum =
+                    finalized_block_number(height, finalized).map_err(RelayError::Rpc)?;
+                get_block_with_authset_from(rpc_addr, blocknum).await
+            },
+        )
+        .await
+    }
+
+    /// Queries state of the client and fails if it can no longer be updated.
+    /// Query errors are only logged, as client state is advisory.
+    async fn refresh_client_state(
+        cfg: &SubstrateConfig,
+        client_id: &str,
+        trusting_period: u64,
+    ) -> Result<Option<ClientState>, RelayError> {
+        match failover::call(&cfg.metrics, &cfg.name, &cfg.rpc_addrs(), |rpc_addr| {
+            Self::query_client_state(rpc_addr, client_id.to_owned())
+        })
+        .await
+        {
+            Ok(state) => {
+                state
+                    .ensure_active(Duration::from_secs(trusting_period), SystemTime::now())
+                    .map_err(RelayError::Verification)?;
+                Ok(Some(state))
+            }
+            Err(e) => {
+                warn!(
+                    "Unable to query state of cosmos light client {}: {}",
+                    client_id, e
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Queries state of cosmos light client stored by tendermint light client module.
+    pub async fn query_client_state(
+        rpc_addr: String,
+        client_id: String,
+    ) -> Result<ClientState, RelayError> {
+        let storage = ClientInfoStorage::query(&rpc_addr).await?;
+        let info = get_client_info(rpc_addr, storage.key(&client_id))
+            .await
+            .map_err(|e| classify_query_error("Unable to get client info", e))?
+            .ok_or_else(|| RelayError::Config(format!("client {} does not exist", client_id)))?;
+        Ok(ClientState {
+            client_id,
+            latest_height: info.latest_height,
+            latest_timestamp: Some(info.latest_time),
+            frozen_height: info.frozen_height,
+            stored: Some(StoredState::Tendermint(info)),
+        })
+    }
+
+    /// Lists identifiers of clients stored by tendermint light client module.
+    pub async fn query_client_ids(rpc_addr: String) -> Result<Vec<String>, RelayError> {
+        let storage = ClientInfoStorage::query(&rpc_addr).await?;
+        let keys = get_storage_keys(rpc_addr, format!("0x{}", hex::encode(&storage.prefix)))
+            .await
+            .map_err(|e| classify_query_error("Unable to get client info keys", e))?;
+        keys.iter()
+            .map(|key| {
+                let key = hex::decode(key.trim_start_matches("0x")).map_err(RelayError::rpc)?;
+                storage.client_id(&key)
+            })
+            .collect()
+    }
+}
+
+/// Number of block at `height`, or of the latest finalized block `finalized` if not given.
+/// Substrate light client trusts justification of every header, which blocks not
+/// finalized yet do not carry, so they are rejected.
+fn finalized_block_number(height: Option<u64>, finalized: String) -> Result<String, String> {
+    let height = match height {
+        Some(height) => height,
+        None => return Ok(finalized),
+    };
+    let finalized_height =
+        u64::from_str_radix(finalized.trim_start_matches("0x"), 16).map_err(to_string)?;
+    if height > finalized_height {
+        return Err(format!(
+            "block at height: {} is not finalized yet, latest finalized block is at height: {}",
+            height, finalized_height
+        ));
+    }
+    Ok(format!("0x{:x}", height))
+}
+
+/// Layout of client info map, as declared in runtime metadata.
+#[derive(Clone, Debug, PartialEq)]
+struct ClientInfoStorage {
+    /// Prefix shared by storage keys of the map.
+    prefix: Vec<u8>,
+    hasher: StorageHasher,
+}
+
+impl ClientInfoStorage {
+    /// Reads layout of client info map from runtime metadata of the node at `rpc_addr`.
+    async fn query(rpc_addr: &str) -> Result<Self, RelayError> {
+        let metadata = get_metadata(rpc_addr.to_owned())
+            .await
+            .map_err(|e| classify_query_error("Unable to get runtime metadata", e))?;
+        Self::from_metadata(&metadata)
+    }
+
+    /// Chain which does not declare the map does not host tendermint light client module,
+    /// or hosts one quantum tunnel does not support, so it is a configuration error.
+    fn from_metadata(metadata: &[u8]) -> Result<Self, RelayError> {
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+            .map_err(|e| RelayError::Encoding(format!("invalid runtime metadata: {}", e)))?;
+        let modules = match metadata {
+            RuntimeMetadataPrefixed(
+                META_RESERVED,
+                RuntimeMetadata::V12(RuntimeMetadataV12 {
+                    modules: DecodeDifferent::Decoded(modules),
+                    ..
+                }),
+            ) => modules,
+            _ => {
+                return Err(RelayError::config(
+                    "unsupported runtime metadata, only version 12 is supported",
+                ))
             }
+        };
+        let missing = || {
+            RelayError::Config(format!(
+                "runtime metadata does not declare storage {}::{}, chain does not host tendermint light client module",
+                CLIENT_INFO_MODULE, CLIENT_INFO_STORAGE_ITEM
+            ))
+        };
+        let storage = modules
+            .into_iter()
+            .find(|module| decoded_str(&module.name) == Some(CLIENT_INFO_MODULE))
+            .and_then(|module| match module.storage {
+                Some(DecodeDifferent::Decoded(storage)) => Some(storage),
+                _ => None,
+            })
+            .ok_or_else(missing)?;
+        let entry = match &storage.entries {
+            DecodeDifferent::Decoded(entries) => entries
+                .iter()
+                .find(|entry| decoded_str(&entry.name) == Some(CLIENT_INFO_STORAGE_ITEM)),
+            DecodeDifferent::Encode(_) => None,
         }
+        .ok_or_else(missing)?;
+        let hasher = match &entry.ty {
+            StorageEntryType::Map { hasher, .. } => hasher.clone(),
+            _ => {
+                return Err(RelayError::Config(format!(
+                    "storage {}::{} is not a map",
+                    CLIENT_INFO_MODULE, CLIENT_INFO_STORAGE_ITEM
+                )))
+            }
+        };
+        let module_prefix = decoded_str(&storage.prefix).ok_or_else(missing)?;
+        let mut prefix = twox_128(module_prefix.as_bytes()).to_vec();
+        prefix.extend_from_slice(&twox_128(CLIENT_INFO_STORAGE_ITEM.as_bytes()));
+        Ok(Self { prefix, hasher })
+    }
+
+    /// Key of storage holding info of given client.
+    fn key(&self, client_id: &str) -> String {
+        let encoded_id = client_id.as_bytes().to_vec().encode();
+        let mut key = self.prefix.clone();
+        key.extend(storage_hash(&self.hasher, &encoded_id));
+        format!("0x{}", hex::encode(key))
+    }
+
+    /// Key is prefix, followed by hash of scale encoded client id, so only maps whose
+    /// hasher keeps the id after its hash can be listed.
+    fn client_id(&self, key: &[u8]) -> Result<String, RelayError> {
+        let hash_len = match self.hasher {
+            StorageHasher::Blake2_128Concat => 16,
+            StorageHasher::Twox64Concat => 8,
+            StorageHasher::Identity => 0,
+            _ => {
+                return Err(RelayError::Config(format!(
+                    "client ids cannot be listed, storage {}::{} is hashed with {:?}",
+                    CLIENT_INFO_MODULE, CLIENT_INFO_STORAGE_ITEM, self.hasher
+                )))
+            }
+        };
+        let mut encoded_id = Some(key)
+            .filter(|key| key.starts_with(&self.prefix))
+            .and_then(|key| key.get(self.prefix.len() + hash_len..))
+            .ok_or_else(|| {
+                RelayError::Rpc(format!("not a client info key: {}", hex::encode(key)))
+            })?;
+        let id = Vec::<u8>::decode(&mut encoded_id).map_err(RelayError::rpc)?;
+        String::from_utf8(id).map_err(RelayError::rpc)
+    }
+}
+
+fn decoded_str(value: &DecodeDifferentStr) -> Option<&str> {
+    match value {
+        DecodeDifferent::Decoded(value) => Some(value.as_str()),
+        DecodeDifferent::Encode(_) => None,
     }
 }
 
-async fn get_block_at_height(
+fn storage_hash(hasher: &StorageHasher, data: &[u8]) -> Vec<u8> {
+    match hasher {
+        StorageHasher::Blake2_128 => blake2_128(data).to_vec(),
+        StorageHasher::Blake2_256 => blake2_256(data).to_vec(),
+        StorageHasher::Blake2_128Concat => [&blake2_128(data)[..], data].concat(),
+        StorageHasher::Twox128 => twox_128(data).to_vec(),
+        StorageHasher::Twox256 => twox_256(data).to_vec(),
+        StorageHasher::Twox64Concat => [&twox_64(data)[..], data].concat(),
+        StorageHasher::Identity => data.to_vec(),
+    }
+}
+
+/// Errors of reaching the node are transport errors, so the query is sent to another
+/// one, while anything else is an error response or one which could not be parsed.
+pub(crate) fn classify_query_error(context: &str, err: Box<dyn Error>) -> RelayError {
+    let message = format!("{}: {}", context, err);
+    if err.downcast_ref::<hyper::Error>().is_some() {
+        RelayError::Transport(message)
+    } else {
+        RelayError::Rpc(message)
+    }
+}
+
+/// Runtime errors are raised by tendermint light client module refusing the payload,
+/// and an extrinsic the node finds invalid never makes it into a block. Codec and
+/// metadata errors mean extrinsic or its events could not be encoded or decoded, while
+/// anything else is an error of the connection to the node.
+fn classify_error(err: substrate_subxt::Error) -> RelayError {
+    match err {
+        substrate_subxt::Error::Runtime(err) => RelayError::TxRejected {
+            codespace: None,
+            code: None,
+            log: err.to_string(),
+        },
+        substrate_subxt::Error::Rpc(err) => RelayError::rpc(err),
+        substrate_subxt::Error::Invalid(err) => RelayError::Rpc(format!("{:?}", err)),
+        substrate_subxt::Error::Codec(err) => RelayError::encoding(err),
+        substrate_subxt::Error::Metadata(err) => RelayError::encoding(err),
+        err => RelayError::transport(err),
+    }
+}
+
+async fn get_block_with_authset(
+    cfg: &SubstrateConfig,
+    blocknum: String,
+) -> Result<SignedBlockWithAuthoritySet, RelayError> {
+    failover::call(&cfg.metrics, &cfg.name, &cfg.rpc_addrs(), |rpc_addr| {
+        get_block_with_authset_from(rpc_addr, blocknum.clone())
+    })
+    .await
+}
+
+async fn get_block_with_authset_from(
+    rpc_addr: String,
+    blocknum: String,
+) -> Result<SignedBlockWithAuthoritySet, RelayError> {
+    let (blockhash, block) = get_block_at_height(rpc_addr.clone(), blocknum.clone())
+        .await
+        .map_err(|e| {
+            classify_query_error(&format!("Unable to get block at height: {}", blocknum), e)
+        })?;
+
+    let (authority_set, set_id) = get_authset_with_id(rpc_addr, blockhash.clone())
+        .await
+        .map_err(|e| {
+            classify_query_error(
+                &format!("Unable to fetch authority set at height: {}", blocknum),
+                e,
+            )
+        })?;
+
+    Ok(SignedBlockWithAuthoritySet::from_parts(
+        block,
+        authority_set,
+        set_id,
+    ))
+}
+
+pub(crate) async fn get_finalized_block_number(rpc_addr: String) -> Result<String, Box<dyn Error>> {
+    let client = Client::new();
+    let head_req = Request::builder()
+        .method(Method::POST)
+        .uri(rpc_addr.clone())
+        .header("content-type", "application/json")
+        .body(Body::from(
+            r#"{"jsonrpc":"2.0", "method": "chain_getFinalizedHead", "params": [], "id":"0"}"#,
+        ))?;
+    let response = client.request(head_req).await?;
+    let response_body = aggregate(response).await?;
+    let stringified_body = String::from_utf8(response_body.bytes().to_vec())?;
+    let head_rpc_response: HashRpcResponse = from_str(&stringified_body)?;
+    let header_req = Request::builder()
+        .method(Method::POST)
+        .uri(rpc_addr.clone())
+        .header("content-type", "application/json")
+        .body(Body::from(format!(
+            r#"{{"jsonrpc":"2.0", "method": "chain_getHeader", "params": ["{}"], "id":"0"}}"#,
+            head_rpc_response.result
+        )))?;
+    let response = client.request(header_req).await?;
+    let response_body = aggregate(response).await?;
+    let stringified_body = String::from_utf8(response_body.bytes().to_vec())?;
+    let json = from_str::<Value>(&stringified_body)?;
+    let blocknum = json["result"]["number"].as_str().ok_or_else(|| {
+        format!(
+            "header response did not include the block number: {:?}",
+            json
+        )
+    })?;
+    Ok(blocknum.to_string())
+}
+
+pub(crate) async fn get_block_at_height(
     rpc_addr: String,
     block_num: String,
 ) -> Result<(String, SignedBlock), Box<dyn Error>> {
@@ -389,7 +1211,7 @@ async fn get_block_at_height(
     Ok((block_hash_rpc_response.result, block_rpc_response.result))
 }
 
-async fn get_authset_with_id(
+pub(crate) async fn get_authset_with_id(
     rpc_addr: String,
     block_hash: String,
 ) -> Result<(AuthorityList, u64), Box<dyn Error>> {
@@ -404,7 +1226,7 @@ async fn get_authset_with_id(
     let set_resp = client.request(set_req).await?;
     let set_body = aggregate(set_resp).await?;
     let set_rstr = String::from_utf8(set_body.bytes().to_vec())?;
-    let set: AuthSetRpcResponse = from_str(&set_rstr).unwrap();
+    let set: AuthSetRpcResponse = from_str(&set_rstr)?;
 
     let setid_req = Request::builder()
     .method(Method::POST)
@@ -415,11 +1237,220 @@ async fn get_authset_with_id(
     let setid_body = aggregate(setid_resp).await?;
     let setid_rstr = String::from_utf8(setid_body.bytes().to_vec())?;
     let setid_response: AuthSetIdRpcResponse = from_str(&setid_rstr)?;
+    let authority_set = set.get_authset()?;
+    let set_id = setid_response.as_u64()?;
     info!(
         "Received set id: {} and authority set: {:?} for block with hash: {}",
-        setid_response.as_u64(),
-        set.get_authset(),
-        block_hash
+        set_id, authority_set, block_hash
     );
-    Ok((set.get_authset(), setid_response.as_u64()))
+    Ok((authority_set, set_id))
+}
+
+async fn get_client_info(
+    rpc_addr: String,
+    storage_key: String,
+) -> Result<Option<TMClientInfo>, Box<dyn Error>> {
+    let client = Client::new();
+    let info_req = Request::builder()
+        .method(Method::POST)
+        .uri(rpc_addr.clone())
+        .header("content-type", "application/json")
+        .body(Body::from(format!(
+            r#"{{"jsonrpc":"2.0", "method": "state_getStorage", "params": ["{}"], "id":"0"}}"#,
+            storage_key
+        )))?;
+    let info_resp = client.request(info_req).await?;
+    let info_body = aggregate(info_resp).await?;
+    let info_rstr = String::from_utf8(info_body.bytes().to_vec())?;
+    let response: StorageRpcResponse = from_str(&info_rstr)?;
+    match response.result {
+        Some(value) => Ok(Some(decode_client_info(&value)?)),
+        None => Ok(None),
+    }
+}
+
+/// Module stores client info as json, the same way it receives create client payload,
+/// wrapped in scale encoded bytes.
+fn decode_client_info(value: &str) -> Result<TMClientInfo, Box<dyn Error>> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))?;
+    let json = Vec::<u8>::decode(&mut bytes.as_slice())?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+async fn get_metadata(rpc_addr: String) -> Result<Vec<u8>, Box<dyn Error>> {
+    let client = Client::new();
+    let metadata_req = Request::builder()
+        .method(Method::POST)
+        .uri(rpc_addr.clone())
+        .header("content-type", "application/json")
+        .body(Body::from(
+            r#"{"jsonrpc":"2.0", "method": "state_getMetadata", "params": [], "id":"0"}"#,
+        ))?;
+    let metadata_resp = client.request(metadata_req).await?;
+    let metadata_body = aggregate(metadata_resp).await?;
+    let metadata_rstr = String::from_utf8(metadata_body.bytes().to_vec())?;
+    let response: MetadataRpcResponse = from_str(&metadata_rstr)?;
+    Ok(hex::decode(response.result.trim_start_matches("0x"))?)
+}
+
+async fn get_storage_keys(rpc_addr: String, prefix: String) -> Result<Vec<String>, Box<dyn Error>> {
+    let client = Client::new();
+    let keys_req = Request::builder()
+        .method(Method::POST)
+        .uri(rpc_addr.clone())
+        .header("content-type", "application/json")
+        .body(Body::from(format!(
+            r#"{{"jsonrpc":"2.0", "method": "state_getKeys", "params": ["{}"], "id":"0"}}"#,
+            prefix
+        )))?;
+    let keys_resp = client.request(keys_req).await?;
+    let keys_body = aggregate(keys_resp).await?;
+    let keys_rstr = String::from_utf8(keys_body.bytes().to_vec())?;
+    let response: StorageKeysRpcResponse = from_str(&keys_rstr)?;
+    Ok(response.result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        finalized_block_number, ClientInfoStorage, CLIENT_INFO_MODULE, CLIENT_INFO_STORAGE_ITEM,
+    };
+    use crate::error::RelayError;
+    use frame_metadata::{
+        DecodeDifferent, ExtrinsicMetadata, ModuleMetadata, RuntimeMetadata,
+        RuntimeMetadataPrefixed, RuntimeMetadataV12, StorageEntryMetadata, StorageEntryModifier,
+        StorageEntryType, StorageHasher, StorageMetadata, META_RESERVED,
+    };
+    use parity_scale_codec::Encode;
+    use sp_core::hashing::{twox_128, twox_64};
+
+    fn map(hasher: StorageHasher) -> StorageEntryType {
+        StorageEntryType::Map {
+            hasher,
+            key: DecodeDifferent::Decoded("Vec<u8>".to_owned()),
+            value: DecodeDifferent::Decoded("Vec<u8>".to_owned()),
+            unused: false,
+        }
+    }
+
+    fn metadata(module: &str, prefix: &str, item: &str, ty: StorageEntryType) -> Vec<u8> {
+        let entry = StorageEntryMetadata {
+            name: DecodeDifferent::Decoded(item.to_owned()),
+            modifier: StorageEntryModifier::Optional,
+            ty,
+            default: DecodeDifferent::Decoded(vec![0]),
+            documentation: DecodeDifferent::Decoded(vec![]),
+        };
+        let module = ModuleMetadata {
+            name: DecodeDifferent::Decoded(module.to_owned()),
+            storage: Some(DecodeDifferent::Decoded(StorageMetadata {
+                prefix: DecodeDifferent::Decoded(prefix.to_owned()),
+                entries: DecodeDifferent::Decoded(vec![entry]),
+            })),
+            calls: None,
+            event: None,
+            constants: DecodeDifferent::Decoded(vec![]),
+            errors: DecodeDifferent::Decoded(vec![]),
+            index: 8,
+        };
+        RuntimeMetadataPrefixed(
+            META_RESERVED,
+            RuntimeMetadata::V12(RuntimeMetadataV12 {
+                modules: DecodeDifferent::Decoded(vec![module]),
+                extrinsic: ExtrinsicMetadata {
+                    version: 4,
+                    signed_extensions: vec![],
+                },
+            }),
+        )
+        .encode()
+    }
+
+    #[test]
+    fn test_client_info_storage_layout_is_read_from_metadata() {
+        let storage = ClientInfoStorage::from_metadata(&metadata(
+            CLIENT_INFO_MODULE,
+            "TendermintClient",
+            CLIENT_INFO_STORAGE_ITEM,
+            map(StorageHasher::Twox64Concat),
+        ))
+        .unwrap();
+        let encoded_id = b"testingclient".to_vec().encode();
+        let mut key = twox_128(b"TendermintClient").to_vec();
+        key.extend_from_slice(&twox_128(CLIENT_INFO_STORAGE_ITEM.as_bytes()));
+        assert_eq!(storage.prefix, key);
+        key.extend_from_slice(&twox_64(&encoded_id));
+        key.extend_from_slice(&encoded_id);
+        assert_eq!(
+            storage.key("testingclient"),
+            format!("0x{}", hex::encode(&key))
+        );
+        assert_eq!(storage.client_id(&key).unwrap(), "testingclient");
+        assert!(storage.client_id(&key[1..]).is_err());
+    }
+
+    #[test]
+    fn test_missing_client_info_storage_fails() {
+        let missing = format!("{}::{}", CLIENT_INFO_MODULE, CLIENT_INFO_STORAGE_ITEM);
+        for metadata in vec![
+            metadata(
+                "Balances",
+                "Balances",
+                CLIENT_INFO_STORAGE_ITEM,
+                map(StorageHasher::Blake2_128Concat),
+            ),
+            metadata(
+                CLIENT_INFO_MODULE,
+                CLIENT_INFO_MODULE,
+                "ClientStates",
+                map(StorageHasher::Blake2_128Concat),
+            ),
+            metadata(
+                CLIENT_INFO_MODULE,
+                CLIENT_INFO_MODULE,
+                CLIENT_INFO_STORAGE_ITEM,
+                StorageEntryType::Plain(DecodeDifferent::Decoded("Vec<u8>".to_owned())),
+            ),
+        ] {
+            match ClientInfoStorage::from_metadata(&metadata) {
+                Err(RelayError::Config(e)) => assert!(e.contains(&missing), "{}", e),
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+        assert!(matches!(
+            ClientInfoStorage::from_metadata(b"not metadata"),
+            Err(RelayError::Encoding(_))
+        ));
+
+        // Ids cannot be recovered from keys hashed without the id appended.
+        let storage = ClientInfoStorage::from_metadata(&metadata(
+            CLIENT_INFO_MODULE,
+            CLIENT_INFO_MODULE,
+            CLIENT_INFO_STORAGE_ITEM,
+            map(StorageHasher::Blake2_256),
+        ))
+        .unwrap();
+        let key = hex::decode(storage.key("testingclient").trim_start_matches("0x")).unwrap();
+        assert!(matches!(
+            storage.client_id(&key),
+            Err(RelayError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_only_finalized_blocks_are_fetched() {
+        assert_eq!(
+            finalized_block_number(None, "0x1f".to_owned()).unwrap(),
+            "0x1f"
+        );
+        assert_eq!(
+            finalized_block_number(Some(31), "0x1f".to_owned()).unwrap(),
+            "0x1f"
+        );
+        assert_eq!(
+            finalized_block_number(Some(10), "0x1f".to_owned()).unwrap(),
+            "0xa"
+        );
+        assert!(finalized_block_number(Some(32), "0x1f".to_owned()).is_err());
+    }
 }
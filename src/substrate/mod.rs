@@ -1,4 +1,9 @@
+pub mod chain;
+pub mod connection;
+pub mod fork;
+pub mod generator;
 pub mod handler;
 pub mod types;
+pub mod verifier;
 
 pub type Handler = handler::SubstrateHandler;
@@ -0,0 +1,48 @@
+use crate::substrate::types::sub::{BlockNumber, Header};
+use parity_scale_codec::{Decode, Encode};
+use sp_core::H256;
+use sp_finality_grandpa::{AuthorityId, AuthoritySignature, SetId};
+
+/// Index of `Precommit` variant in `finality_grandpa::Message`, used to build
+/// the payload signed by each authority.
+const PRECOMMIT_MESSAGE_INDEX: u8 = 1;
+
+/// GRANDPA precommit vote, SCALE compatible with `finality_grandpa::Precommit`.
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct Precommit {
+    pub target_hash: H256,
+    pub target_number: BlockNumber,
+}
+
+impl Precommit {
+    /// Payload signed by authorities, mirrors
+    /// `sp_finality_grandpa::localized_payload(round, set_id, &Message::Precommit(_))`.
+    pub fn signing_payload(&self, round: u64, set_id: SetId) -> Vec<u8> {
+        (PRECOMMIT_MESSAGE_INDEX, self, round, set_id).encode()
+    }
+}
+
+/// SCALE compatible with `finality_grandpa::SignedPrecommit`.
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct SignedPrecommit {
+    pub precommit: Precommit,
+    pub signature: AuthoritySignature,
+    pub id: AuthorityId,
+}
+
+/// SCALE compatible with `finality_grandpa::Commit`.
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct Commit {
+    pub target_hash: H256,
+    pub target_number: BlockNumber,
+    pub precommits: Vec<SignedPrecommit>,
+}
+
+/// SCALE compatible with `sc_finality_grandpa::GrandpaJustification`, which
+/// is what substrate nodes attach to finalized blocks.
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct GrandpaJustification {
+    pub round: u64,
+    pub commit: Commit,
+    pub votes_ancestries: Vec<Header>,
+}
@@ -1,4 +1,6 @@
+mod grandpa;
 mod sub;
+use crate::utils::to_string;
 use parity_scale_codec::Decode;
 use serde::{Deserialize, Serialize};
 use sp_finality_grandpa::{AuthorityId, AuthorityWeight, VersionedAuthorityList};
@@ -6,6 +8,13 @@ use sp_finality_grandpa::{AuthorityId, AuthorityWeight, VersionedAuthorityList};
 pub type SignedBlockWithAuthoritySet = sub::SignedBlockWithAuthoritySet;
 pub type CreateSignedBlockWithAuthoritySet = sub::CreateSignedBlockWithAuthoritySet;
 pub type SignedBlock = sub::SignedBlock;
+pub type Block = sub::Block;
+pub type Header = sub::Header;
+pub type BlockNumber = sub::BlockNumber;
+pub type Precommit = grandpa::Precommit;
+pub type SignedPrecommit = grandpa::SignedPrecommit;
+pub type Commit = grandpa::Commit;
+pub type GrandpaJustification = grandpa::GrandpaJustification;
 pub type AuthorityList = Vec<(AuthorityId, AuthorityWeight)>;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -18,15 +27,32 @@ pub struct BlockRpcResponse {
     pub result: SignedBlock,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageRpcResponse {
+    pub result: Option<String>, // hex encoded storage value, if any
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MetadataRpcResponse {
+    pub result: String, // hex encoded runtime metadata
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageKeysRpcResponse {
+    pub result: Vec<String>, // hex encoded storage keys
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AuthSetRpcResponse {
     pub result: String, // scale-encoded AuthorityList
 }
 
 impl AuthSetRpcResponse {
-    pub fn get_authset(&self) -> AuthorityList {
-        let bytes = hex::decode(&self.result[2..]).unwrap();
-        (VersionedAuthorityList::decode(&mut bytes.as_slice()).unwrap()).into()
+    pub fn get_authset(&self) -> Result<AuthorityList, String> {
+        let bytes = hex::decode(self.result.trim_start_matches("0x")).map_err(to_string)?;
+        Ok(VersionedAuthorityList::decode(&mut bytes.as_slice())
+            .map_err(to_string)?
+            .into())
     }
 }
 
@@ -36,10 +62,7 @@ pub struct AuthSetIdRpcResponse {
 }
 
 impl AuthSetIdRpcResponse {
-    pub fn as_u64(&self) -> u64 {
-        match u64::from_str_radix(&self.result[2..], 16) {
-            Ok(val) => val,
-            Err(e) => panic!(e),
-        }
+    pub fn as_u64(&self) -> Result<u64, String> {
+        u64::from_str_radix(self.result.trim_start_matches("0x"), 16).map_err(to_string)
     }
 }
@@ -0,0 +1,302 @@
+use crate::substrate::types::{
+    AuthorityList, BlockNumber, GrandpaJustification, Header, SignedBlockWithAuthoritySet,
+};
+use crate::utils::to_string;
+use parity_scale_codec::Decode;
+use sp_core::{Pair, H256};
+use sp_finality_grandpa::{AuthorityPair, ConsensusLog, GRANDPA_ENGINE_ID};
+use sp_runtime::generic::DigestItem;
+use sp_runtime::traits::Header as HeaderT;
+use std::collections::{HashMap, HashSet};
+
+/// In-process counterpart of substrate light client running inside cosmos
+/// chain. It follows the same rules as the wasm client: headers must form a
+/// contiguous chain, and every justification must be signed by more than 2/3
+/// of the authority set currently trusted by the client. Wasm client is built for
+/// the wormhole chain and is not available as a crate, so its rules are ported here.
+pub struct GrandpaVerifier {
+    last_header: Header,
+    authority_set: AuthorityList,
+    set_id: u64,
+    pending_change: Option<(BlockNumber, AuthorityList, bool)>,
+    headers_since_justification: u64,
+    max_headers_allowed_between_justifications: u64,
+}
+
+impl GrandpaVerifier {
+    /// Trusts given header unconditionally, the same way client creation does.
+    pub fn new(
+        header: SignedBlockWithAuthoritySet,
+        max_headers_allowed_between_justifications: u64,
+    ) -> Self {
+        GrandpaVerifier {
+            last_header: header.block.block.header,
+            authority_set: header.authority_set,
+            set_id: header.set_id,
+            pending_change: None,
+            headers_since_justification: 0,
+            max_headers_allowed_between_justifications,
+        }
+    }
+
+    /// Height of the latest ingested header.
+    pub fn height(&self) -> BlockNumber {
+        self.last_header.number
+    }
+
+    /// Verifies header against trusted state and, if valid, advances it.
+    pub fn ingest(&mut self, header: SignedBlockWithAuthoritySet) -> Result<(), String> {
+        let block = header.block;
+        let number = block.block.header.number;
+        if number != self.last_header.number + 1 {
+            return Err(format!(
+                "expected header at height: {}, received header at height: {}",
+                self.last_header.number + 1,
+                number
+            ));
+        }
+        if block.block.header.parent_hash != self.last_header.hash() {
+            return Err(format!(
+                "parent hash of header at height: {} does not match hash of trusted header",
+                number
+            ));
+        }
+
+        let pending_change = Self::find_authority_change(&block.block.header)?;
+
+        let finalized = match block.justification {
+            Some(justification) => {
+                verify_justification(
+                    &block.block.header,
+                    &justification,
+                    &self.authority_set,
+                    self.set_id,
+                )?;
+                self.headers_since_justification = 0;
+                true
+            }
+            None => {
+                self.headers_since_justification += 1;
+                if self.headers_since_justification
+                    > self.max_headers_allowed_between_justifications
+                {
+                    return Err(format!(
+                        "received {} headers without justification, at most {} are allowed",
+                        self.headers_since_justification,
+                        self.max_headers_allowed_between_justifications
+                    ));
+                }
+                false
+            }
+        };
+
+        if let Some(change) = pending_change {
+            if self.pending_change.is_some() {
+                return Err(format!(
+                    "header at height: {} announces authority set change while another one is pending",
+                    number
+                ));
+            }
+            self.pending_change = Some(change);
+        }
+
+        let enacted = match &self.pending_change {
+            Some((at, _, forced)) => *at <= number && (finalized || *forced),
+            None => false,
+        };
+        if enacted {
+            let (_, next_authorities, _) = self.pending_change.take().unwrap();
+            self.authority_set = next_authorities;
+            self.set_id += 1;
+        }
+
+        if header.set_id != self.set_id {
+            return Err(format!(
+                "set id reported with header at height: {} is {}, while verifier expects {}",
+                number, header.set_id, self.set_id
+            ));
+        }
+
+        self.last_header = block.block.header;
+        Ok(())
+    }
+
+    fn find_authority_change(
+        header: &Header,
+    ) -> Result<Option<(BlockNumber, AuthorityList, bool)>, String> {
+        for log in header.digest.logs() {
+            if let DigestItem::Consensus(engine_id, data) = log {
+                if *engine_id != GRANDPA_ENGINE_ID {
+                    continue;
+                }
+                match ConsensusLog::<BlockNumber>::decode(&mut data.as_slice())
+                    .map_err(to_string)?
+                {
+                    ConsensusLog::ScheduledChange(change) => {
+                        return Ok(Some((
+                            header.number + change.delay,
+                            change.next_authorities,
+                            false,
+                        )))
+                    }
+                    ConsensusLog::ForcedChange(_, change) => {
+                        return Ok(Some((
+                            header.number + change.delay,
+                            change.next_authorities,
+                            true,
+                        )))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Verifies that justification of `header` is signed by more than 2/3 of the weight of
+/// given authority set, and returns it decoded.
+pub fn verify_justification(
+    header: &Header,
+    encoded: &[u8],
+    authority_set: &AuthorityList,
+    set_id: u64,
+) -> Result<GrandpaJustification, String> {
+    let justification = GrandpaJustification::decode(&mut &encoded[..]).map_err(to_string)?;
+    let hash = header.hash();
+    if justification.commit.target_hash != hash
+        || justification.commit.target_number != header.number
+    {
+        return Err(format!(
+            "justification of header at height: {} targets a different block",
+            header.number
+        ));
+    }
+
+    let ancestry: HashMap<H256, H256> = justification
+        .votes_ancestries
+        .iter()
+        .map(|h| (h.hash(), h.parent_hash))
+        .collect();
+    let mut visited = HashSet::new();
+    let mut voters = HashSet::new();
+    let mut signed_weight = 0u64;
+    for signed in &justification.commit.precommits {
+        let weight = authority_set
+            .iter()
+            .find(|(id, _)| *id == signed.id)
+            .map(|(_, weight)| *weight)
+            .ok_or_else(|| {
+                format!(
+                    "justification of header at height: {} is signed by unknown authority: {:?}",
+                    header.number, signed.id
+                )
+            })?;
+        // Precommit may vote for a descendant of the target, which has to be
+        // connected to it by headers of the ancestry proof.
+        let mut voted = signed.precommit.target_hash;
+        while voted != hash {
+            match ancestry.get(&voted) {
+                Some(parent) => {
+                    visited.insert(voted);
+                    voted = *parent;
+                }
+                None => {
+                    return Err(format!(
+                        "precommit in justification of header at height: {} votes for block which does not descend from it",
+                        header.number
+                    ))
+                }
+            }
+        }
+        let payload = signed
+            .precommit
+            .signing_payload(justification.round, set_id);
+        if !AuthorityPair::verify(&signed.signature, &payload, &signed.id) {
+            return Err(format!(
+                "invalid precommit signature of authority: {:?} in justification of header at height: {}",
+                signed.id, header.number
+            ));
+        }
+        if voters.insert(signed.id.clone()) {
+            signed_weight += weight;
+        }
+    }
+
+    if visited.len() != ancestry.len() {
+        return Err(format!(
+            "justification of header at height: {} carries ancestry headers no precommit votes through",
+            header.number
+        ));
+    }
+
+    let total_weight: u64 = authority_set.iter().map(|(_, weight)| weight).sum();
+    if signed_weight * 3 <= total_weight * 2 {
+        return Err(format!(
+            "justification of header at height: {} is signed by {} out of {} weight, more than 2/3 is required",
+            header.number, signed_weight, total_weight
+        ));
+    }
+    Ok(justification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GrandpaVerifier;
+    use crate::substrate::generator::{AuthorityChange, ChainGenerator, ChangeKind};
+    use crate::substrate::types::{GrandpaJustification, SignedBlockWithAuthoritySet};
+    use parity_scale_codec::{Decode, Encode};
+
+    fn next(generator: &mut ChainGenerator) -> SignedBlockWithAuthoritySet {
+        let block = generator.next_block().unwrap();
+        SignedBlockWithAuthoritySet::from_parts(block.block, block.authority_set, block.set_id)
+    }
+
+    #[test]
+    fn test_verifier_follows_authority_set_changes() {
+        let mut generator = ChainGenerator::new(1, 4, 0, 2);
+        generator
+            .schedule_change(AuthorityChange::parse(ChangeKind::Scheduled, "3:2:5").unwrap())
+            .unwrap();
+        let mut verifier = GrandpaVerifier::new(next(&mut generator), 512);
+        for _ in 0..8 {
+            verifier.ingest(next(&mut generator)).unwrap();
+        }
+        assert_eq!(verifier.height(), 9);
+        assert_eq!(verifier.set_id, 1);
+    }
+
+    #[test]
+    fn test_verifier_rejects_wrong_set_id_and_unused_ancestry() {
+        let mut generator = ChainGenerator::new(1, 4, 0, 1);
+        let first = next(&mut generator);
+        let second = next(&mut generator);
+        let mut verifier = GrandpaVerifier::new(first.clone(), 512);
+
+        let mut wrong_set_id = second.clone();
+        wrong_set_id.set_id = 1;
+        assert!(verifier.ingest(wrong_set_id).is_err());
+
+        let mut extra_ancestry = second.clone();
+        let mut justification = GrandpaJustification::decode(
+            &mut &extra_ancestry.block.justification.as_ref().unwrap()[..],
+        )
+        .unwrap();
+        justification
+            .votes_ancestries
+            .push(first.block.block.header);
+        extra_ancestry.block.justification = Some(justification.encode());
+        assert!(verifier.ingest(extra_ancestry).is_err());
+
+        verifier.ingest(second).unwrap();
+        assert_eq!(verifier.height(), 2);
+    }
+
+    #[test]
+    fn test_verifier_rejects_gap() {
+        let mut generator = ChainGenerator::new(1, 4, 0, 1);
+        let mut verifier = GrandpaVerifier::new(next(&mut generator), 512);
+        let _ = next(&mut generator);
+        assert!(verifier.ingest(next(&mut generator)).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Virtual clock used by simulations, so that verification of simulated
+//! headers does not depend on when the simulation is run.
+//! It only reaches the in-process tendermint light client: GRANDPA
+//! justifications carry no timestamps, and light clients on live chains use
+//! their chain's block time.
+use crate::config::SimulationTiming;
+use crate::utils::to_string;
+use parse_duration::parse;
+use std::time::{Duration, Instant, SystemTime};
+
+pub struct VirtualClock {
+    origin: SystemTime,
+    started: Instant,
+    speed: f64,
+    poll_interval: Duration,
+    header_time_as_now: bool,
+}
+
+impl VirtualClock {
+    pub fn from_config(cfg: &SimulationTiming) -> Result<Self, String> {
+        let origin = match &cfg.virtual_now {
+            Some(now) => tendermint::Time::parse_from_rfc3339(now)
+                .map_err(to_string)?
+                .into(),
+            None => SystemTime::now(),
+        };
+        if cfg.replay_speed < 0.0 {
+            return Err(format!(
+                "replay speed cannot be negative, got: {}",
+                cfg.replay_speed
+            ));
+        }
+        Ok(VirtualClock {
+            origin,
+            started: Instant::now(),
+            speed: cfg.replay_speed,
+            poll_interval: parse(&cfg.poll_interval).map_err(to_string)?,
+            header_time_as_now: cfg.header_time_as_now,
+        })
+    }
+
+    /// Current virtual time, which advances `speed` times faster than wall clock.
+    pub fn now(&self) -> SystemTime {
+        self.origin + self.started.elapsed().mul_f64(self.speed)
+    }
+
+    /// Time at which header with given timestamp is considered to be verified.
+    pub fn now_for_header(&self, header_time: SystemTime) -> SystemTime {
+        if self.header_time_as_now {
+            header_time
+        } else {
+            self.now()
+        }
+    }
+
+    /// Waits for the configured poll interval.
+    pub async fn tick(&self) {
+        tokio::time::delay_for(self.poll_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VirtualClock;
+    use crate::config::SimulationTiming;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_frozen_clock_stays_at_virtual_now() {
+        let clock = VirtualClock::from_config(&SimulationTiming {
+            virtual_now: Some("2020-07-13T09:13:28Z".to_owned()),
+            replay_speed: 0.0,
+            ..SimulationTiming::default()
+        })
+        .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(1_594_631_608));
+    }
+
+    #[test]
+    fn test_clock_advances_at_replay_speed() {
+        let clock = VirtualClock::from_config(&SimulationTiming {
+            virtual_now: Some("2020-07-13T09:13:28Z".to_owned()),
+            replay_speed: 1000.0,
+            ..SimulationTiming::default()
+        })
+        .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        let elapsed = clock
+            .now()
+            .duration_since(UNIX_EPOCH + Duration::from_secs(1_594_631_608))
+            .unwrap();
+        assert!(elapsed >= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_header_time_as_now_ignores_virtual_now() {
+        let header_time = UNIX_EPOCH + Duration::from_secs(1_000);
+        let timing = SimulationTiming {
+            virtual_now: Some("2020-07-13T09:13:28Z".to_owned()),
+            replay_speed: 0.0,
+            ..SimulationTiming::default()
+        };
+        let clock = VirtualClock::from_config(&timing).unwrap();
+        assert_eq!(clock.now_for_header(header_time), clock.now());
+
+        let clock = VirtualClock::from_config(&SimulationTiming {
+            header_time_as_now: true,
+            ..timing
+        })
+        .unwrap();
+        assert_eq!(clock.now_for_header(header_time), header_time);
+    }
+
+    #[test]
+    fn test_invalid_timing_is_rejected() {
+        assert!(VirtualClock::from_config(&SimulationTiming {
+            replay_speed: -1.0,
+            ..SimulationTiming::default()
+        })
+        .is_err());
+        assert!(VirtualClock::from_config(&SimulationTiming {
+            virtual_now: Some("yesterday".to_owned()),
+            ..SimulationTiming::default()
+        })
+        .is_err());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod clock;
 pub mod from_str;
 
 use rand::{thread_rng, Rng};
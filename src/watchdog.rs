@@ -0,0 +1,222 @@
+//! Watchdog keeping light clients from expiring. A light client which did not
+//! ingest a header within its trusting period has to be recreated, so send
+//! handlers consult the watchdog to know when to force a refresh.
+
+use crate::client_state::ClientState;
+use crate::config::WatchdogConfig;
+use crate::health::Health;
+use crate::metrics::Metrics;
+use log::*;
+use std::time::{Duration, Instant, SystemTime};
+
+pub struct ExpiryWatchdog {
+    health: Health,
+    metrics: Metrics,
+    chain: String,
+    path: Option<String>,
+    trusting_period: Duration,
+    cfg: WatchdogConfig,
+    last_update: Instant,
+    alerted: bool,
+    /// Whether relaying a later header ahead of queued ones refreshes the client.
+    refreshable: bool,
+    refresh_warned: bool,
+}
+
+impl ExpiryWatchdog {
+    /// Watches light client of `path`, if relaying paths, hosted on `chain`, reporting
+    /// it to `health` and `metrics`. Until the first header is relayed, time is counted
+    /// from the moment watchdog is created, unless it is seeded with state of the client.
+    pub fn new(
+        health: &Health,
+        metrics: &Metrics,
+        chain: &str,
+        path: Option<&str>,
+        trusting_period: Duration,
+        cfg: WatchdogConfig,
+    ) -> Self {
+        health.track_client(chain, path, trusting_period, &cfg);
+        ExpiryWatchdog {
+            health: health.clone(),
+            metrics: metrics.clone(),
+            chain: chain.to_owned(),
+            path: path.map(str::to_owned),
+            trusting_period,
+            cfg,
+            last_update: Instant::now(),
+            alerted: false,
+            refreshable: true,
+            refresh_warned: false,
+        }
+    }
+
+    /// Watches light client which accepts only contiguous headers, so it cannot be
+    /// refreshed; once `refresh_fraction` passes, a warning is logged instead.
+    pub fn alert_only(
+        health: &Health,
+        metrics: &Metrics,
+        chain: &str,
+        path: Option<&str>,
+        trusting_period: Duration,
+        cfg: WatchdogConfig,
+    ) -> Self {
+        ExpiryWatchdog {
+            refreshable: false,
+            ..Self::new(health, metrics, chain, path, trusting_period, cfg)
+        }
+    }
+
+    /// Light client as named in logs.
+    fn client(&self) -> String {
+        match &self.path {
+            Some(path) => format!("{} light client of path {}", self.chain, path),
+            None => format!("{} light client", self.chain),
+        }
+    }
+
+    /// Counts time from the latest header ingested by client, if it keeps track of
+    /// header time, rather than from the moment watchdog was created.
+    pub fn seed(&mut self, state: &ClientState) {
+        let timestamp: SystemTime = match state.latest_timestamp {
+            Some(timestamp) => timestamp.into(),
+            None => return,
+        };
+        let age = SystemTime::now()
+            .duration_since(timestamp)
+            .unwrap_or_default();
+        self.last_update = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+        self.health
+            .client_updated_at(&self.chain, self.path.as_deref(), self.last_update);
+    }
+
+    /// Records that light client ingested a header.
+    pub fn updated(&mut self) {
+        self.health
+            .client_updated(&self.chain, self.path.as_deref());
+        if self.alerted {
+            info!(
+                "{} was updated, it is no longer about to expire",
+                self.client()
+            );
+        }
+        self.last_update = Instant::now();
+        self.alerted = false;
+        self.refresh_warned = false;
+    }
+
+    fn elapsed_fraction(&self) -> f64 {
+        self.last_update.elapsed().as_secs_f64() / self.trusting_period.as_secs_f64()
+    }
+
+    /// Whether latest header should be relayed regardless of relaying policy.
+    /// Never holds for clients which cannot be refreshed.
+    pub fn refresh_due(&self) -> bool {
+        self.refreshable && self.elapsed_fraction() >= self.cfg.refresh_fraction
+    }
+
+    /// Publishes time left until expiry and raises an alert, once per crossing,
+    /// when expiry is imminent.
+    pub fn check(&mut self) {
+        let remaining = self
+            .trusting_period
+            .checked_sub(self.last_update.elapsed())
+            .unwrap_or_default();
+        self.metrics
+            .observe_client_expiry(&self.chain, self.path.as_deref(), remaining);
+        if !self.refreshable
+            && !self.refresh_warned
+            && self.elapsed_fraction() >= self.cfg.refresh_fraction
+        {
+            self.refresh_warned = true;
+            warn!(
+                "{} was not updated for {:?}, it accepts only contiguous headers so it cannot be refreshed ahead of queued ones, and expires in {:?}",
+                self.client(),
+                self.last_update.elapsed(),
+                remaining
+            );
+        }
+        if self.alerted || self.elapsed_fraction() < self.cfg.alert_fraction {
+            return;
+        }
+        self.alerted = true;
+        error!(
+            "ALERT: {} was not updated for {:?} and expires in {:?}, it will have to be recreated unless a header is relayed",
+            self.client(),
+            self.last_update.elapsed(),
+            remaining
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExpiryWatchdog;
+    use crate::client_state::ClientState;
+    use crate::config::WatchdogConfig;
+    use crate::health::Health;
+    use crate::metrics::Metrics;
+    use std::time::{Duration, Instant, SystemTime};
+
+    #[test]
+    fn test_refresh_is_due_after_configured_fraction() {
+        let mut watchdog = ExpiryWatchdog::new(
+            &Health::default(),
+            &Metrics::default(),
+            "substrate",
+            None,
+            Duration::from_secs(100),
+            WatchdogConfig::default(),
+        );
+        assert!(!watchdog.refresh_due());
+        watchdog.last_update = Instant::now() - Duration::from_secs(60);
+        assert!(watchdog.refresh_due());
+        watchdog.check();
+        assert!(!watchdog.alerted);
+        watchdog.last_update = Instant::now() - Duration::from_secs(95);
+        watchdog.check();
+        assert!(watchdog.alerted);
+        watchdog.updated();
+        assert!(!watchdog.refresh_due());
+    }
+
+    #[test]
+    fn test_watchdog_is_seeded_with_latest_header_time() {
+        let mut watchdog = ExpiryWatchdog::new(
+            &Health::default(),
+            &Metrics::default(),
+            "substrate",
+            None,
+            Duration::from_secs(100),
+            WatchdogConfig::default(),
+        );
+        let mut state = ClientState {
+            client_id: "abcdefghij".to_owned(),
+            latest_height: 10,
+            latest_timestamp: None,
+            frozen_height: None,
+            stored: None,
+        };
+        watchdog.seed(&state);
+        assert!(!watchdog.refresh_due());
+        state.latest_timestamp = Some((SystemTime::now() - Duration::from_secs(60)).into());
+        watchdog.seed(&state);
+        assert!(watchdog.refresh_due());
+    }
+
+    #[test]
+    fn test_alert_only_watchdog_never_requests_refresh() {
+        let mut watchdog = ExpiryWatchdog::alert_only(
+            &Health::default(),
+            &Metrics::default(),
+            "cosmos",
+            Some("path"),
+            Duration::from_secs(100),
+            WatchdogConfig::default(),
+        );
+        watchdog.last_update = Instant::now() - Duration::from_secs(60);
+        assert!(!watchdog.refresh_due());
+        watchdog.check();
+        assert!(watchdog.refresh_warned);
+        assert!(!watchdog.alerted);
+    }
+}